@@ -1,6 +1,10 @@
 mod props;
 
-use crate::{BlockColorVariant, BlockDirection, BlockFacing, BlockProperty, SignType};
+use crate::block_entities::BlockEntity;
+use crate::items::Item;
+use crate::{
+    BlockBox, BlockColorVariant, BlockDirection, BlockFacing, BlockProperty, SignRotation, SignType,
+};
 use mchprs_proc_macros::BlockTransform;
 pub use props::*;
 use std::collections::HashMap;
@@ -18,6 +22,30 @@ pub enum RotateAmt {
     Rotate270,
 }
 
+/// What happens to a block when a piston tries to move it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PushReaction {
+    /// The block moves with the piston
+    Normal,
+    /// The block breaks and drops its item
+    Destroy,
+    /// The block can't be moved and stops the piston from extending
+    Block,
+    /// The block can be pushed but sticky pistons can't pull it
+    PushOnly,
+}
+
+/// What a comparator reads from a block next to it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComparatorSignal {
+    /// Blocks like containers replace the comparator's rear input with their own signal
+    Override(u8),
+    /// Redstone components pass their power through
+    Power(u8),
+    /// The block doesn't send a signal into comparators
+    None,
+}
+
 trait BlockTransform {
     fn rotate(&mut self, amt: crate::blocks::RotateAmt) {
         match amt {
@@ -89,6 +117,22 @@ impl BlockTransform for BlockDirection {
     }
 }
 
+impl BlockTransform for SignRotation {
+    fn flip(&mut self, dir: FlipDirection) {
+        // Mirrored across the axis that stays in place, south (0) for X and east (12) for Z
+        let mirror = match dir {
+            FlipDirection::FlipX => 16,
+            FlipDirection::FlipZ => 24,
+        };
+        self.0 = (mirror - self.0) & 15;
+    }
+
+    fn rotate90(&mut self) {
+        // A quarter turn is 4 of the 16 steps
+        self.0 = (self.0 + 4) & 15;
+    }
+}
+
 impl Block {
     pub fn has_block_entity(self) -> bool {
         matches!(
@@ -102,6 +146,252 @@ impl Block {
         )
     }
 
+    /// Returns true if both blocks are the same kind of block, regardless of their properties
+    pub fn compare_variant(self, other: Block) -> bool {
+        std::mem::discriminant(&self) == std::mem::discriminant(&other)
+    }
+
+    /// Returns true if redstone wire on the `side` of this block would connect to it.
+    /// `side` is the direction from the wire to this block.
+    pub fn can_connect_redstone(self, side: BlockDirection) -> bool {
+        match self {
+            Block::RedstoneWire { .. }
+            | Block::RedstoneComparator { .. }
+            | Block::RedstoneTorch { .. }
+            | Block::RedstoneBlock { .. }
+            | Block::RedstoneWallTorch { .. }
+            | Block::StonePressurePlate { .. }
+            | Block::TripwireHook { .. }
+            | Block::StoneButton { .. }
+            | Block::Target { .. }
+            | Block::Lever { .. } => true,
+            Block::RedstoneRepeater { repeater } => {
+                repeater.facing == side || repeater.facing == side.opposite()
+            }
+            Block::Observer { facing } => facing == side.block_facing(),
+            _ => false,
+        }
+    }
+
+    /// Returns the box that players target when they click on this block, or `None` if
+    /// the block can't be clicked on. Blocks that aren't listed here are treated as full
+    /// cubes, which never rejects a click on them.
+    pub fn interaction_box(self) -> Option<BlockBox> {
+        Some(match self {
+            Block::Air {} => return None,
+            Block::RedstoneWire { .. } => BlockBox::from_pixels((0, 0, 0), (16, 1, 16)),
+            Block::RedstoneRepeater { .. } | Block::RedstoneComparator { .. } => {
+                BlockBox::from_pixels((0, 0, 0), (16, 2, 16))
+            }
+            Block::SmoothStoneSlab { .. } | Block::QuartzSlab { .. } => {
+                BlockBox::from_pixels((0, 0, 0), (16, 8, 16))
+            }
+            Block::StonePressurePlate { .. } => BlockBox::from_pixels((1, 0, 1), (15, 1, 15)),
+            Block::RedstoneTorch { .. } => BlockBox::from_pixels((6, 0, 6), (10, 10, 10)),
+            Block::RedstoneWallTorch { facing, .. } => BlockBox {
+                min: (5.5 / 16.0, 3.0 / 16.0, 11.0 / 16.0),
+                max: (10.5 / 16.0, 13.0 / 16.0, 1.0),
+            }
+            .rotate_from_north(facing),
+            Block::Lever { lever } => {
+                let floor = BlockBox::from_pixels((5, 0, 4), (11, 6, 12));
+                match lever.face {
+                    LeverFace::Floor => floor,
+                    LeverFace::Ceiling => floor.flip_y(),
+                    LeverFace::Wall => BlockBox::from_pixels((5, 4, 10), (11, 12, 16)),
+                }
+                .rotate_from_north(lever.facing)
+            }
+            Block::StoneButton { button } => {
+                // Pressed buttons are pushed halfway in
+                let depth = if button.powered { 1 } else { 2 };
+                let floor = BlockBox::from_pixels((5, 0, 6), (11, depth, 10));
+                match button.face {
+                    ButtonFace::Floor => floor,
+                    ButtonFace::Ceiling => floor.flip_y(),
+                    ButtonFace::Wall => BlockBox::from_pixels((5, 6, 16 - depth), (11, 10, 16)),
+                }
+                .rotate_from_north(button.facing)
+            }
+            Block::Sign { .. } => BlockBox::from_pixels((4, 0, 4), (12, 16, 12)),
+            Block::WallSign { facing, .. } => BlockBox {
+                min: (0.0, 4.5 / 16.0, 14.0 / 16.0),
+                max: (1.0, 12.5 / 16.0, 1.0),
+            }
+            .rotate_from_north(facing),
+            Block::TripwireHook { direction } => {
+                BlockBox::from_pixels((5, 0, 10), (11, 10, 16)).rotate_from_north(direction)
+            }
+            Block::Cake { bites } => BlockBox::from_pixels((1 + bites * 2, 0, 1), (15, 8, 15)),
+            Block::IronTrapdoor { half, .. } => match half {
+                TrapdoorHalf::Bottom => BlockBox::from_pixels((0, 0, 0), (16, 3, 16)),
+                TrapdoorHalf::Top => BlockBox::from_pixels((0, 13, 0), (16, 16, 16)),
+            },
+            _ => BlockBox::FULL_CUBE,
+        })
+    }
+
+    /// Returns how this block reacts to being moved by a piston. Blocks with block entities
+    /// can't be moved, unless they break like comparators do.
+    pub fn push_reaction(self) -> PushReaction {
+        match self {
+            Block::RedstoneWire { .. }
+            | Block::RedstoneTorch { .. }
+            | Block::RedstoneWallTorch { .. }
+            | Block::RedstoneRepeater { .. }
+            | Block::RedstoneComparator { .. }
+            | Block::Lever { .. }
+            | Block::StoneButton { .. }
+            | Block::StonePressurePlate { .. }
+            | Block::TripwireHook { .. }
+            | Block::SeaPickle { .. }
+            | Block::Cake { .. }
+            | Block::Pumpkin {} => PushReaction::Destroy,
+            // We don't know anything about unknown blocks, so they are left where they are
+            Block::Unknown { .. } => PushReaction::Block,
+            block if block.has_block_entity() => PushReaction::Block,
+            _ => PushReaction::Normal,
+        }
+    }
+
+    /// Returns true if comparators read this block's contents, like a container's
+    /// fullness, instead of its redstone power.
+    pub fn has_comparator_override(self) -> bool {
+        // Whether a block overrides doesn't depend on the side or its block entity
+        matches!(
+            self.comparator_signal(BlockDirection::North, None),
+            ComparatorSignal::Override(_)
+        )
+    }
+
+    /// Returns what a comparator reads from this block. `side` is the direction from the
+    /// comparator to this block and `block_entity` is this block's block entity, if any.
+    pub fn comparator_signal(
+        self,
+        side: BlockDirection,
+        block_entity: Option<&BlockEntity>,
+    ) -> ComparatorSignal {
+        match self {
+            Block::Barrel { .. } | Block::Furnace { .. } | Block::Hopper { .. } => {
+                match block_entity {
+                    Some(BlockEntity::Container {
+                        comparator_override,
+                        ..
+                    }) => ComparatorSignal::Override(*comparator_override),
+                    _ => ComparatorSignal::Override(0),
+                }
+            }
+            Block::Cauldron { level } => ComparatorSignal::Override(level),
+            Block::Composter { level } => ComparatorSignal::Override(level),
+            Block::Cake { bites } => ComparatorSignal::Override(14 - 2 * bites),
+            Block::RedstoneWire { wire } => ComparatorSignal::Power(wire.power),
+            Block::RedstoneBlock {} => ComparatorSignal::Power(15),
+            // Diodes only power what they face
+            Block::RedstoneRepeater { repeater } => {
+                ComparatorSignal::Power(if repeater.facing == side && repeater.powered {
+                    15
+                } else {
+                    0
+                })
+            }
+            Block::RedstoneComparator { comparator } => {
+                ComparatorSignal::Power(match block_entity {
+                    Some(BlockEntity::Comparator { output_strength })
+                        if comparator.facing == side =>
+                    {
+                        *output_strength
+                    }
+                    _ => 0,
+                })
+            }
+            _ => ComparatorSignal::None,
+        }
+    }
+
+    /// Returns the id of the item this block drops when it is broken, if any.
+    /// Redstone components drop their item form, like dust dropping redstone.
+    pub fn dropped_item(self) -> Option<u32> {
+        let item = match self {
+            Block::Stone {} => Item::Stone {},
+            Block::Glass {} => Item::Glass {},
+            Block::Glowstone {} => Item::Glowstone {},
+            Block::Sandstone {} => Item::Sandstone {},
+            Block::RedstoneWire { .. } => Item::Redstone {},
+            Block::RedstoneTorch { .. } | Block::RedstoneWallTorch { .. } => Item::RedstoneTorch {},
+            Block::RedstoneRepeater { .. } => Item::Repeater {},
+            Block::RedstoneComparator { .. } => Item::Comparator {},
+            Block::RedstoneLamp { .. } => Item::RedstoneLamp {},
+            Block::RedstoneBlock {} => Item::RedstoneBlock {},
+            Block::Lever { .. } => Item::Lever {},
+            Block::StoneButton { .. } => Item::StoneButton {},
+            Block::StonePressurePlate { .. } => Item::StonePressurePlate {},
+            Block::TripwireHook { .. } => Item::TripwireHook {},
+            Block::Target {} => Item::Target {},
+            Block::IronTrapdoor { .. } => Item::IronTrapdoor {},
+            Block::NoteBlock { .. } => Item::NoteBlock {},
+            Block::SeaPickle { .. } => Item::SeaPickle {},
+            Block::Barrel {} => Item::Barrel {},
+            Block::Hopper {} => Item::Hopper {},
+            Block::Furnace {} => Item::Furnace {},
+            Block::SmoothStoneSlab { .. } => Item::SmoothStoneSlab {},
+            Block::QuartzSlab { .. } => Item::QuartzSlab {},
+            Block::Terracotta {} => Item::Terracotta {},
+            Block::ColoredTerracotta { color } => Item::ColoredTerracotta { color },
+            Block::Concrete { color } => Item::Concrete { color },
+            Block::StainedGlass { color } => Item::StainedGlass { color },
+            Block::Wool { color } => Item::Wool { color },
+            Block::Sign { sign_type, .. } | Block::WallSign { sign_type, .. } => Item::Sign {
+                // The item and block orders are swapped the same way in both directions
+                sign_type: SignType::from_item_type(sign_type.0).0,
+            },
+            Block::Clay {} => Item::Clay {},
+            Block::GoldBlock {} => Item::GoldBlock {},
+            Block::PackedIce {} => Item::PackedIce {},
+            Block::BoneBlock { .. } => Item::BoneBlock {},
+            Block::IronBlock {} => Item::IronBlock {},
+            Block::SoulSand {} => Item::SoulSand {},
+            Block::Pumpkin {} => Item::Pumpkin {},
+            Block::EmeraldBlock {} => Item::EmeraldBlock {},
+            Block::HayBlock { .. } => Item::HayBlock {},
+            Block::Sand {} => Item::Sand {},
+            _ => return None,
+        };
+        Some(item.get_id())
+    }
+
+    /// Parses a block state string like `repeater[delay=3,facing=west]`, as used in schematic
+    /// palettes. The `minecraft:` prefix is optional and omitted properties keep their defaults.
+    /// Returns `None` if the name is unknown or the property list is malformed.
+    pub fn from_state(state: &str) -> Option<Block> {
+        let state = state.trim_start_matches("minecraft:");
+        let (name, props) = match state.split_once('[') {
+            Some((name, props)) => (name, Some(props.strip_suffix(']')?)),
+            None => (state, None),
+        };
+        let mut block = Block::from_name(name)?;
+        if let Some(props) = props.filter(|props| !props.is_empty()) {
+            let props = props
+                .split(',')
+                .map(|prop| prop.split_once('='))
+                .collect::<Option<HashMap<_, _>>>()?;
+            block.set_properties(props);
+        }
+        Some(block)
+    }
+
+    /// Returns the block state's properties as name and value pairs, sorted by name.
+    /// These are the same strings `from_state` accepts.
+    pub fn get_state_properties(&self) -> Vec<(&'static str, String)> {
+        let mut props: Vec<_> = self.properties().into_iter().collect();
+        props.sort_unstable_by_key(|(name, _)| *name);
+        props
+    }
+
+    /// Returns true if this is a water source block, which waterloggable blocks absorb when placed in it
+    pub fn is_water_source(self) -> bool {
+        self.get_id() == 34
+    }
+
     pub fn can_place_block_in(self) -> bool {
         matches!(self.get_id(),
             0             // Air
@@ -119,6 +409,95 @@ impl Block {
     }
 }
 
+#[test]
+fn push_reaction_test() {
+    let dust = Block::RedstoneWire {
+        wire: RedstoneWire::default(),
+    };
+    assert_eq!(dust.push_reaction(), PushReaction::Destroy);
+    assert_eq!(Block::Stone {}.push_reaction(), PushReaction::Normal);
+    assert_eq!(
+        Block::RedstoneBlock {}.push_reaction(),
+        PushReaction::Normal
+    );
+    assert_eq!(Block::Barrel {}.push_reaction(), PushReaction::Block);
+}
+
+#[test]
+fn comparator_signal_test() {
+    use crate::block_entities::ContainerType;
+
+    let barrel = BlockEntity::Container {
+        comparator_override: 7,
+        inventory: Vec::new(),
+        ty: ContainerType::Barrel,
+    };
+    let signal =
+        |block: Block, block_entity| block.comparator_signal(BlockDirection::North, block_entity);
+
+    assert!(Block::Barrel {}.has_comparator_override());
+    assert!(Block::Cake { bites: 6 }.has_comparator_override());
+    assert!(!Block::Stone {}.has_comparator_override());
+    // Redstone components pass their power through instead
+    assert!(!Block::RedstoneBlock {}.has_comparator_override());
+    assert_eq!(
+        signal(Block::Barrel {}, Some(&barrel)),
+        ComparatorSignal::Override(7)
+    );
+    assert_eq!(
+        signal(Block::Cauldron { level: 2 }, None),
+        ComparatorSignal::Override(2)
+    );
+    assert_eq!(
+        signal(Block::Composter { level: 5 }, None),
+        ComparatorSignal::Override(5)
+    );
+    assert_eq!(
+        signal(Block::Cake { bites: 3 }, None),
+        ComparatorSignal::Override(8)
+    );
+
+    let wire = RedstoneWire {
+        power: 9,
+        ..Default::default()
+    };
+    assert_eq!(
+        signal(Block::RedstoneWire { wire }, None),
+        ComparatorSignal::Power(9)
+    );
+    assert_eq!(
+        signal(Block::RedstoneBlock {}, None),
+        ComparatorSignal::Power(15)
+    );
+
+    // Diodes only count if they face the comparator
+    let facing = RedstoneRepeater::new(1, BlockDirection::North, false, true);
+    let facing_away = RedstoneRepeater::new(1, BlockDirection::South, false, true);
+    assert_eq!(
+        signal(Block::RedstoneRepeater { repeater: facing }, None),
+        ComparatorSignal::Power(15)
+    );
+    assert_eq!(
+        signal(
+            Block::RedstoneRepeater {
+                repeater: facing_away
+            },
+            None
+        ),
+        ComparatorSignal::Power(0)
+    );
+    let comparator = RedstoneComparator::new(BlockDirection::North, ComparatorMode::Compare, true);
+    let output = BlockEntity::Comparator {
+        output_strength: 11,
+    };
+    assert_eq!(
+        signal(Block::RedstoneComparator { comparator }, Some(&output)),
+        ComparatorSignal::Power(11)
+    );
+
+    assert_eq!(signal(Block::Stone {}, None), ComparatorSignal::None);
+}
+
 #[test]
 fn repeater_id_test() {
     let original = Block::RedstoneRepeater {
@@ -130,6 +509,68 @@ fn repeater_id_test() {
     assert_eq!(new, original);
 }
 
+#[test]
+fn block_state_parse_test() {
+    let parsed = Block::from_state("repeater[delay=3,facing=west,locked=true,powered=false]");
+    let expected = Block::RedstoneRepeater {
+        repeater: RedstoneRepeater::new(3, BlockDirection::West, true, false),
+    };
+    assert_eq!(parsed, Some(expected));
+    assert_eq!(
+        Block::from_state("minecraft:repeater[delay=3]"),
+        Some(Block::RedstoneRepeater {
+            repeater: RedstoneRepeater::new(3, BlockDirection::West, false, false),
+        })
+    );
+    assert_eq!(Block::from_state("stone"), Some(Block::Stone {}));
+    assert_eq!(Block::from_state("repeater[delay]"), None);
+    assert_eq!(Block::from_state("repeater[delay=3"), None);
+    assert_eq!(Block::from_state("not_a_block"), None);
+}
+
+#[test]
+fn waterlogged_slab_id_test() {
+    let original = Block::SmoothStoneSlab { waterlogged: true };
+    let id = original.get_id();
+    assert_eq!(id, 8592);
+    assert_eq!(Block::from_id(id), original);
+
+    let dry = Block::QuartzSlab { waterlogged: false };
+    assert_eq!(dry.get_id(), 8641);
+    assert_eq!(Block::from_id(8641), dry);
+}
+
+#[test]
+fn can_connect_redstone_test() {
+    let repeater = Block::RedstoneRepeater {
+        repeater: RedstoneRepeater::new(1, BlockDirection::North, false, false),
+    };
+    assert!(repeater.can_connect_redstone(BlockDirection::North));
+    assert!(repeater.can_connect_redstone(BlockDirection::South));
+    assert!(!repeater.can_connect_redstone(BlockDirection::East));
+    assert!(!repeater.can_connect_redstone(BlockDirection::West));
+
+    let lever = Block::Lever {
+        lever: Lever::new(LeverFace::Floor, BlockDirection::North, false),
+    };
+    for side in [
+        BlockDirection::North,
+        BlockDirection::South,
+        BlockDirection::East,
+        BlockDirection::West,
+    ] {
+        assert!(lever.can_connect_redstone(side));
+    }
+}
+
+#[test]
+fn compare_variant_test() {
+    let lit = Block::RedstoneLamp { lit: true };
+    let unlit = Block::RedstoneLamp { lit: false };
+    assert!(lit.compare_variant(unlit));
+    assert!(!lit.compare_variant(Block::Stone {}));
+}
+
 #[test]
 fn comparator_id_test() {
     let original = Block::RedstoneComparator {
@@ -141,6 +582,172 @@ fn comparator_id_test() {
     assert_eq!(new, original);
 }
 
+#[test]
+fn block_transform_test() {
+    let mut repeater = Block::RedstoneRepeater {
+        repeater: RedstoneRepeater::new(2, BlockDirection::West, false, true),
+    };
+    repeater.rotate(RotateAmt::Rotate90);
+    assert_eq!(
+        repeater,
+        Block::RedstoneRepeater {
+            repeater: RedstoneRepeater::new(2, BlockDirection::North, false, true),
+        }
+    );
+
+    let mut repeater = Block::RedstoneRepeater {
+        repeater: RedstoneRepeater::new(2, BlockDirection::West, false, true),
+    };
+    repeater.flip(FlipDirection::FlipX);
+    assert_eq!(
+        repeater,
+        Block::RedstoneRepeater {
+            repeater: RedstoneRepeater::new(2, BlockDirection::East, false, true),
+        }
+    );
+
+    let mut torch = Block::RedstoneWallTorch {
+        lit: true,
+        facing: BlockDirection::North,
+    };
+    torch.flip(FlipDirection::FlipZ);
+    assert_eq!(
+        torch,
+        Block::RedstoneWallTorch {
+            lit: true,
+            facing: BlockDirection::South,
+        }
+    );
+
+    // A sign facing west ends up facing north, then gets mirrored to face south
+    let mut sign = Block::Sign {
+        sign_type: SignType(0),
+        rotation: SignRotation(4),
+    };
+    sign.rotate(RotateAmt::Rotate90);
+    assert_eq!(
+        sign,
+        Block::Sign {
+            sign_type: SignType(0),
+            rotation: SignRotation(8),
+        }
+    );
+    sign.flip(FlipDirection::FlipZ);
+    assert_eq!(
+        sign,
+        Block::Sign {
+            sign_type: SignType(0),
+            rotation: SignRotation(0),
+        }
+    );
+}
+
+#[test]
+fn pillar_axis_id_test() {
+    let hay = Block::HayBlock { axis: BlockAxis::X };
+    assert_eq!(hay.get_id(), 8113);
+    assert_eq!(Block::from_id(8115), Block::HayBlock { axis: BlockAxis::Z });
+    assert_eq!(
+        Block::from_state("bone_block[axis=z]"),
+        Some(Block::BoneBlock { axis: BlockAxis::Z })
+    );
+    let mut bone = Block::BoneBlock { axis: BlockAxis::X };
+    bone.rotate(RotateAmt::Rotate90);
+    assert_eq!(bone, Block::BoneBlock { axis: BlockAxis::Z });
+}
+
+#[test]
+fn comparator_state_properties_test() {
+    let comparator = Block::RedstoneComparator {
+        comparator: RedstoneComparator::new(BlockDirection::West, ComparatorMode::Subtract, true),
+    };
+    assert_eq!(
+        comparator.get_state_properties(),
+        vec![
+            ("facing", "west".to_string()),
+            ("mode", "subtract".to_string()),
+            ("powered", "true".to_string()),
+        ]
+    );
+    assert!(Block::Stone {}.get_state_properties().is_empty());
+}
+
+#[test]
+fn dropped_item_test() {
+    let repeater = Block::RedstoneRepeater {
+        repeater: RedstoneRepeater::new(2, BlockDirection::North, false, true),
+    };
+    assert_eq!(repeater.dropped_item(), Some(Item::Repeater {}.get_id()));
+    let wire = Block::RedstoneWire {
+        wire: RedstoneWire::default(),
+    };
+    assert_eq!(wire.dropped_item(), Some(Item::Redstone {}.get_id()));
+    assert_eq!(Block::Air {}.dropped_item(), None);
+}
+
+#[test]
+fn interaction_box_test() {
+    let repeater = Block::RedstoneRepeater {
+        repeater: RedstoneRepeater::default(),
+    };
+    let repeater_box = repeater.interaction_box().unwrap();
+    assert_eq!(repeater_box.max.1, 2.0 / 16.0);
+    assert!(!repeater_box.is_full_cube());
+    assert!(repeater_box.contains(0.5, 0.1, 0.5));
+    assert!(!repeater_box.contains(0.5, 0.5, 0.5));
+
+    assert_eq!(Block::Stone {}.interaction_box(), Some(BlockBox::FULL_CUBE));
+    assert_eq!(Block::Air {}.interaction_box(), None);
+
+    // Wall mounted blocks hug the wall they're attached to, opposite their facing
+    let torch = Block::RedstoneWallTorch {
+        lit: true,
+        facing: BlockDirection::East,
+    };
+    let torch_box = torch.interaction_box().unwrap();
+    assert!(torch_box.contains(0.1, 0.5, 0.5));
+    assert!(!torch_box.contains(0.9, 0.5, 0.5));
+
+    let lever = Block::Lever {
+        lever: Lever::new(LeverFace::Floor, BlockDirection::West, false),
+    };
+    assert_eq!(
+        lever.interaction_box(),
+        Some(BlockBox::from_pixels((4, 0, 5), (12, 6, 11)))
+    );
+    let lever = Block::Lever {
+        lever: Lever::new(LeverFace::Ceiling, BlockDirection::North, false),
+    };
+    assert_eq!(
+        lever.interaction_box(),
+        Some(BlockBox::from_pixels((5, 10, 4), (11, 16, 12)))
+    );
+
+    let button = Block::StoneButton {
+        button: StoneButton::new(ButtonFace::Wall, BlockDirection::South, true),
+    };
+    assert_eq!(
+        button.interaction_box(),
+        Some(BlockBox::from_pixels((5, 6, 0), (11, 10, 1)))
+    );
+
+    let sign = Block::WallSign {
+        sign_type: SignType(0),
+        facing: BlockDirection::North,
+    };
+    let sign_box = sign.interaction_box().unwrap();
+    assert!(sign_box.contains(0.5, 0.5, 0.95));
+    assert!(!sign_box.contains(0.5, 0.5, 0.5));
+
+    let hook = Block::TripwireHook {
+        direction: BlockDirection::South,
+    };
+    assert_eq!(
+        hook.interaction_box(),
+        Some(BlockBox::from_pixels((5, 0, 0), (11, 10, 6)))
+    );
+}
+
 macro_rules! blocks {
     (
         $(
@@ -532,9 +1139,9 @@ blocks! {
     Sign {
         props: {
             sign_type: SignType,
-            rotation: u32
+            rotation: SignRotation
         },
-        get_id: 1 + (sign_type.0 << 5) + (rotation << 1) + match sign_type.0 {
+        get_id: 1 + (sign_type.0 << 5) + (rotation.0 << 1) + match sign_type.0 {
             0..=5 => 3438,
             6..=7 => 15909 - (6 << 5),
             _ => unreachable!(),
@@ -546,44 +1153,44 @@ blocks! {
                 15909..=15972 => ((id - 15909) >> 5) + 6,
                 _ => unreachable!(),
             }),
-            rotation: (match id {
+            rotation: SignRotation((match id {
                 3438..=3629 => id - 3438,
                 15909..=15972 => id - 15909,
                 _ => unreachable!(),
-            } & 0b11110) >> 1
+            } & 0b11110) >> 1)
         },
         from_names(_name): {
             "oak_sign" => {
                 sign_type: SignType(0),
-                rotation: 0
+                rotation: SignRotation(0)
             },
             "spruce_sign" => {
                 sign_type: SignType(1),
-                rotation: 0
+                rotation: SignRotation(0)
             },
             "birch_sign" => {
                 sign_type: SignType(2),
-                rotation: 0
+                rotation: SignRotation(0)
             },
             "acacia_sign" => {
                 sign_type: SignType(3),
-                rotation: 0
+                rotation: SignRotation(0)
             },
             "jungle_sign" => {
                 sign_type: SignType(4),
-                rotation: 0
+                rotation: SignRotation(0)
             },
             "dark_oak_sign" => {
                 sign_type: SignType(5),
-                rotation: 0
+                rotation: SignRotation(0)
             },
             "crimson_sign" => {
                 sign_type: SignType(6),
-                rotation: 0
+                rotation: SignRotation(0)
             },
             "warped_sign" => {
                 sign_type: SignType(7),
-                rotation: 0
+                rotation: SignRotation(0)
             }
         },
         get_name: match sign_type.0 {
@@ -853,6 +1460,17 @@ blocks! {
         solid: true,
         cube: true,
     },
+    StoneBricks {
+        props: {},
+        get_id: 4564,
+        from_id(_id): 4564 => {},
+        from_names(_name): {
+            "stone_bricks" => {}
+        },
+        get_name: "stone_bricks",
+        solid: true,
+        cube: true,
+    },
     CoalBlock {
         props: {},
         get_id: 8133,
@@ -898,22 +1516,44 @@ blocks! {
         cube: true,
     },
     SmoothStoneSlab {
-        props: {},
-        get_id: 8593,
-        from_id(_id): 8593 => {},
+        props: {
+            waterlogged: bool
+        },
+        get_id: if waterlogged {
+            8592
+        } else {
+            8593
+        },
+        from_id_offset: 8592,
+        from_id(id): 8592..=8593 => {
+            waterlogged: id == 0
+        },
         from_names(_name): {
-            "smooth_stone_slab" => {}
+            "smooth_stone_slab" => {
+                waterlogged: false
+            }
         },
         get_name: "smooth_stone_slab[type=top]",
         transparent: true,
         cube: true,
     },
     QuartzSlab {
-        props: {},
-        get_id: 8641,
-        from_id(_id): 8641 => {},
+        props: {
+            waterlogged: bool
+        },
+        get_id: if waterlogged {
+            8640
+        } else {
+            8641
+        },
+        from_id_offset: 8640,
+        from_id(id): 8640..=8641 => {
+            waterlogged: id == 0
+        },
         from_names(_name): {
-            "quartz_slab" => {}
+            "quartz_slab" => {
+                waterlogged: false
+            }
         },
         get_name: "quartz_slab",
         transparent: true,
@@ -1255,11 +1895,18 @@ blocks! {
         cube: true,
     },
     BoneBlock {
-        props: {},
-        get_id: 9507,
-        from_id(_id): 9506..=9508 => {},
+        props: {
+            axis: BlockAxis
+        },
+        get_id: axis.get_id() + 9506,
+        from_id_offset: 9506,
+        from_id(id): 9506..=9508 => {
+            axis: BlockAxis::from_id(id)
+        },
         from_names(_name): {
-            "bone_block" => {}
+            "bone_block" => {
+                axis: Default::default()
+            }
         },
         get_name: "bone_block",
         solid: true,
@@ -1310,11 +1957,18 @@ blocks! {
         cube: true,
     },
     HayBlock {
-        props: {},
-        get_id: 8114,
-        from_id(_id): 8113..=8115 => {},
+        props: {
+            axis: BlockAxis
+        },
+        get_id: axis.get_id() + 8113,
+        from_id_offset: 8113,
+        from_id(id): 8113..=8115 => {
+            axis: BlockAxis::from_id(id)
+        },
         from_names(_name): {
-            "hay_block" => {}
+            "hay_block" => {
+                axis: Default::default()
+            }
         },
         get_name: "hay_block",
         solid: true,
@@ -1,4 +1,5 @@
 use super::{Block, BlockDirection, BlockProperty, BlockTransform, FlipDirection};
+use crate::BlockFace;
 use std::str::FromStr;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, BlockProperty, BlockTransform)]
@@ -391,6 +392,75 @@ impl FromStr for TrapdoorHalf {
     }
 }
 
+/// The axis pillar-like blocks such as bone blocks and hay bales are lined up along
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BlockAxis {
+    X,
+    #[default]
+    Y,
+    Z,
+}
+
+impl BlockAxis {
+    pub fn get_id(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_id(id: u32) -> BlockAxis {
+        use BlockAxis::*;
+        match id {
+            0 => X,
+            1 => Y,
+            2 => Z,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the axis a pillar placed against `face` lines up along
+    pub fn from_face(face: BlockFace) -> BlockAxis {
+        match face {
+            BlockFace::Top | BlockFace::Bottom => BlockAxis::Y,
+            BlockFace::East | BlockFace::West => BlockAxis::X,
+            BlockFace::North | BlockFace::South => BlockAxis::Z,
+        }
+    }
+}
+
+impl BlockTransform for BlockAxis {
+    fn rotate90(&mut self) {
+        *self = match self {
+            BlockAxis::X => BlockAxis::Z,
+            BlockAxis::Y => BlockAxis::Y,
+            BlockAxis::Z => BlockAxis::X,
+        }
+    }
+
+    fn flip(&mut self, _dir: FlipDirection) {}
+}
+
+impl ToString for BlockAxis {
+    fn to_string(&self) -> String {
+        match self {
+            BlockAxis::X => "x".to_owned(),
+            BlockAxis::Y => "y".to_owned(),
+            BlockAxis::Z => "z".to_owned(),
+        }
+    }
+}
+
+impl FromStr for BlockAxis {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "x" => BlockAxis::X,
+            "y" => BlockAxis::Y,
+            "z" => BlockAxis::Z,
+            _ => return Err(()),
+        })
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Instrument {
     Harp,
@@ -461,12 +531,12 @@ impl Instrument {
             Block::GoldBlock {} => Instrument::Bell,
             Block::Wool { .. } => Instrument::Guitar,
             Block::PackedIce {} => Instrument::Chime,
-            Block::BoneBlock {} => Instrument::Xylophone,
+            Block::BoneBlock { .. } => Instrument::Xylophone,
             Block::IronBlock {} => Instrument::IronXylophone,
             Block::SoulSand {} => Instrument::CowBell,
             Block::Pumpkin {} => Instrument::Didgeridoo,
             Block::EmeraldBlock {} => Instrument::Bit,
-            Block::HayBlock {} => Instrument::Banjo,
+            Block::HayBlock { .. } => Instrument::Banjo,
             Block::Glowstone { .. } => Instrument::Pling,
             _ => Instrument::Harp,
         }
@@ -166,6 +166,12 @@ items! {
         from_id(_id): 782 => {},
         max_stack: 1,
     },
+    DebugStick {
+        props: {},
+        get_id: 1014,
+        from_id(_id): 1014 => {},
+        max_stack: 1,
+    },
     Stone {
         props: {},
         get_id: 1,
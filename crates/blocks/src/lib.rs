@@ -7,6 +7,19 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::str::FromStr;
 
+/// The position of a block in the world. It is `Copy`, so pass it around by value:
+///
+/// ```
+/// use mchprs_blocks::{BlockFace, BlockPos};
+///
+/// let mut pos = BlockPos::new(0, 0, 0);
+/// for _ in 0..3 {
+///     let next = pos.offset(BlockFace::East);
+///     assert_eq!(next.x, pos.x + 1);
+///     pos = next;
+/// }
+/// assert_eq!(pos, BlockPos::new(3, 0, 0));
+/// ```
 #[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize, Hash)]
 pub struct BlockPos {
     pub x: i32,
@@ -49,6 +62,32 @@ impl BlockPos {
             z: std::cmp::min(self.z, other.z),
         }
     }
+
+    /// Iterates over every position in the box with corners `first` and `second`, inclusive.
+    /// Positions are yielded with x changing fastest, then z, then y, matching the order
+    /// blocks are laid out in chunk sections.
+    pub fn cuboid(first: BlockPos, second: BlockPos) -> impl Iterator<Item = BlockPos> {
+        let min = first.min(second);
+        let max = first.max(second);
+        (min.y..=max.y).flat_map(move |y| {
+            (min.z..=max.z).flat_map(move |z| (min.x..=max.x).map(move |x| BlockPos::new(x, y, z)))
+        })
+    }
+}
+
+#[test]
+fn cuboid_yields_every_position_in_order() {
+    let positions: Vec<_> =
+        BlockPos::cuboid(BlockPos::new(2, 5, -1), BlockPos::new(0, 4, 1)).collect();
+    assert_eq!(positions.len(), 3 * 2 * 3);
+    assert_eq!(positions[0], BlockPos::new(0, 4, -1));
+    assert_eq!(positions[1], BlockPos::new(1, 4, -1));
+    assert_eq!(positions[3], BlockPos::new(0, 4, 0));
+    assert_eq!(positions[9], BlockPos::new(0, 5, -1));
+    assert_eq!(positions[17], BlockPos::new(2, 5, 1));
+
+    let single: Vec<_> = BlockPos::cuboid(BlockPos::zero(), BlockPos::zero()).collect();
+    assert_eq!(single, [BlockPos::zero()]);
 }
 
 impl std::ops::Sub for BlockPos {
@@ -93,6 +132,91 @@ impl std::fmt::Display for BlockPos {
     }
 }
 
+/// An axis aligned box inside of a single block. Coordinates are relative to the
+/// block's lowest corner, so a full cube goes from `(0, 0, 0)` to `(1, 1, 1)`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BlockBox {
+    pub min: (f32, f32, f32),
+    pub max: (f32, f32, f32),
+}
+
+impl BlockBox {
+    pub const FULL_CUBE: BlockBox = BlockBox {
+        min: (0.0, 0.0, 0.0),
+        max: (1.0, 1.0, 1.0),
+    };
+
+    /// Creates a box from coordinates measured in pixels (sixteenths of a block),
+    /// which is how vanilla defines block shapes
+    pub fn from_pixels(min: (u8, u8, u8), max: (u8, u8, u8)) -> BlockBox {
+        BlockBox {
+            min: (
+                min.0 as f32 / 16.0,
+                min.1 as f32 / 16.0,
+                min.2 as f32 / 16.0,
+            ),
+            max: (
+                max.0 as f32 / 16.0,
+                max.1 as f32 / 16.0,
+                max.2 as f32 / 16.0,
+            ),
+        }
+    }
+
+    pub fn is_full_cube(self) -> bool {
+        self == BlockBox::FULL_CUBE
+    }
+
+    /// Rotates a box around the center of the block. The box is given as if it was facing
+    /// north, which is how vanilla describes the shapes of wall mounted blocks.
+    pub fn rotate_from_north(self, facing: BlockDirection) -> BlockBox {
+        let rotate = |(x, y, z): (f32, f32, f32)| match facing {
+            BlockDirection::North => (x, y, z),
+            BlockDirection::South => (1.0 - x, y, 1.0 - z),
+            BlockDirection::East => (1.0 - z, y, x),
+            BlockDirection::West => (z, y, 1.0 - x),
+        };
+        BlockBox::from_corners(rotate(self.min), rotate(self.max))
+    }
+
+    /// Flips a box upside down, turning the shape of a block on the floor into the shape of
+    /// the same block hanging from the ceiling
+    pub fn flip_y(self) -> BlockBox {
+        let flip = |(x, y, z): (f32, f32, f32)| (x, 1.0 - y, z);
+        BlockBox::from_corners(flip(self.min), flip(self.max))
+    }
+
+    /// Returns the box grown by `amount` in every direction
+    pub fn grow(self, amount: f32) -> BlockBox {
+        BlockBox {
+            min: (
+                self.min.0 - amount,
+                self.min.1 - amount,
+                self.min.2 - amount,
+            ),
+            max: (
+                self.max.0 + amount,
+                self.max.1 + amount,
+                self.max.2 + amount,
+            ),
+        }
+    }
+
+    fn from_corners(a: (f32, f32, f32), b: (f32, f32, f32)) -> BlockBox {
+        BlockBox {
+            min: (a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)),
+            max: (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2)),
+        }
+    }
+
+    /// Returns true if the point, relative to the block's lowest corner, is inside the box
+    pub fn contains(self, x: f32, y: f32, z: f32) -> bool {
+        (self.min.0..=self.max.0).contains(&x)
+            && (self.min.1..=self.max.1).contains(&y)
+            && (self.min.2..=self.max.2).contains(&z)
+    }
+}
+
 pub trait BlockProperty: Sized {
     fn encode(self, props: &mut HashMap<&'static str, String>, name: &'static str);
     fn decode(&mut self, props: &HashMap<&str, &str>, name: &str);
@@ -295,8 +419,8 @@ impl BlockDirection {
         }
     }
 
-    pub fn from_rotation(rotation: u32) -> Option<BlockDirection> {
-        match rotation {
+    pub fn from_rotation(rotation: SignRotation) -> Option<BlockDirection> {
+        match rotation.0 {
             0 => Some(BlockDirection::South),
             4 => Some(BlockDirection::West),
             8 => Some(BlockDirection::North),
@@ -454,3 +578,17 @@ impl BlockProperty for SignType {
     fn encode(self, _props: &mut HashMap<&'static str, String>, _name: &'static str) {}
     fn decode(&mut self, _props: &HashMap<&str, &str>, _name: &str) {}
 }
+
+/// The direction a standing sign faces, as one of 16 steps counted clockwise from south
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SignRotation(pub u32);
+
+impl BlockProperty for SignRotation {
+    fn encode(self, props: &mut HashMap<&'static str, String>, name: &'static str) {
+        self.0.encode(props, name);
+    }
+
+    fn decode(&mut self, props: &HashMap<&str, &str>, name: &str) {
+        self.0.decode(props, name);
+    }
+}
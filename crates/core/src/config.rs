@@ -1,12 +1,21 @@
 use crate::permissions::PermissionsConfig;
+use crate::player::Gamemode;
+use mchprs_blocks::blocks::Block;
+use mchprs_network::{NetworkConfig, PacketLog};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
+use std::time::Duration;
 use toml_edit::{value, Document};
 
+#[cfg(not(test))]
 pub static CONFIG: Lazy<ServerConfig> = Lazy::new(|| ServerConfig::load("Config.toml"));
 
+// Tests run with the default config and shouldn't create a Config.toml
+#[cfg(test)]
+pub static CONFIG: Lazy<ServerConfig> = Lazy::new(|| ServerConfig::parse("").0);
+
 trait ConfigSerializeDefault {
     fn fix_config(self, name: &str, doc: &mut Document);
 }
@@ -43,8 +52,9 @@ macro_rules! gen_config {
         }
 
         impl ServerConfig {
-            fn load(config_file: &str) -> ServerConfig {
-                let str = fs::read_to_string("Config.toml").unwrap_or_default();
+            /// Parses a config file, filling in the default for every missing field.
+            /// Also returns the config with the defaults added, so it can be written back.
+            fn parse(str: &str) -> (ServerConfig, String) {
                 let mut doc = str.parse::<Document>().unwrap();
 
                 $(
@@ -52,12 +62,21 @@ macro_rules! gen_config {
                 )*
 
                 let patched = doc.to_string();
+                (toml::from_str(&patched).unwrap(), patched)
+            }
+
+            #[cfg(not(test))]
+            fn load(config_file: &str) -> ServerConfig {
+                let str = fs::read_to_string(config_file).unwrap_or_default();
+                let (config, patched) = ServerConfig::parse(&str);
+                if let Err(err) = config.validate() {
+                    panic!("Invalid config in {}: {}", config_file, err);
+                }
                 if str != patched {
                     let mut file = fs::OpenOptions::new().create(true).write(true).open(&config_file).unwrap();
                     write!(file, "{}", patched).unwrap();
                 }
-
-                toml::from_str(&patched).unwrap()
+                config
             }
         }
     };
@@ -74,5 +93,189 @@ gen_config! {
     schemati: bool = false,
     luckperms: Option<PermissionsConfig> = None,
     block_in_hitbox: bool = true,
-    auto_redpiler: bool = true
+    auto_redpiler: bool = true,
+    max_packet_length: i64 = 2097151,
+    max_pending_connections: i64 = 64,
+    login_timeout: i64 = 30,
+    watchdog_timeout: i64 = 60,
+    compression_threshold: i64 = 256,
+    log_packets: bool = false,
+    log_packet_payloads: bool = false,
+    default_rtps: i64 = 10,
+    tick_update_limit: i64 = 1000000,
+    clock_warning_threshold: i64 = 200000,
+    freeze_on_load: bool = true,
+    default_gamemode: String = "creative".to_string(),
+    plot_floor_block: String = "sandstone".to_string(),
+    plot_border_block: String = "stone_bricks".to_string(),
+    allow_flight: bool = true,
+    rng_seed: Option<i64> = None
+}
+
+impl ServerConfig {
+    /// Checks the values that can be parsed but don't make sense
+    fn validate(&self) -> Result<(), String> {
+        if self.tick_update_limit <= 0 {
+            return Err(format!(
+                "tick_update_limit must be greater than 0, but it is {}",
+                self.tick_update_limit
+            ));
+        }
+        self.network_config()?;
+        self.default_gamemode()?;
+        self.plot_blocks()?;
+        Ok(())
+    }
+
+    /// The gamemode given to players joining for the first time
+    pub fn default_gamemode(&self) -> Result<Gamemode, String> {
+        Gamemode::from_name(&self.default_gamemode).ok_or_else(|| {
+            format!(
+                "default_gamemode must be creative or spectator, but it is {}",
+                self.default_gamemode
+            )
+        })
+    }
+
+    /// The `(floor, border)` blocks used when generating a fresh plot
+    pub fn plot_blocks(&self) -> Result<(Block, Block), String> {
+        Ok((
+            parse_block("plot_floor_block", &self.plot_floor_block)?,
+            parse_block("plot_border_block", &self.plot_border_block)?,
+        ))
+    }
+
+    pub fn network_config(&self) -> Result<NetworkConfig, String> {
+        Ok(NetworkConfig {
+            bind_address: self.bind_address.clone(),
+            max_packet_length: convert("max_packet_length", self.max_packet_length)?,
+            max_pending_connections: convert(
+                "max_pending_connections",
+                self.max_pending_connections,
+            )?,
+            login_timeout: Duration::from_secs(convert("login_timeout", self.login_timeout)?),
+            // Like vanilla, a negative threshold disables compression
+            compression_threshold: (self.compression_threshold >= 0)
+                .then(|| convert("compression_threshold", self.compression_threshold))
+                .transpose()?,
+            packet_log: match (self.log_packets, self.log_packet_payloads) {
+                (false, _) => PacketLog::Off,
+                (true, false) => PacketLog::Headers,
+                (true, true) => PacketLog::Payloads,
+            },
+        })
+    }
+}
+
+/// Parses a block state like `stone_bricks` from the config
+fn parse_block(name: &str, state: &str) -> Result<Block, String> {
+    Block::from_state(state).ok_or_else(|| format!("{} is not a known block: {}", name, state))
+}
+
+/// Converts an integer from the config to the type it is used as
+fn convert<T: TryFrom<i64>>(name: &str, value: i64) -> Result<T, String> {
+    value
+        .try_into()
+        .map_err(|_| format!("{} is out of range: {}", name, value))
+}
+
+#[test]
+fn sample_config_populates_fields() {
+    let sample = r#"
+bind_address = "127.0.0.1:25566"
+motd = "Testing"
+max_players = 10
+view_distance = 4
+default_gamemode = "spectator"
+default_rtps = 20
+compression_threshold = 512
+"#;
+    let (config, patched) = ServerConfig::parse(sample);
+    assert_eq!(config.motd, "Testing");
+    assert_eq!(config.max_players, 10);
+    assert_eq!(config.view_distance, 4);
+    assert_eq!(config.default_gamemode, "spectator");
+    assert_eq!(config.default_rtps, 20);
+    // Missing fields are filled in with their defaults
+    assert_eq!(config.login_timeout, 30);
+    assert!(patched.contains("login_timeout = 30"));
+
+    let network = config.network_config().unwrap();
+    assert_eq!(network.bind_address, "127.0.0.1:25566");
+    assert_eq!(network.compression_threshold, Some(512));
+    assert_eq!(network.login_timeout, Duration::from_secs(30));
+}
+
+#[test]
+fn negative_compression_threshold_disables_compression() {
+    let (config, _) = ServerConfig::parse("compression_threshold = -1");
+    assert_eq!(config.network_config().unwrap().compression_threshold, None);
+    let (config, _) = ServerConfig::parse("compression_threshold = 0");
+    assert_eq!(
+        config.network_config().unwrap().compression_threshold,
+        Some(0)
+    );
+}
+
+#[test]
+fn tick_update_limit_must_be_positive() {
+    let (config, _) = ServerConfig::parse("");
+    assert!(config.validate().is_ok());
+    for limit in [0, -5] {
+        let (config, _) = ServerConfig::parse(&format!("tick_update_limit = {}", limit));
+        assert!(config.validate().is_err());
+    }
+}
+
+#[test]
+fn out_of_range_network_config_rejected() {
+    for field in [
+        "max_packet_length",
+        "max_pending_connections",
+        "login_timeout",
+    ] {
+        let (config, _) = ServerConfig::parse(&format!("{} = -1", field));
+        let err = config.network_config().unwrap_err();
+        assert!(err.contains(field), "unexpected error: {}", err);
+        assert!(config.validate().is_err());
+    }
+}
+
+#[test]
+fn log_packets_selects_packet_log() {
+    let (config, _) = ServerConfig::parse("");
+    assert_eq!(config.network_config().unwrap().packet_log, PacketLog::Off);
+    let (config, _) = ServerConfig::parse("log_packets = true");
+    assert_eq!(
+        config.network_config().unwrap().packet_log,
+        PacketLog::Headers
+    );
+    let (config, _) = ServerConfig::parse("log_packets = true\nlog_packet_payloads = true");
+    assert_eq!(
+        config.network_config().unwrap().packet_log,
+        PacketLog::Payloads
+    );
+    // Payloads are only dumped while packets are being logged
+    let (config, _) = ServerConfig::parse("log_packet_payloads = true");
+    assert_eq!(config.network_config().unwrap().packet_log, PacketLog::Off);
+}
+
+#[test]
+fn unknown_gamemode_and_plot_blocks_rejected() {
+    let (config, _) = ServerConfig::parse("");
+    assert!(matches!(config.default_gamemode(), Ok(Gamemode::Creative)));
+    assert_eq!(
+        config.plot_blocks(),
+        Ok((Block::Sandstone {}, Block::StoneBricks {}))
+    );
+
+    for (field, value) in [
+        ("default_gamemode", "survival"),
+        ("plot_floor_block", "not_a_block"),
+        ("plot_border_block", "not_a_block"),
+    ] {
+        let (config, _) = ServerConfig::parse(&format!("{} = \"{}\"", field, value));
+        let err = config.validate().unwrap_err();
+        assert!(err.contains(field), "unexpected error: {}", err);
+    }
 }
@@ -5,13 +5,20 @@ use crate::plot::PLOT_BLOCK_HEIGHT;
 use crate::redstone;
 use crate::redstone::noteblock;
 use crate::world::World;
-use mchprs_blocks::block_entities::BlockEntity;
+use mchprs_blocks::block_entities::{BlockEntity, SignBlockEntity};
 use mchprs_blocks::blocks::*;
 use mchprs_blocks::items::{Item, ItemStack};
-use mchprs_blocks::{BlockFace, BlockPos, SignType};
+use mchprs_blocks::{BlockFace, BlockPos, SignRotation, SignType};
 use mchprs_network::packets::clientbound::{COpenSignEditor, ClientBoundPacket};
 use mchprs_world::TickPriority;
 
+/// Returns true if clicking a block should use it, like changing a repeater's delay.
+/// Sneaking while holding something in either hand places it against the block instead,
+/// but with both hands empty the block is still used, like in vanilla.
+pub fn uses_block(crouching: bool, holding_item: bool) -> bool {
+    !(crouching && holding_item)
+}
+
 pub fn on_use(
     block: Block,
     world: &mut impl World,
@@ -108,6 +115,13 @@ pub fn on_use(
 
             ActionResult::Success
         }
+        Block::Sign { .. } | Block::WallSign { .. } => match world.get_block_entity(pos) {
+            // The plot runs the commands, since it knows who wrote the sign
+            Some(BlockEntity::Sign(sign)) if command_sign_commands(sign).is_some() => {
+                ActionResult::Success
+            }
+            _ => ActionResult::Pass,
+        },
         b if b.has_block_entity() => {
             // Open container
             let block_entity = world.get_block_entity(pos);
@@ -120,12 +134,138 @@ pub fn on_use(
     }
 }
 
+/// The first line of a sign that makes the rest of its lines run as commands when it is used
+const COMMAND_SIGN_TRIGGER: &str = "[cmd]";
+
+/// Sign rows are stored as JSON text components, this gets the plain text back out
+fn sign_row_text(row: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(row) {
+        Ok(serde_json::Value::Object(component)) => component
+            .get("text")
+            .and_then(|text| text.as_str())
+            .unwrap_or_default()
+            .to_owned(),
+        Ok(serde_json::Value::String(text)) => text,
+        _ => row.to_owned(),
+    }
+}
+
+/// If the sign is a command sign, returns the commands written on its remaining lines
+pub fn command_sign_commands(sign: &SignBlockEntity) -> Option<Vec<String>> {
+    if sign_row_text(&sign.rows[0]).trim() != COMMAND_SIGN_TRIGGER {
+        return None;
+    }
+    let commands = sign.rows[1..]
+        .iter()
+        .map(|row| sign_row_text(row).trim().to_owned())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            if line.starts_with('/') {
+                line
+            } else {
+                format!("/{}", line)
+            }
+        })
+        .collect();
+    Some(commands)
+}
+
+/// Steps `value` forward (or backward if `reverse`) through `min..=max`, wrapping around at the ends
+fn cycle_value(value: u8, min: u8, max: u8, reverse: bool) -> u8 {
+    if reverse {
+        if value <= min {
+            max
+        } else {
+            value - 1
+        }
+    } else if value >= max {
+        min
+    } else {
+        value + 1
+    }
+}
+
+/// Returns the block with its most relevant property cycled, as done by the debug stick.
+/// Returns `None` if the block doesn't have a property that can be cycled.
+pub fn cycle_state(block: Block, reverse: bool) -> Option<Block> {
+    Some(match block {
+        Block::RedstoneRepeater { mut repeater } => {
+            repeater.delay = cycle_value(repeater.delay, 1, 4, reverse);
+            Block::RedstoneRepeater { repeater }
+        }
+        Block::RedstoneComparator { mut comparator } => {
+            comparator.mode = comparator.mode.toggle();
+            Block::RedstoneComparator { comparator }
+        }
+        Block::RedstoneWire { mut wire } => {
+            wire.power = cycle_value(wire.power, 0, 15, reverse);
+            Block::RedstoneWire { wire }
+        }
+        Block::NoteBlock {
+            instrument,
+            note,
+            powered,
+        } => Block::NoteBlock {
+            instrument,
+            note: cycle_value(note as u8, 0, 24, reverse) as u32,
+            powered,
+        },
+        Block::RedstoneTorch { lit } => Block::RedstoneTorch { lit: !lit },
+        Block::RedstoneWallTorch { lit, facing } => Block::RedstoneWallTorch { lit: !lit, facing },
+        Block::RedstoneLamp { lit } => Block::RedstoneLamp { lit: !lit },
+        Block::Lever { mut lever } => {
+            lever.powered = !lever.powered;
+            Block::Lever { lever }
+        }
+        _ => return None,
+    })
+}
+
+/// Returns which half of the block a trapdoor placed against `face` goes in. On the side of
+/// a block, this depends on whether the upper or lower half of the face was clicked.
+fn trapdoor_half(face: BlockFace, cursor_y: f32) -> TrapdoorHalf {
+    match face {
+        BlockFace::Bottom => TrapdoorHalf::Top,
+        BlockFace::Top => TrapdoorHalf::Bottom,
+        _ if cursor_y > 0.5 => TrapdoorHalf::Top,
+        _ => TrapdoorHalf::Bottom,
+    }
+}
+
+/// How far outside of the clicked block's box the cursor can be before the click is thrown
+/// out. The client sends the cursor as a float, so clicks right on the edge of the box can
+/// land just outside of it.
+const CURSOR_TOLERANCE: f32 = 1.0 / 256.0;
+
+/// Returns true if the cursor lies on the clicked block's interaction box. Clicks on partial
+/// blocks like repeaters and levers only land on the part of the block that's there.
+fn cursor_on_block(block: Block, ctx: &UseOnBlockContext<'_>) -> bool {
+    block.interaction_box().is_some_and(|bounds| {
+        bounds
+            .grow(CURSOR_TOLERANCE)
+            .contains(ctx.cursor_x, ctx.cursor_y, ctx.cursor_z)
+    })
+}
+
+/// Returns true if the block placed by `item` can attach to the clicked `face`.
+/// Diodes have to sit on top of a block, and torches can't hang from the underside of one.
+fn can_place_against_face(item: Item, face: BlockFace) -> bool {
+    match item {
+        Item::Repeater {} | Item::Comparator {} => face == BlockFace::Top,
+        Item::RedstoneTorch {} => face != BlockFace::Bottom,
+        _ => true,
+    }
+}
+
 pub fn get_state_for_placement(
     world: &impl World,
     pos: BlockPos,
     item: Item,
     context: &UseOnBlockContext<'_>,
 ) -> Block {
+    if !can_place_against_face(item, context.block_face) {
+        return Block::Air {};
+    }
     let block = match item {
         Item::Stone {} => Block::Stone {},
         Item::Glass {} => Block::Glass {},
@@ -204,7 +344,9 @@ pub fn get_state_for_placement(
             BlockFace::Bottom => Block::Air {},
             BlockFace::Top => Block::Sign {
                 sign_type: SignType::from_item_type(sign_type),
-                rotation: (((180.0 + context.player.yaw) * 16.0 / 360.0) + 0.5).floor() as u32 & 15,
+                rotation: SignRotation(
+                    (((180.0 + context.player.yaw) * 16.0 / 360.0) + 0.5).floor() as u32 & 15,
+                ),
             },
             _ => Block::WallSign {
                 sign_type: SignType::from_item_type(sign_type),
@@ -217,28 +359,19 @@ pub fn get_state_for_placement(
         Item::Barrel {} => Block::Barrel {},
         Item::Target {} => Block::Target {},
         Item::StainedGlass { color } => Block::StainedGlass { color },
-        Item::SmoothStoneSlab {} => Block::SmoothStoneSlab {},
-        Item::QuartzSlab {} => Block::QuartzSlab {},
-        Item::IronTrapdoor {} => match context.block_face {
-            BlockFace::Bottom => Block::IronTrapdoor {
-                facing: context.player.get_direction().opposite(),
-                half: TrapdoorHalf::Top,
-                powered: false,
-            },
-            BlockFace::Top => Block::IronTrapdoor {
-                facing: context.player.get_direction().opposite(),
-                half: TrapdoorHalf::Bottom,
-                powered: false,
-            },
-            _ => Block::IronTrapdoor {
-                facing: context.block_face.unwrap_direction(),
-                half: if context.cursor_y > 0.5 {
-                    TrapdoorHalf::Top
-                } else {
-                    TrapdoorHalf::Bottom
-                },
-                powered: false,
+        Item::SmoothStoneSlab {} => Block::SmoothStoneSlab {
+            waterlogged: world.get_block(pos).is_water_source(),
+        },
+        Item::QuartzSlab {} => Block::QuartzSlab {
+            waterlogged: world.get_block(pos).is_water_source(),
+        },
+        Item::IronTrapdoor {} => Block::IronTrapdoor {
+            facing: match context.block_face {
+                BlockFace::Top | BlockFace::Bottom => context.player.get_direction().opposite(),
+                face => face.unwrap_direction(),
             },
+            half: trapdoor_half(context.block_face, context.cursor_y),
+            powered: false,
         },
         Item::NoteBlock {} => Block::NoteBlock {
             instrument: Instrument::Harp,
@@ -248,12 +381,16 @@ pub fn get_state_for_placement(
         Item::Clay {} => Block::Clay {},
         Item::GoldBlock {} => Block::GoldBlock {},
         Item::PackedIce {} => Block::PackedIce {},
-        Item::BoneBlock {} => Block::BoneBlock {},
+        Item::BoneBlock {} => Block::BoneBlock {
+            axis: BlockAxis::from_face(context.block_face),
+        },
         Item::IronBlock {} => Block::IronBlock {},
         Item::SoulSand {} => Block::SoulSand {},
         Item::Pumpkin {} => Block::Pumpkin {},
         Item::EmeraldBlock {} => Block::EmeraldBlock {},
-        Item::HayBlock {} => Block::HayBlock {},
+        Item::HayBlock {} => Block::HayBlock {
+            axis: BlockAxis::from_face(context.block_face),
+        },
         Item::Sand {} => Block::Sand {},
         _ => Block::Air {},
     };
@@ -279,7 +416,14 @@ pub fn place_in_world(
             }
         };
     }
-    world.set_block(pos, block);
+    let old_block = world.get_block(pos);
+    if old_block.has_block_entity() && !old_block.compare_variant(block) {
+        world.delete_block_entity(pos);
+    }
+    if !world.set_block(pos, block) {
+        // Placing the exact same state again doesn't change anything, so there is nothing to update
+        return;
+    }
     change_surrounding_blocks(world, pos);
     if let Block::RedstoneWire { .. } = block {
         redstone::update_wire_neighbors(world, pos);
@@ -338,8 +482,12 @@ pub fn is_valid_position(block: Block, world: &impl World, pos: BlockPos) -> boo
     }
 
     match block {
-        Block::RedstoneWire { .. }
-        | Block::RedstoneComparator { .. }
+        Block::RedstoneWire { .. } => {
+            // Unlike other components, dust can't sit on transparent blocks like glass
+            let bottom_block = world.get_block(pos.offset(BlockFace::Bottom));
+            bottom_block.is_cube() && !bottom_block.is_transparent()
+        }
+        Block::RedstoneComparator { .. }
         | Block::RedstoneRepeater { .. }
         | Block::Sign { .. }
         | Block::RedstoneTorch { .. } => {
@@ -435,7 +583,10 @@ pub struct UseOnBlockContext<'a> {
     pub block_pos: BlockPos,
     pub block_face: BlockFace,
     pub player: &'a mut Player,
+    /// Where on the clicked face the player was pointing, from 0 to 1 on each axis
+    pub cursor_x: f32,
     pub cursor_y: f32,
+    pub cursor_z: f32,
 }
 
 /// returns true if cancelled
@@ -446,6 +597,11 @@ pub fn use_item_on_block(
 ) -> bool {
     let use_pos = ctx.block_pos;
     let use_block = world.get_block(use_pos);
+    if !cursor_on_block(use_block, &ctx) {
+        // The client clicked on a block shape that isn't here anymore, so cancelling
+        // resyncs it instead of placing against a face that doesn't exist
+        return true;
+    }
     let block_pos = ctx.block_pos.offset(ctx.block_face);
     let mut top_pos = ctx.player.pos.block_pos();
     top_pos.y += 1;
@@ -453,9 +609,19 @@ pub fn use_item_on_block(
     {
         return false;
     }
+    if item.item_type == (Item::DebugStick {}) {
+        // Sneaking cycles in the opposite direction
+        if let Some(new_block) = cycle_state(use_block, ctx.player.crouching) {
+            world.set_block(use_pos, new_block);
+            redstone::update_surrounding_blocks(world, use_pos);
+        }
+        // Cancelling resends the real block state to the client
+        return true;
+    }
+
     let can_place = item.item_type.is_block() && world.get_block(block_pos).can_place_block_in();
 
-    if !ctx.player.crouching
+    if uses_block(ctx.player.crouching, ctx.player.holding_item())
         && on_use(
             use_block,
             world,
@@ -472,6 +638,8 @@ pub fn use_item_on_block(
         let block = get_state_for_placement(world, block_pos, item.item_type, &ctx);
 
         match block {
+            // The block can't be placed here
+            Block::Air {} => return true,
             Block::Sign { .. } | Block::WallSign { .. } => {
                 if !item
                     .nbt
@@ -496,3 +664,84 @@ pub fn use_item_on_block(
         true
     }
 }
+
+#[test]
+fn placement_against_clicked_face() {
+    assert!(!can_place_against_face(
+        Item::Repeater {},
+        BlockFace::Bottom
+    ));
+    assert!(!can_place_against_face(
+        Item::Comparator {},
+        BlockFace::North
+    ));
+    assert!(can_place_against_face(Item::Repeater {}, BlockFace::Top));
+    assert!(!can_place_against_face(
+        Item::RedstoneTorch {},
+        BlockFace::Bottom
+    ));
+    assert!(can_place_against_face(
+        Item::RedstoneTorch {},
+        BlockFace::East
+    ));
+    assert!(can_place_against_face(Item::Stone {}, BlockFace::Bottom));
+}
+
+#[test]
+fn placement_orientation_from_face_and_cursor() {
+    // Clicking the top face stands pillars up and puts trapdoors at the bottom,
+    // wherever on the face the cursor is
+    assert_eq!(BlockAxis::from_face(BlockFace::Top), BlockAxis::Y);
+    assert_eq!(trapdoor_half(BlockFace::Top, 1.0), TrapdoorHalf::Bottom);
+    assert_eq!(trapdoor_half(BlockFace::Bottom, 0.0), TrapdoorHalf::Top);
+
+    // On the side of a block, the cursor height picks the trapdoor half
+    assert_eq!(BlockAxis::from_face(BlockFace::East), BlockAxis::X);
+    assert_eq!(BlockAxis::from_face(BlockFace::North), BlockAxis::Z);
+    assert_eq!(trapdoor_half(BlockFace::East, 0.75), TrapdoorHalf::Top);
+    assert_eq!(trapdoor_half(BlockFace::East, 0.25), TrapdoorHalf::Bottom);
+}
+
+#[test]
+fn debug_stick_cycles_repeater_delay() {
+    use mchprs_blocks::BlockDirection;
+
+    let repeater = Block::RedstoneRepeater {
+        repeater: RedstoneRepeater::new(4, BlockDirection::North, false, false),
+    };
+    let Some(Block::RedstoneRepeater { repeater: cycled }) = cycle_state(repeater, false) else {
+        panic!("Repeater state was not cycled");
+    };
+    assert_eq!(cycled.delay, 1);
+    let Some(Block::RedstoneRepeater { repeater: reversed }) = cycle_state(repeater, true) else {
+        panic!("Repeater state was not cycled");
+    };
+    assert_eq!(reversed.delay, 3);
+    assert_eq!(cycle_state(Block::Stone {}, false), None);
+}
+
+#[test]
+fn command_sign_lines() {
+    let sign = |lines: [&str; 4]| SignBlockEntity {
+        rows: lines.map(|line| serde_json::json!({ "text": line }).to_string()),
+    };
+
+    assert_eq!(
+        command_sign_commands(&sign(["[cmd]", "/time set night", "", "rtps 20"])),
+        Some(vec!["/time set night".to_owned(), "/rtps 20".to_owned()])
+    );
+    assert_eq!(
+        command_sign_commands(&sign(["Hello", "/time set night", "", ""])),
+        None
+    );
+}
+
+#[test]
+fn sneaking_with_item_places_instead_of_using() {
+    // Sneaking with a block in hand places it against the repeater
+    assert!(!uses_block(true, true));
+    // Otherwise the repeater's delay is changed
+    assert!(uses_block(false, true));
+    assert!(uses_block(true, false));
+    assert!(uses_block(false, false));
+}
@@ -12,6 +12,7 @@ mod profile;
 pub mod redpiler;
 pub mod redstone;
 pub mod server;
+mod watchdog;
 pub mod world;
 
 #[macro_use]
@@ -33,6 +33,21 @@ struct PermissionNode {
 }
 
 impl PermissionNode {
+    fn new(path: &str, value: i32, server_context: String) -> PermissionNode {
+        let path = path
+            .split('.')
+            .map(|s| match s {
+                "*" => PathSegment::WildCard,
+                s => PathSegment::Named(s.to_owned()),
+            })
+            .collect();
+        PermissionNode {
+            path,
+            value,
+            server_context,
+        }
+    }
+
     fn matches(&self, str: &str) -> bool {
         if self.server_context != "global" && self.server_context != config().server_context {
             return false;
@@ -58,6 +73,16 @@ pub struct PlayerPermissionsCache {
 }
 
 impl PlayerPermissionsCache {
+    /// A cache that grants exactly `nodes` on every server, for players in tests
+    #[cfg(test)]
+    pub fn granting(nodes: &[&str]) -> PlayerPermissionsCache {
+        let nodes = nodes
+            .iter()
+            .map(|node| PermissionNode::new(node, 1, "global".to_owned()))
+            .collect();
+        PlayerPermissionsCache { nodes }
+    }
+
     pub fn get_node_val(&self, name: &str) -> Option<i32> {
         for node in &self.nodes {
             if node.matches(name) {
@@ -112,19 +137,11 @@ pub fn load_player_cache(uuid: u128) -> Result<PlayerPermissionsCache> {
 
     let mut nodes = Vec::new();
     for row in res {
-        let path_str = String::from_value(row[2].clone());
-        let path = path_str
-            .split('.')
-            .map(|s| match s {
-                "*" => PathSegment::WildCard,
-                s => PathSegment::Named(s.to_owned()),
-            })
-            .collect();
-        let node = PermissionNode {
-            path,
-            server_context: FromValue::from_value(row[4].clone()),
-            value: FromValue::from_value(row[3].clone()),
-        };
+        let node = PermissionNode::new(
+            &String::from_value(row[2].clone()),
+            FromValue::from_value(row[3].clone()),
+            FromValue::from_value(row[4].clone()),
+        );
         nodes.push(node);
     }
 
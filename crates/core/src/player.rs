@@ -3,7 +3,7 @@ use crate::config::CONFIG;
 use crate::permissions::{self, PlayerPermissionsCache};
 use crate::plot::worldedit::{WorldEditClipboard, WorldEditUndo};
 use crate::plot::PLOT_SCALE;
-use crate::utils::HyphenatedUUID;
+use crate::utils::{world_dir, HyphenatedUUID};
 use byteorder::{BigEndian, ReadBytesExt};
 use mchprs_blocks::block_entities::{ContainerType, InventoryEntry};
 use mchprs_blocks::items::{Item, ItemStack};
@@ -13,15 +13,50 @@ use mchprs_network::packets::{PacketEncoder, SlotData};
 use mchprs_network::{PlayerConn, PlayerPacketSender};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::HashSet;
 use std::fmt;
 use std::fs::{self, OpenOptions};
 use std::io::{Cursor, Write};
-use std::sync::atomic::{AtomicU32, Ordering};
-use std::time::{Instant, SystemTime};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
 use tracing::{error, warn};
 
 pub type EntityId = u32;
-static ENTITY_ID_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Entity ids for the whole server. Players keep their id when moving between plots,
+/// so ids have to be unique across all plots and not just within one.
+pub static ENTITY_IDS: Mutex<EntityIdAllocator> = Mutex::new(EntityIdAllocator::new());
+
+/// Hands out unique entity ids, reusing the ids of entities that have been removed
+#[derive(Debug, Default)]
+pub struct EntityIdAllocator {
+    next_id: EntityId,
+    free_ids: Vec<EntityId>,
+}
+
+impl EntityIdAllocator {
+    pub const fn new() -> EntityIdAllocator {
+        EntityIdAllocator {
+            next_id: 0,
+            free_ids: Vec::new(),
+        }
+    }
+
+    pub fn allocate(&mut self) -> EntityId {
+        if let Some(id) = self.free_ids.pop() {
+            return id;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Makes `id` available again. The entity must already be destroyed for every client.
+    pub fn free(&mut self, id: EntityId) {
+        debug_assert!(id < self.next_id && !self.free_ids.contains(&id));
+        self.free_ids.push(id);
+    }
+}
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Gamemode {
@@ -36,6 +71,99 @@ impl Gamemode {
             Gamemode::Spectator => 3,
         }
     }
+
+    /// Parses a gamemode from its name or id
+    pub fn from_name(name: &str) -> Option<Gamemode> {
+        match name {
+            "creative" | "1" => Some(Gamemode::Creative),
+            "spectator" | "3" => Some(Gamemode::Spectator),
+            _ => None,
+        }
+    }
+}
+
+/// Window ids count up from 1 to 100 and then wrap around, like in vanilla.
+/// 0 is always the player's own inventory.
+fn next_window_id(last_window_id: u8) -> u8 {
+    last_window_id % 100 + 1
+}
+
+/// Keeps track of the last teleport sent to a client. Until the client confirms it,
+/// its movement packets may still be based on where it was before the teleport,
+/// so accepting them would drag the player back.
+#[derive(Debug, Default)]
+pub struct TeleportTracker {
+    next_id: i32,
+    awaiting: Option<i32>,
+}
+
+impl TeleportTracker {
+    /// Returns the id to send with a new teleport. Only the newest teleport has to be confirmed.
+    pub fn start(&mut self) -> i32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.awaiting = Some(id);
+        id
+    }
+
+    /// Handles a teleport confirmation from the client. Ids other than the newest one are ignored.
+    /// Returns true if the client is no longer waiting on a teleport.
+    pub fn confirm(&mut self, id: i32) -> bool {
+        if self.awaiting == Some(id) {
+            self.awaiting = None;
+        }
+        self.awaiting.is_none()
+    }
+
+    /// Returns true if movement from the client should be ignored
+    pub fn is_awaiting(&self) -> bool {
+        self.awaiting.is_some()
+    }
+}
+
+/// The flags sent in the Player Abilities packet.
+/// Spectators can always fly, otherwise flight depends on the `allow_flight` option.
+fn ability_flags(gamemode: Gamemode, flying: bool, allow_flight: bool) -> u8 {
+    let can_fly = allow_flight || matches!(gamemode, Gamemode::Spectator);
+    let mut flags = 0x01; // Invulnerable
+    if flying && can_fly {
+        flags |= 0x02;
+    }
+    if can_fly {
+        flags |= 0x04;
+    }
+    if matches!(gamemode, Gamemode::Creative) {
+        flags |= 0x08; // Instant break
+    }
+    flags
+}
+
+/// The number of blocks in the cuboid selection between two corners, inclusive
+fn selection_volume(first_pos: BlockPos, second_pos: BlockPos) -> u64 {
+    let size = |a: i32, b: i32| (a - b).unsigned_abs() as u64 + 1;
+    size(first_pos.x, second_pos.x)
+        * size(first_pos.y, second_pos.y)
+        * size(first_pos.z, second_pos.z)
+}
+
+/// Shows the selection size after a position is set, once both positions are
+fn selection_size_suffix(first_pos: Option<BlockPos>, second_pos: Option<BlockPos>) -> String {
+    match (first_pos, second_pos) {
+        (Some(first_pos), Some(second_pos)) => {
+            format!(" ({})", selection_volume(first_pos, second_pos))
+        }
+        _ => String::new(),
+    }
+}
+
+/// Players can't take damage in creative or spectator, the only gamemodes a plot has,
+/// so they are always kept at full health and food.
+fn full_health() -> CUpdateHealth {
+    CUpdateHealth {
+        health: 20.0,
+        food: 20,
+        food_saturation: 5.0,
+    }
 }
 
 /// This structure represents how the player will be
@@ -66,11 +194,41 @@ impl Default for PlayerData {
             selected_item_slot: 0,
             fly_speed: 1.0,
             walk_speed: 1.0,
-            gamemode: Gamemode::Creative,
+            gamemode: CONFIG
+                .default_gamemode()
+                .expect("the config is validated when it is loaded"),
         }
     }
 }
 
+impl PlayerData {
+    /// Player data is keyed by uuid, so a player that reconnects gets their
+    /// position, gamemode and inventory back even with a new connection.
+    fn path(uuid: u128) -> String {
+        world_dir()
+            .join(format!("players/{:032x}", uuid))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Returns `None` if the player has never been saved
+    fn read(path: &str) -> Option<bincode::Result<PlayerData>> {
+        let data = fs::read(path).ok()?;
+        Some(bincode::deserialize(&data))
+    }
+
+    fn write(&self, path: &str) {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .unwrap();
+        let data = bincode::serialize(self).unwrap();
+        file.write_all(&data).unwrap();
+    }
+}
+
 bitflags! {
     #[derive(Default)]
     pub struct SkinParts: u32 {
@@ -132,6 +290,8 @@ pub struct Player {
     pub last_chunk_x: i32,
     /// The last Z chunk the player was in. This is used for updated view position.
     pub last_chunk_z: i32,
+    /// The chunks that have been sent to the client and haven't been unloaded since
+    pub loaded_chunks: HashSet<(i32, i32)>,
     /// The player's head yaw rotation.
     pub yaw: f32,
     /// The player's head pitch rotation.
@@ -149,6 +309,12 @@ pub struct Player {
     pub last_keep_alive_received: Instant,
     /// The last time the keep alive packet was sent.
     last_keep_alive_sent: Instant,
+    /// The id of the keep alive packet the client hasn't answered yet
+    pending_keep_alive: Option<i64>,
+    /// The round trip time of the last answered keep alive packet
+    pub ping: Option<Duration>,
+    /// Teleports sent to the client that it hasn't confirmed yet
+    pub teleports: TeleportTracker,
     /// The worldedit first position.
     pub first_position: Option<BlockPos>,
     /// The worldedit second position.
@@ -161,7 +327,11 @@ pub struct Player {
     pub worldedit_redo: Vec<WorldEditUndo>,
     /// Commands are stored so they can be handled after packets
     pub command_queue: Vec<String>,
-    permissions_cache: Option<PlayerPermissionsCache>,
+    /// The id of the container window the player has open, if any
+    pub open_window_id: Option<u8>,
+    last_window_id: u8,
+    /// `None` when permissions are disabled, which allows everything
+    pub(crate) permissions_cache: Option<PlayerPermissionsCache>,
 }
 
 impl fmt::Debug for Player {
@@ -220,7 +390,8 @@ impl Player {
             yaw: player_data.rotation[1],
             last_chunk_x: 0,
             last_chunk_z: 0,
-            entity_id: ENTITY_ID_COUNTER.fetch_add(1, Ordering::Relaxed),
+            loaded_chunks: HashSet::new(),
+            entity_id: ENTITY_IDS.lock().unwrap().allocate(),
             client,
             flying: player_data.flying,
             sprinting: false,
@@ -231,12 +402,17 @@ impl Player {
             fly_speed: player_data.fly_speed,
             last_keep_alive_received: Instant::now(),
             last_keep_alive_sent: Instant::now(),
+            pending_keep_alive: None,
+            ping: None,
+            teleports: Default::default(),
             first_position: None,
             second_position: None,
             worldedit_clipboard: None,
             worldedit_undo: Vec::new(),
             worldedit_redo: Vec::new(),
             command_queue: Vec::new(),
+            open_window_id: None,
+            last_window_id: 0,
             permissions_cache,
         }
     }
@@ -244,33 +420,24 @@ impl Player {
     /// This will load the player from the file. If the file does not exist,
     /// It will be created.
     pub fn load_player(uuid: u128, username: String, client: PlayerConn) -> Player {
-        let filename = format!("./world/players/{:032x}", uuid);
-        if let Ok(data) = fs::read(&filename) {
-            let player_data: PlayerData = match bincode::deserialize(&data) {
-                Ok(data) => data,
-                Err(_) => {
-                    warn!("There was an error loading the player data for {}, player data will be backed up and reset.", username);
-                    if let Err(err) = fs::rename(&filename, filename.clone() + ".bak") {
-                        error!("Failed to back up player data: {}", err);
-                    }
-                    return Player::from_data(Default::default(), uuid, username, client);
+        let filename = PlayerData::path(uuid);
+        let player_data = match PlayerData::read(&filename) {
+            Some(Ok(data)) => data,
+            Some(Err(_)) => {
+                warn!("There was an error loading the player data for {}, player data will be backed up and reset.", username);
+                if let Err(err) = fs::rename(&filename, filename.clone() + ".bak") {
+                    error!("Failed to back up player data: {}", err);
                 }
-            };
-
-            Player::from_data(player_data, uuid, username, client)
-        } else {
-            Player::from_data(Default::default(), uuid, username, client)
-        }
+                Default::default()
+            }
+            None => Default::default(),
+        };
+        Player::from_data(player_data, uuid, username, client)
     }
 
     /// Saves the player to `./world/players/{uuid}`. This will create
     /// the file if it does not already exist.
     pub fn save(&self) {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(format!("./world/players/{:032x}", self.uuid))
-            .unwrap();
         let mut inventory: Vec<InventoryEntry> = Vec::new();
         for (slot, item_option) in self.inventory.iter().enumerate() {
             if let Some(item) = item_option {
@@ -287,7 +454,7 @@ impl Player {
                 });
             }
         }
-        let data = bincode::serialize(&PlayerData {
+        PlayerData {
             fly_speed: self.fly_speed,
             flying: self.flying,
             gamemode: self.gamemode,
@@ -298,9 +465,8 @@ impl Player {
             rotation: [self.pitch, self.yaw],
             selected_item_slot: self.selected_slot as i32,
             walk_speed: self.walk_speed,
-        })
-        .unwrap();
-        file.write_all(&data).unwrap();
+        }
+        .write(&PlayerData::path(self.uuid));
     }
 
     /// Manages keep alives and packet reading. Return true if the view position should be updated.
@@ -325,15 +491,32 @@ impl Player {
 
     /// Sends the keep alive packet to the client and updates `last_keep_alive_sent`
     pub fn send_keep_alive(&mut self) {
-        let keep_alive = CKeepAlive {
-            id: SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as i64,
-        }
-        .encode();
+        let id = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let keep_alive = CKeepAlive { id }.encode();
         self.client.send_packet(&keep_alive);
         self.last_keep_alive_sent = Instant::now();
+        self.pending_keep_alive = Some(id);
+    }
+
+    /// Handles the client's answer to a keep alive packet. Returns the measured ping if
+    /// `id` answers the last keep alive that was sent.
+    pub fn receive_keep_alive(&mut self, id: i64) -> Option<Duration> {
+        self.last_keep_alive_received = Instant::now();
+        if self.pending_keep_alive != Some(id) {
+            return None;
+        }
+        self.pending_keep_alive = None;
+        let ping = self.last_keep_alive_sent.elapsed();
+        self.ping = Some(ping);
+        Some(ping)
+    }
+
+    /// Whether the player has anything in their main or off hand
+    pub fn holding_item(&self) -> bool {
+        self.inventory[self.selected_slot as usize + 36].is_some() || self.inventory[45].is_some()
     }
 
     pub fn get_direction(&self) -> BlockDirection {
@@ -378,7 +561,7 @@ impl Player {
             yaw: 0f32,
             pitch: 0f32,
             flags: 0x08 | 0x10, // pitch and yaw are relative
-            teleport_id: 0,
+            teleport_id: self.teleports.start(),
             dismount_vehicle: false,
         }
         .encode();
@@ -421,8 +604,11 @@ impl Player {
 
     pub fn worldedit_set_first_position(&mut self, pos: BlockPos) {
         self.send_worldedit_message(&format!(
-            "First position set to ({}, {}, {})",
-            pos.x, pos.y, pos.z
+            "First position set to ({}, {}, {}){}",
+            pos.x,
+            pos.y,
+            pos.z,
+            selection_size_suffix(Some(pos), self.second_position)
         ));
         self.first_position = Some(pos);
         self.worldedit_send_cui(&format!("p|0|{}|{}|{}|0", pos.x, pos.y, pos.z));
@@ -430,8 +616,11 @@ impl Player {
 
     pub fn worldedit_set_second_position(&mut self, pos: BlockPos) {
         self.send_worldedit_message(&format!(
-            "Second position set to ({}, {}, {})",
-            pos.x, pos.y, pos.z
+            "Second position set to ({}, {}, {}){}",
+            pos.x,
+            pos.y,
+            pos.z,
+            selection_size_suffix(self.first_position, Some(pos))
         ));
         self.second_position = Some(pos);
         self.worldedit_send_cui(&format!("p|1|{}|{}|{}|0", pos.x, pos.y, pos.z));
@@ -454,7 +643,7 @@ impl Player {
 
     pub fn update_player_abilities(&self) {
         let player_abilities = CPlayerAbilities {
-            flags: 0x0D | ((self.flying as u8) << 1),
+            flags: ability_flags(self.gamemode, self.flying, CONFIG.allow_flight),
             fly_speed: 0.05 * self.fly_speed,
             fov_modifier: 0.1,
         }
@@ -462,6 +651,10 @@ impl Player {
         self.client.send_packet(&player_abilities);
     }
 
+    pub fn send_full_health(&self) {
+        self.client.send_packet(&full_health().encode());
+    }
+
     pub fn set_gamemode(&mut self, gamemode: Gamemode) {
         self.gamemode = gamemode;
         let change_game_state = CChangeGameState {
@@ -470,6 +663,7 @@ impl Player {
         }
         .encode();
         self.client.send_packet(&change_game_state);
+        self.update_player_abilities();
     }
 
     pub fn has_permission(&self, node: &str) -> bool {
@@ -486,7 +680,7 @@ impl Player {
         }
     }
 
-    pub fn open_container(&self, inventory: &[InventoryEntry], container_type: ContainerType) {
+    pub fn open_container(&mut self, inventory: &[InventoryEntry], container_type: ContainerType) {
         let mut slots: Vec<Option<SlotData>> =
             (0..container_type.num_slots()).map(|_| None).collect();
         for entry in inventory {
@@ -501,8 +695,12 @@ impl Player {
             });
         }
 
+        let window_id = next_window_id(self.last_window_id);
+        self.last_window_id = window_id;
+        self.open_window_id = Some(window_id);
+
         let open_window = COpenWindow {
-            window_id: 1,
+            window_id: window_id as i32,
             window_type: container_type.window_type() as i32,
             window_title: r#"{"text":"Container"}"#.to_owned(),
         }
@@ -510,7 +708,7 @@ impl Player {
         self.client.send_packet(&open_window);
 
         let window_items = CWindowItems {
-            window_id: 1,
+            window_id,
             state_id: 0,
             slot_data: slots,
             carried_item: None,
@@ -586,3 +784,169 @@ impl PacketSender for Player {
         self.client.send_packet(data);
     }
 }
+
+#[test]
+fn ability_flags_for_gamemode() {
+    // Creative with flight allowed, as a redstone server usually runs
+    let flags = ability_flags(Gamemode::Creative, false, true);
+    assert_eq!(flags & 0x04, 0x04);
+    assert_eq!(flags & 0x08, 0x08);
+    assert_eq!(ability_flags(Gamemode::Creative, true, true) & 0x02, 0x02);
+
+    // Creative without flight can't fly, even if it was flying before
+    assert_eq!(ability_flags(Gamemode::Creative, true, false) & 0x06, 0);
+
+    // Spectators can always fly
+    assert_eq!(
+        ability_flags(Gamemode::Spectator, false, false) & 0x04,
+        0x04
+    );
+
+    assert!(matches!(
+        Gamemode::from_name("spectator"),
+        Some(Gamemode::Spectator)
+    ));
+    assert!(Gamemode::from_name("survival").is_none());
+}
+
+#[test]
+fn movement_ignored_until_teleport_confirmed() {
+    let mut teleports = TeleportTracker::default();
+    assert!(!teleports.is_awaiting());
+
+    let first = teleports.start();
+    let second = teleports.start();
+    assert_ne!(first, second);
+    assert!(teleports.is_awaiting());
+
+    // Confirming an older or unknown teleport doesn't count
+    assert!(!teleports.confirm(first));
+    assert!(!teleports.confirm(second + 100));
+    assert!(teleports.is_awaiting());
+
+    assert!(teleports.confirm(second));
+    assert!(!teleports.is_awaiting());
+}
+
+#[test]
+fn window_ids_skip_player_inventory() {
+    assert_eq!(next_window_id(0), 1);
+    assert_eq!(next_window_id(1), 2);
+    assert_eq!(next_window_id(100), 1);
+    let mut window_id = 0;
+    for _ in 0..250 {
+        window_id = next_window_id(window_id);
+        assert!((1..=100).contains(&window_id));
+    }
+}
+
+#[test]
+fn entity_ids_unique_across_players() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let mut ids = EntityIdAllocator::new();
+    let first = ids.allocate();
+    let second = ids.allocate();
+    assert_ne!(first, second);
+    ids.free(first);
+    // The freed id is reused instead of growing forever
+    assert_eq!(ids.allocate(), first);
+    assert_eq!(ids.allocate(), 2);
+
+    // Players joining on different threads at the same time
+    let ids = Arc::new(Mutex::new(ids));
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let ids = ids.clone();
+            thread::spawn(move || {
+                (0..100)
+                    .map(|_| ids.lock().unwrap().allocate())
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let mut allocated: Vec<EntityId> = handles
+        .into_iter()
+        .flat_map(|handle| handle.join().unwrap())
+        .collect();
+    allocated.extend([first, second, 2]);
+    let count = allocated.len();
+    allocated.sort_unstable();
+    allocated.dedup();
+    assert_eq!(allocated.len(), count);
+}
+
+#[test]
+fn player_data_restored_on_reconnect() {
+    let uuid = Player::generate_offline_uuid("reconnect_test");
+    let path = std::env::temp_dir()
+        .join(format!("{:032x}", uuid))
+        .to_string_lossy()
+        .into_owned();
+    assert!(PlayerData::read(&path).is_none());
+
+    // First session ends with a full inventory
+    let mut data = PlayerData {
+        inventory: (0..9)
+            .map(|slot| InventoryEntry {
+                id: Item::Redstone {}.get_id(),
+                slot,
+                count: 64,
+                nbt: None,
+            })
+            .collect(),
+        ..Default::default()
+    };
+    data.write(&path);
+
+    // Second session moves to another plot as a spectator and empties the inventory
+    data.position = [1000.5, 64.0, -300.5];
+    data.gamemode = Gamemode::Spectator;
+    data.inventory.clear();
+    data.write(&path);
+
+    // The same uuid reconnects
+    let restored = PlayerData::read(&path).unwrap().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(restored.position, [1000.5, 64.0, -300.5]);
+    assert!(matches!(restored.gamemode, Gamemode::Spectator));
+    assert!(restored.inventory.is_empty());
+}
+
+#[test]
+fn joining_player_kept_at_full_health() {
+    let mut data = Vec::new();
+    full_health()
+        .encode()
+        .write_uncompressed(&mut data)
+        .unwrap();
+    let mut expected = vec![10, 0x52];
+    expected.extend(20.0f32.to_be_bytes());
+    expected.push(20);
+    expected.extend(5.0f32.to_be_bytes());
+    assert_eq!(data, expected);
+
+    // Health only matters if the player could be hurt
+    assert_eq!(ability_flags(Gamemode::Creative, false, false) & 0x01, 0x01);
+    assert_eq!(
+        ability_flags(Gamemode::Spectator, false, false) & 0x01,
+        0x01
+    );
+}
+
+#[test]
+fn selection_volume_from_both_positions() {
+    // //pos1 10 64 -3 and //pos2 12 60 -3
+    let first_pos = BlockPos::new(10, 64, -3);
+    let second_pos = BlockPos::new(12, 60, -3);
+    assert_eq!(selection_volume(first_pos, second_pos), 15);
+    assert_eq!(selection_volume(second_pos, first_pos), 15);
+    assert_eq!(selection_volume(first_pos, first_pos), 1);
+
+    assert_eq!(selection_size_suffix(Some(first_pos), None), "");
+    assert_eq!(
+        selection_size_suffix(Some(first_pos), Some(second_pos)),
+        " (15)"
+    );
+}
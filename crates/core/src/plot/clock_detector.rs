@@ -0,0 +1,65 @@
+//! Finds fast clocks that keep a plot busy with block updates. Without redpiler, a clock
+//! running at a high tps can peg a whole core, so operators get a warning pointing at it.
+
+use mchprs_blocks::BlockPos;
+use rustc_hash::FxHashMap;
+use std::time::{Duration, Instant};
+
+/// How many seconds in a row a plot has to stay over the threshold before the warning is logged
+pub const CLOCK_WARNING_SECONDS: u32 = 5;
+
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Counts the block updates of a plot every second, grouped by chunk section.
+#[derive(Default)]
+pub struct ClockDetector {
+    window_start: Option<Instant>,
+    updates: u64,
+    section_updates: FxHashMap<(i32, i32, i32), u64>,
+    busy_seconds: u32,
+    warned: bool,
+}
+
+impl ClockDetector {
+    pub fn record(&mut self, pos: BlockPos) {
+        self.updates += 1;
+        *self
+            .section_updates
+            .entry((pos.x >> 4, pos.y >> 4, pos.z >> 4))
+            .or_default() += 1;
+    }
+
+    /// Closes the current window once a second has passed since it started. Returns the
+    /// center of the busiest chunk section when the plot has done more than `threshold`
+    /// updates per second for `CLOCK_WARNING_SECONDS` windows in a row. This only happens
+    /// once until the plot calms down again. A threshold of 0 disables the detection.
+    pub fn check(&mut self, now: Instant, threshold: u64) -> Option<BlockPos> {
+        let window_start = *self.window_start.get_or_insert(now);
+        let elapsed = now.saturating_duration_since(window_start);
+        if elapsed < WINDOW {
+            return None;
+        }
+
+        let rate = self.updates as f64 / elapsed.as_secs_f64();
+        let densest = self
+            .section_updates
+            .iter()
+            .max_by_key(|(_, updates)| **updates)
+            .map(|(&(x, y, z), _)| BlockPos::new(x * 16 + 8, y * 16 + 8, z * 16 + 8));
+        self.window_start = Some(now);
+        self.updates = 0;
+        self.section_updates.clear();
+
+        if threshold == 0 || rate <= threshold as f64 {
+            self.busy_seconds = 0;
+            self.warned = false;
+            return None;
+        }
+        self.busy_seconds += 1;
+        if self.busy_seconds < CLOCK_WARNING_SECONDS || self.warned {
+            return None;
+        }
+        self.warned = true;
+        densest
+    }
+}
@@ -1,15 +1,22 @@
-use super::{database, worldedit, Plot, PlotWorld};
+use super::snapshot::RedstoneSnapshot;
+use super::worldedit::{CloneMask, UpdateContext, WorldEditPattern};
+use super::{database, worldedit, Plot, PlotWorld, TickWarp, FIX_REDSTONE_MAX_TICKS};
 use crate::chat::ChatComponent;
+use crate::config::CONFIG;
 use crate::player::{Gamemode, PacketSender, PlayerPos};
 use crate::plot::data::sleep_time_for_tps;
 use crate::profile::PlayerProfile;
-use crate::redpiler::CompilerOptions;
+use crate::redpiler::{self, CompilerOptions};
+use crate::redstone;
 use crate::server::Message;
+use crate::world::storage::{Chunk, StorageKind};
 use bitflags::_core::i32::MAX;
-use mchprs_blocks::items::ItemStack;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::items::{Item, ItemStack};
+use mchprs_blocks::BlockPos;
 use mchprs_network::packets::clientbound::{
     CDeclareCommands, CDeclareCommandsNode as Node, CDeclareCommandsNodeParser as Parser,
-    ClientBoundPacket,
+    CTimeUpdate, ClientBoundPacket,
 };
 use mchprs_network::packets::PacketEncoder;
 use mchprs_network::PlayerPacketSender;
@@ -21,7 +28,7 @@ use std::time::Instant;
 use tracing::{debug, info, warn};
 
 // Parses a relative or absolute coordinate relative to a reference coordinate
-fn parse_relative_coord<F: FromStr + Add + Add<Output = F>>(
+pub(super) fn parse_relative_coord<F: FromStr + Add + Add<Output = F>>(
     coord: &str,
     ref_coord: F,
 ) -> Result<F, <F as FromStr>::Err> {
@@ -34,6 +41,106 @@ fn parse_relative_coord<F: FromStr + Add + Add<Output = F>>(
     }
 }
 
+// Parses three coordinates like vanilla commands do. Each one can be absolute or relative
+// to the player's position (`~`), or all three can be local to where the player is
+// looking (`^left ^up ^forward`).
+fn parse_command_pos(args: &[&str], pos: PlayerPos, yaw: f32, pitch: f32) -> Option<BlockPos> {
+    let [x, y, z] = args else {
+        return None;
+    };
+    let local = [x, y, z].map(|coord| coord.strip_prefix('^'));
+    let (x, y, z) = match local {
+        [Some(left), Some(up), Some(forward)] => {
+            let parse_offset = |offset: &str| match offset {
+                "" => Some(0.0),
+                offset => offset.parse::<f64>().ok(),
+            };
+            let (left, up, forward) = (
+                parse_offset(left)?,
+                parse_offset(up)?,
+                parse_offset(forward)?,
+            );
+            let yaw = (yaw as f64 + 90.0).to_radians();
+            let pitch = -(pitch as f64).to_radians();
+            let forward_dir = [
+                yaw.cos() * pitch.cos(),
+                pitch.sin(),
+                yaw.sin() * pitch.cos(),
+            ];
+            let up_dir = [
+                yaw.cos() * -pitch.sin(),
+                pitch.cos(),
+                yaw.sin() * -pitch.sin(),
+            ];
+            // The cross product of up and forward points to the player's left
+            let left_dir = [
+                up_dir[1] * forward_dir[2] - up_dir[2] * forward_dir[1],
+                up_dir[2] * forward_dir[0] - up_dir[0] * forward_dir[2],
+                up_dir[0] * forward_dir[1] - up_dir[1] * forward_dir[0],
+            ];
+            let offset = |axis: usize| {
+                left_dir[axis] * left + up_dir[axis] * up + forward_dir[axis] * forward
+            };
+            (pos.x + offset(0), pos.y + offset(1), pos.z + offset(2))
+        }
+        [None, None, None] => (
+            parse_relative_coord(x, pos.x).ok()?,
+            parse_relative_coord(y, pos.y).ok()?,
+            parse_relative_coord(z, pos.z).ok()?,
+        ),
+        // Local coordinates can't be mixed with other kinds
+        _ => return None,
+    };
+    Some(BlockPos::new(
+        x.floor() as i32,
+        y.floor() as i32,
+        z.floor() as i32,
+    ))
+}
+
+// Parses three relative or absolute coordinates into a block position
+fn parse_block_pos(args: &[&str], reference: BlockPos) -> Option<BlockPos> {
+    match args {
+        [x, y, z] => Some(BlockPos::new(
+            parse_relative_coord(x, reference.x).ok()?,
+            parse_relative_coord(y, reference.y).ok()?,
+            parse_relative_coord(z, reference.z).ok()?,
+        )),
+        _ => None,
+    }
+}
+
+/// The items `/kit redstone` fills the hotbar with, from left to right
+const REDSTONE_KIT: [Item; 9] = [
+    Item::Redstone {},
+    Item::Repeater {},
+    Item::Comparator {},
+    Item::RedstoneTorch {},
+    Item::Lever {},
+    Item::StoneButton {},
+    Item::RedstoneBlock {},
+    Item::RedstoneLamp {},
+    Item::Target {},
+];
+
+/// Returns the inventory slots and items a kit fills, or `None` if there is no kit named `name`
+fn kit_slots(name: &str) -> Option<Vec<(u32, ItemStack)>> {
+    let items = match name {
+        "redstone" => REDSTONE_KIT,
+        _ => return None,
+    };
+    // The hotbar starts at slot 36 of the player's inventory window
+    let slots = (36..).zip(items).map(|(slot, item_type)| {
+        let item = ItemStack {
+            item_type,
+            count: 64,
+            nbt: None,
+        };
+        (slot, item)
+    });
+    Some(slots.collect())
+}
+
 impl Plot {
     /// Handles a command that starts with `/plot` or `/p`
     fn handle_plot_command(&mut self, player: usize, command: &str, args: &[&str]) {
@@ -45,8 +152,12 @@ impl Plot {
             "auto" | "a" => "plots.auto",
             "middle" => "plots.middle",
             "visit" | "v" => "plots.visit",
+            "home" | "h" => "plots.home",
+            "trust" => "plots.trust",
             "teleport" | "tp" => "plots.visit",
             "lock" | "unlock" => "plots.lock",
+            "clear" => "plots.clear",
+            "unclaim" => "plots.unclaim",
             _ => {
                 self.players[player].send_error_message("Invalid argument for /plot");
                 return;
@@ -122,6 +233,56 @@ impl Plot {
                         .send_system_message(&format!("{} does not own any plots.", args[0]));
                 }
             }
+            "home" | "h" => {
+                let idx = match args.first() {
+                    Some(arg) => match arg.parse::<usize>() {
+                        Ok(idx) => idx.wrapping_sub(1),
+                        Err(_) => {
+                            self.players[player].send_error_message("Unable to parse index");
+                            return;
+                        }
+                    },
+                    None => 0,
+                };
+
+                let plots = database::get_owned_plots(&self.players[player].username);
+                if plots.is_empty() {
+                    self.players[player].send_system_message(
+                        "You do not own any plots. Use /plot claim or /plot auto to get one.",
+                    );
+                } else if let Some(&(plot_x, plot_z)) = plots.get(idx) {
+                    let center = Plot::get_center(plot_x, plot_z);
+                    self.players[player].teleport(PlayerPos::new(center.0, 64.0, center.1));
+                } else {
+                    self.players[player]
+                        .send_system_message(&format!("Plot range (1, {}).", plots.len()));
+                }
+            }
+            "trust" => {
+                if args.len() != 1 {
+                    self.players[player].send_error_message("Invalid number of arguments!");
+                    return;
+                }
+                if self.owner != Some(self.players[player].uuid)
+                    && !self.players[player].has_permission("plots.admin.trust.other")
+                {
+                    self.players[player]
+                        .send_error_message("You can only trust players on plots you own!");
+                    return;
+                }
+
+                let PlotWorld { x, z, .. } = self.world;
+                if database::trust_player(x, z, args[0]) {
+                    self.trusted = Plot::get_trusted(x, z);
+                    self.players[player]
+                        .send_system_message(&format!("{} can now build on this plot.", args[0]));
+                } else {
+                    self.players[player].send_error_message(&format!(
+                        "{} has never joined or can already build on this plot.",
+                        args[0]
+                    ));
+                }
+            }
             "teleport" | "tp" => {
                 if args.len() != 2 {
                     self.players[player].send_error_message("Invalid number of arguments!");
@@ -163,6 +324,50 @@ impl Plot {
                     self.players[player].send_system_message("You are not locked to this plot.");
                 }
             }
+            "clear" => {
+                if self.owner != Some(self.players[player].uuid)
+                    && !self.players[player].has_permission("plots.admin.clear.other")
+                {
+                    self.players[player].send_error_message("You can only clear plots you own!");
+                    return;
+                }
+
+                self.reset_redpiler();
+                let (floor, border) = CONFIG
+                    .plot_blocks()
+                    .expect("the config is validated when it is loaded");
+                self.world.clear(floor, border);
+                // These all belong to the circuits that were just cleared. The chunks are
+                // sent again below, which also gets rid of the overlay.
+                self.power_overlay.clear();
+                self.snapshots.clear();
+                self.tick_warp = None;
+                self.forget_sign_placers();
+                for player_idx in 0..self.players.len() {
+                    self.update_view_pos_for_player(player_idx, true);
+                }
+                self.players[player].send_system_message("Plot has been cleared.");
+            }
+            "unclaim" => {
+                if self.owner.is_none() {
+                    self.players[player].send_error_message("This plot isn't claimed!");
+                    return;
+                }
+                if self.owner != Some(self.players[player].uuid)
+                    && !self.players[player].has_permission("plots.admin.unclaim.other")
+                {
+                    self.players[player].send_error_message("You can only unclaim plots you own!");
+                    return;
+                }
+
+                let PlotWorld { x, z, .. } = self.world;
+                database::unclaim_plot(x, z);
+                self.owner = None;
+                self.trusted.clear();
+                // Signs written while it was claimed shouldn't run for whoever claims it next
+                self.forget_sign_placers();
+                self.players[player].send_system_message("Plot has been unclaimed.");
+            }
             _ => self.players[player].send_error_message("Invalid argument for /plot"),
         }
     }
@@ -175,6 +380,12 @@ impl Plot {
                 let args = args.join(" ");
                 let options = CompilerOptions::parse(&args);
 
+                if self.world.instant_redstone {
+                    self.players[player]
+                        .send_error_message("Redpiler can't be used while instant redstone is on.");
+                    return;
+                }
+
                 if options.optimize {
                     let msg = "Redpiler optimization is highly unstable and can break builds. Use with caution!";
                     warn!("{}", msg);
@@ -208,6 +419,16 @@ impl Plot {
         }
     }
 
+    /// Commands that change blocks can only be used by players that can build in this plot.
+    /// Tells the player when they can't.
+    fn check_can_build(&self, player: usize) -> bool {
+        if self.can_build(player) {
+            return true;
+        }
+        self.players[player].send_no_permission_message();
+        false
+    }
+
     // Returns true if packets should stop being handled
     pub(super) fn handle_command(
         &mut self,
@@ -270,6 +491,12 @@ impl Plot {
                     return false;
                 }
             },
+            "/list" => {
+                let packet_sender = PlayerPacketSender::new(&self.players[player].client);
+                let _ = self
+                    .message_sender
+                    .send(Message::ListPlayers(packet_sender));
+            }
             "/rtps" => {
                 if args.is_empty() {
                     let report = self.timings.generate_report();
@@ -314,7 +541,19 @@ impl Plot {
                 self.reset_timings();
                 self.players[player].send_system_message("The rtps was successfully set.");
             }
-            "/radv" | "/radvance" => {
+            "/freeze" => {
+                self.frozen = !self.frozen;
+                // Don't try to catch up on the time spent frozen
+                self.reset_timings();
+                if self.frozen {
+                    self.players[player].send_system_message(
+                        "The plot has been frozen. Use /radv to step it forward.",
+                    );
+                } else {
+                    self.players[player].send_system_message("The plot has been unfrozen.");
+                }
+            }
+            "/radv" | "/radvance" | "/step" => {
                 if args.is_empty() {
                     self.players[player]
                         .send_error_message("Please specify a number of ticks to advance.");
@@ -326,16 +565,61 @@ impl Plot {
                     self.players[player].send_error_message("Unable to parse ticks!");
                     return false;
                 };
-                let start_time = Instant::now();
-                for _ in 0..ticks {
-                    self.tick();
+                if self.tick_warp.is_some() {
+                    self.players[player].send_error_message(
+                        "A tick warp is already running. Use /tick warp stop to cancel it.",
+                    );
+                    return false;
+                }
+                let mut warp = TickWarp::new(ticks as u64);
+                if warp.run_batch(TickWarp::BATCH_TIME, || self.tick()) {
+                    self.players[player].send_system_message(&format!(
+                        "Plot has been advanced by {} ticks ({:?})",
+                        ticks,
+                        warp.start_time.elapsed()
+                    ));
+                } else {
+                    // Running the rest right away would leave the plot unresponsive, and
+                    // the watchdog would take it for stuck
+                    self.players[player].send_system_message(&format!(
+                        "Advancing the remaining {} ticks in the background. Use /tick warp stop to cancel.",
+                        warp.remaining
+                    ));
+                    self.tick_warp = Some(warp);
                 }
-                self.players[player].send_system_message(&format!(
-                    "Plot has been advanced by {} ticks ({:?})",
-                    ticks,
-                    start_time.elapsed()
-                ));
             }
+            "/redstone" => match args.as_slice() {
+                ["mode", "java"] => {
+                    self.auto_redpiler = false;
+                    self.reset_redpiler();
+                    self.players[player].send_system_message(
+                        "Redstone now runs in java mode, matching vanilla behavior exactly.",
+                    );
+                }
+                ["mode", "fast"] => {
+                    if self.world.instant_redstone {
+                        self.players[player].send_error_message(
+                            "Fast mode can't be used while instant redstone is on.",
+                        );
+                        return false;
+                    }
+                    self.auto_redpiler = true;
+                    self.reset_redpiler();
+                    self.start_redpiler(Default::default());
+                    self.players[player]
+                        .send_system_message("Redstone now runs in fast mode, using redpiler.");
+                }
+                ["mode"] => {
+                    let mode = if self.auto_redpiler || self.redpiler.is_active() {
+                        "fast"
+                    } else {
+                        "java"
+                    };
+                    self.players[player]
+                        .send_system_message(&format!("Redstone is running in {} mode.", mode));
+                }
+                _ => self.players[player].send_error_message("Usage: /redstone mode <java|fast>"),
+            },
             "/toggleautorp" => {
                 self.auto_redpiler = !self.auto_redpiler;
                 if self.auto_redpiler {
@@ -447,15 +731,41 @@ impl Plot {
                     return false;
                 }
                 let name = args.remove(0);
-                let gamemode = match name {
-                    "creative" | "1" => Gamemode::Creative,
-                    "spectator" | "3" => Gamemode::Spectator,
+                let Some(gamemode) = Gamemode::from_name(name) else {
+                    self.players[player].send_error_message("Unknown gamemode");
+                    return false;
+                };
+                self.change_player_gamemode(player, gamemode);
+            }
+            "/time" => {
+                let time_of_day = match args.as_slice() {
+                    ["set", "day"] => 1000,
+                    ["set", "noon"] => 6000,
+                    ["set", "night"] => 13000,
+                    ["set", "midnight"] => 18000,
+                    ["set", time] => match time.parse::<i64>() {
+                        Ok(time) if time >= 0 => time % 24000,
+                        _ => {
+                            self.players[player].send_error_message("Unable to parse time!");
+                            return false;
+                        }
+                    },
                     _ => {
-                        self.players[player].send_error_message("Unknown gamemode");
+                        self.players[player]
+                            .send_error_message("Usage: /time set [day|noon|night|midnight|ticks]");
                         return false;
                     }
                 };
-                self.change_player_gamemode(player, gamemode);
+                // A negative time stops the daylight cycle. A whole day is added so that
+                // a time of 0 still counts as negative.
+                let time_update = CTimeUpdate {
+                    world_age: 0,
+                    time_of_day: -(time_of_day + 24000),
+                }
+                .encode();
+                self.players[player].client.send_packet(&time_update);
+                self.players[player]
+                    .send_system_message(&format!("Set your time to {}", time_of_day));
             }
             "/container" => {
                 if args.len() != 2 {
@@ -491,6 +801,15 @@ impl Plot {
                 let slot = 36 + self.players[player].selected_slot;
                 self.players[player].set_inventory_slot(slot, Some(item));
             }
+            "/kit" => {
+                let Some(slots) = args.first().and_then(|name| kit_slots(name)) else {
+                    self.players[player].send_error_message("Usage: /kit redstone");
+                    return false;
+                };
+                for (slot, item) in slots {
+                    self.players[player].set_inventory_slot(slot, Some(item));
+                }
+            }
             "/worldsendrate" | "/wsr" => {
                 if args.len() != 1 {
                     self.players[player].send_error_message("Usage: /worldsendrate <hertz>");
@@ -516,116 +835,506 @@ impl Plot {
                 self.players[player]
                     .send_system_message("The world send rate was successfully set.");
             }
-            _ => self.players[player].send_error_message("Command not found!"),
-        }
-        false
-    }
-}
+            "/save-state" => {
+                let name = args.first().copied().unwrap_or("default");
+                self.reset_redpiler();
+                let snapshot = RedstoneSnapshot::capture(&self.world);
+                self.players[player].send_system_message(&format!(
+                    "Saved the state of {} redstone components as \"{}\".",
+                    snapshot.component_count(),
+                    name
+                ));
+                self.snapshots.insert(name.to_owned(), snapshot);
+            }
+            "/reset" => {
+                if !self.check_can_build(player) {
+                    return false;
+                }
+                let name = args.first().copied().unwrap_or("default");
+                if !self.snapshots.contains_key(name) {
+                    self.players[player].send_error_message(&format!(
+                        "There is no saved state named \"{}\".",
+                        name
+                    ));
+                    return false;
+                }
+                self.reset_redpiler();
+                self.snapshots[name].restore(&mut self.world);
+                self.reset_timings();
+                self.players[player]
+                    .send_system_message(&format!("Reset redstone to \"{}\".", name));
+            }
+            "/debug" => match args.as_slice() {
+                ["power", "on"] => {
+                    self.set_power_overlay(true);
+                    self.players[player].send_system_message(&format!(
+                        "Showing {} powered components.",
+                        self.power_overlay.len()
+                    ));
+                }
+                ["power", "off"] => {
+                    self.set_power_overlay(false);
+                    self.players[player].send_system_message("The power overlay has been cleared.");
+                }
+                _ => self.players[player].send_error_message("Usage: /debug power <on|off>"),
+            },
+            "/clone" => {
+                if !self.players[player].has_permission("plots.clone") {
+                    self.players[player].send_no_permission_message();
+                    return false;
+                }
+                if !self.check_can_build(player) {
+                    return false;
+                }
+                if args.len() < 9 {
+                    self.players[player].send_error_message(
+                        "Usage: /clone <x1 y1 z1> <x2 y2 z2> <dx dy dz> [replace|masked|filtered]",
+                    );
+                    return false;
+                }
+                let player_pos = self.players[player].pos.block_pos();
+                let (Some(first_pos), Some(second_pos), Some(offset)) = (
+                    parse_block_pos(&args[0..3], player_pos),
+                    parse_block_pos(&args[3..6], player_pos),
+                    parse_block_pos(&args[6..9], BlockPos::new(0, 0, 0)),
+                ) else {
+                    self.players[player].send_error_message("Unable to parse coordinates!");
+                    return false;
+                };
+                let mask = match args.get(9).copied() {
+                    None | Some("replace") => CloneMask::Replace,
+                    Some("masked") => CloneMask::Masked,
+                    Some("filtered") => {
+                        let Some(filter) = args.get(10) else {
+                            self.players[player].send_error_message("Missing block to filter by!");
+                            return false;
+                        };
+                        match WorldEditPattern::from_str(filter) {
+                            Ok(pattern) => CloneMask::Filtered(pattern),
+                            Err(err) => {
+                                self.players[player]
+                                    .send_error_message(&format!("Invalid filter: {}", err));
+                                return false;
+                            }
+                        }
+                    }
+                    Some(mode) => {
+                        self.players[player]
+                            .send_error_message(&format!("Unknown clone mode: {}", mode));
+                        return false;
+                    }
+                };
 
-bitflags! {
-    struct CommandFlags: u32 {
-        const ROOT = 0x0;
-        const LITERAL = 0x1;
-        const ARGUMENT = 0x2;
-        const EXECUTABLE = 0x4;
-        const REDIRECT = 0x8;
-        const HAS_SUGGESTIONS_TYPE = 0x10;
-    }
-}
+                let (plot_min, plot_max) = self.world.get_corners();
+                let in_plot =
+                    |pos: BlockPos| pos.min(plot_min) == plot_min && pos.max(plot_max) == plot_max;
+                if ![
+                    first_pos,
+                    second_pos,
+                    first_pos + offset,
+                    second_pos + offset,
+                ]
+                .into_iter()
+                .all(in_plot)
+                {
+                    self.players[player]
+                        .send_error_message("The source and destination must be inside the plot!");
+                    return false;
+                }
 
-// In the future a DSL or some type of generation would be much better.
-// For more information, see https://wiki.vg/Command_Data
-/// The `DeclareCommands` packet that is sent when the player joins.
-/// This is used for command autocomplete.
-pub static DECLARE_COMMANDS: Lazy<PacketEncoder> = Lazy::new(|| {
-    CDeclareCommands {
-        nodes: &[
-            // 0: Root Node
-            Node {
-                flags: CommandFlags::ROOT.bits() as i8,
-                children: &[
-                    1, 4, 5, 6, 11, 12, 14, 16, 18, 19, 20, 21, 22, 23, 24, 26, 29, 31, 32, 34, 36,
-                    47, 49, 53, 60, 61, 63, 65, 66, 67, 71, 73,
-                ],
-                redirect_node: None,
-                name: None,
-                parser: None,
-                suggestions_type: None,
-            },
-            // 1: /teleport
-            Node {
-                flags: CommandFlags::LITERAL.bits() as i8,
-                children: &[2, 3],
-                redirect_node: None,
-                name: Some("teleport"),
-                parser: None,
-                suggestions_type: None,
-            },
-            // 2: /teleport [x, y, z]
-            Node {
-                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
-                children: &[],
-                redirect_node: None,
-                name: Some("x, y, z"),
-                parser: Some(Parser::Vec3),
-                suggestions_type: None,
-            },
-            // 3: /teleport [player]
-            Node {
-                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
-                children: &[],
-                redirect_node: None,
-                name: Some("player"),
-                parser: Some(Parser::Entity(3)), // Only allow one player
-                suggestions_type: None,
-            },
-            // 4: /tp
-            Node {
-                flags: (CommandFlags::REDIRECT | CommandFlags::LITERAL).bits() as i8,
-                children: &[],
-                redirect_node: Some(1),
-                name: Some("tp"),
-                parser: None,
-                suggestions_type: None,
-            },
-            // 5: /stop
-            Node {
-                flags: (CommandFlags::EXECUTABLE | CommandFlags::LITERAL).bits() as i8,
-                children: &[],
-                redirect_node: None,
-                name: Some("stop"),
-                parser: None,
-                suggestions_type: None,
-            },
-            // 6: /plot
-            Node {
-                flags: (CommandFlags::LITERAL).bits() as i8,
-                children: &[7, 8, 9, 10, 38, 39, 40, 41, 43, 44, 46, 58, 59],
-                redirect_node: None,
-                name: Some("plot"),
-                parser: None,
-                suggestions_type: None,
-            },
-            // 7: /plot info
-            Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
-                children: &[],
-                redirect_node: None,
-                name: Some("info"),
-                parser: None,
-                suggestions_type: None,
-            },
-            // 8: /plot i
-            Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
-                children: &[],
-                redirect_node: Some(7),
-                name: Some("i"),
-                parser: None,
-                suggestions_type: None,
+                self.reset_redpiler();
+                let mut updates = UpdateContext::default();
+                let copied = worldedit::clone_region(
+                    &mut self.world,
+                    first_pos,
+                    second_pos,
+                    offset,
+                    &mask,
+                    &mut updates,
+                );
+                // Like vanilla, the cloned blocks are updated once they are all placed
+                updates.flush(&mut self.world);
+                self.players[player]
+                    .send_system_message(&format!("Successfully cloned {} blocks.", copied));
+            }
+            "/tick" => match args.as_slice() {
+                ["warp", "stop"] => {
+                    if let Some(warp) = self.tick_warp.take() {
+                        self.reset_timings();
+                        self.players[player].send_system_message(&format!(
+                            "Cancelled the tick warp after {} of {} ticks.",
+                            warp.ticks - warp.remaining,
+                            warp.ticks
+                        ));
+                    } else {
+                        self.players[player].send_error_message("There is no tick warp running.");
+                    }
+                }
+                ["warp", ticks] => {
+                    let Ok(ticks) = ticks.parse::<u64>() else {
+                        self.players[player].send_error_message("Unable to parse ticks!");
+                        return false;
+                    };
+                    if self.tick_warp.is_some() {
+                        self.players[player].send_error_message(
+                            "A tick warp is already running. Use /tick warp stop to cancel it.",
+                        );
+                        return false;
+                    }
+                    self.tick_warp = Some(TickWarp::new(ticks));
+                    self.players[player].send_system_message(&format!(
+                        "Warping {} ticks. Use /tick warp stop to cancel.",
+                        ticks
+                    ));
+                }
+                _ => self.players[player].send_error_message("Usage: /tick warp <ticks|stop>"),
             },
-            // 9: /plot claim
-            Node {
+            "/fixredstone" => {
+                if !self.check_can_build(player) {
+                    return false;
+                }
+                // Only the selection is fixed if there is one, otherwise the whole plot is
+                let selection = (
+                    self.players[player].first_position,
+                    self.players[player].second_position,
+                );
+                let (first_pos, second_pos) = match selection {
+                    (Some(first_pos), Some(second_pos)) => (first_pos, second_pos),
+                    _ => self.world.get_corners(),
+                };
+                self.reset_redpiler();
+                let (updated, settled) = self.world.fix_redstone(first_pos, second_pos);
+                if settled {
+                    self.players[player]
+                        .send_system_message(&format!("Updated {} redstone components.", updated));
+                } else {
+                    self.players[player].send_error_message(&format!(
+                        "Updated {} redstone components, but the circuit is still running after {} ticks.",
+                        updated, FIX_REDSTONE_MAX_TICKS
+                    ));
+                }
+            }
+            "/setblock" => {
+                if !self.players[player].has_permission("plots.setblock") {
+                    self.players[player].send_no_permission_message();
+                    return false;
+                }
+                if !self.check_can_build(player) {
+                    return false;
+                }
+                if args.len() != 4 {
+                    self.players[player].send_error_message("Usage: /setblock <x> <y> <z> <block>");
+                    return false;
+                }
+                let (pos, yaw, pitch) = {
+                    let player = &self.players[player];
+                    (player.pos, player.yaw, player.pitch)
+                };
+                let Some(block_pos) = parse_command_pos(&args[0..3], pos, yaw, pitch) else {
+                    self.players[player].send_error_message("Unable to parse coordinates!");
+                    return false;
+                };
+                let Some(block) = Block::from_state(args[3]) else {
+                    self.players[player].send_error_message(&format!("Unknown block: {}", args[3]));
+                    return false;
+                };
+                let (plot_min, plot_max) = self.world.get_corners();
+                if block_pos.min(plot_min) != plot_min || block_pos.max(plot_max) != plot_max {
+                    self.players[player].send_error_message("That position is outside the plot!");
+                    return false;
+                }
+                self.reset_redpiler();
+                self.world.set_block(block_pos, block);
+                let mut updates = UpdateContext::default();
+                updates.mark_changed(block_pos);
+                updates.flush(&mut self.world);
+                self.players[player]
+                    .send_system_message(&format!("Changed the block at {}.", block_pos));
+            }
+            "/power" => {
+                if !self.check_can_build(player) {
+                    return false;
+                }
+                if args.len() != 4 {
+                    self.players[player].send_error_message("Usage: /power <x> <y> <z> <0-15>");
+                    return false;
+                }
+                let player_pos = self.players[player].pos.block_pos();
+                let Some(pos) = parse_block_pos(&args[0..3], player_pos) else {
+                    self.players[player].send_error_message("Unable to parse coordinates!");
+                    return false;
+                };
+                let power = match args[3].parse::<u8>() {
+                    Ok(power) if power <= 15 => power,
+                    _ => {
+                        self.players[player]
+                            .send_error_message("The power level must be between 0 and 15!");
+                        return false;
+                    }
+                };
+                self.reset_redpiler();
+                if redstone::force_power(&mut self.world, pos, power) {
+                    self.players[player]
+                        .send_system_message(&format!("Forced the power at {} to {}.", pos, power));
+                } else {
+                    self.players[player]
+                        .send_error_message("Only redstone dust and comparators can be forced!");
+                }
+            }
+            "/neighborupdate" => {
+                if !self.check_can_build(player) {
+                    return false;
+                }
+                if args.len() != 3 {
+                    self.players[player].send_error_message("Usage: /neighborupdate <x> <y> <z>");
+                    return false;
+                }
+                let player_pos = self.players[player].pos.block_pos();
+                let Some(pos) = parse_block_pos(&args[0..3], player_pos) else {
+                    self.players[player].send_error_message("Unable to parse coordinates!");
+                    return false;
+                };
+                self.reset_redpiler();
+                self.world.neighbor_update(pos);
+                self.players[player].send_system_message(&format!("Updated the block at {}.", pos));
+            }
+            "/label" => {
+                if !self.check_can_build(player) {
+                    return false;
+                }
+                if args.is_empty() {
+                    self.players[player].send_error_message("Usage: /label <text>");
+                    return false;
+                }
+                let pos = self.players[player].pos.block_pos();
+                if !self.world.place_label(pos, &args.join(" ")) {
+                    self.players[player]
+                        .send_error_message("That label is too long to fit on a sign!");
+                    return false;
+                }
+                self.players[player].send_system_message(&format!("Placed a label at {}.", pos));
+            }
+            "/instantredstone" => {
+                let enabled = match args.as_slice() {
+                    ["on"] => true,
+                    ["off"] => false,
+                    _ => {
+                        self.players[player].send_error_message("Usage: /instantredstone <on|off>");
+                        return false;
+                    }
+                };
+                self.world.instant_redstone = enabled;
+                // Redpiler compiles the real delays into its graph
+                self.reset_redpiler();
+                if enabled {
+                    self.players[player].send_system_message(
+                        "Instant redstone is on. Every delay now takes a single tick.",
+                    );
+                } else {
+                    self.players[player].send_system_message("Instant redstone is off.");
+                }
+            }
+            "/updatelimit" => {
+                if args.is_empty() {
+                    self.players[player].send_system_message(&format!(
+                        "This plot runs at most {} scheduled ticks per tick.",
+                        self.world.tick_update_limit
+                    ));
+                    return false;
+                }
+
+                if self.owner != Some(self.players[player].uuid)
+                    && !self.players[player].has_permission("plots.admin.updatelimit.other")
+                {
+                    self.players[player].send_error_message(
+                        "You can only change the update limit of plots you own!",
+                    );
+                    return false;
+                }
+                let Ok(limit) = args[0].parse::<usize>() else {
+                    self.players[player].send_error_message("Unable to parse limit!");
+                    return false;
+                };
+                if limit == 0 {
+                    self.players[player].send_error_message("The update limit cannot be 0!");
+                    return false;
+                }
+
+                self.world.tick_update_limit = limit;
+                self.players[player].send_system_message("The update limit was successfully set.");
+            }
+            "/storage" => {
+                if args.is_empty() {
+                    let memory_usage: usize =
+                        self.world.chunks.iter().map(Chunk::memory_usage).sum();
+                    let kind = match self.world.storage_kind() {
+                        StorageKind::Dense => "dense",
+                        StorageKind::Sparse => "sparse",
+                    };
+                    self.players[player].send_system_message(&format!(
+                        "This plot uses {} storage, taking up about {} KiB.",
+                        kind,
+                        memory_usage / 1024
+                    ));
+                    return false;
+                }
+
+                if self.owner != Some(self.players[player].uuid)
+                    && !self.players[player].has_permission("plots.admin.storage.other")
+                {
+                    self.players[player]
+                        .send_error_message("You can only change the storage of plots you own!");
+                    return false;
+                }
+                let Ok(kind) = args[0].parse::<StorageKind>() else {
+                    self.players[player].send_error_message("Usage: /storage [dense|sparse]");
+                    return false;
+                };
+                self.world.set_storage_kind(kind);
+                self.players[player].send_system_message("The plot storage was successfully set.");
+            }
+            "/dumpgraph" => {
+                if !self.check_can_build(player) {
+                    return false;
+                }
+                let (Some(first_pos), Some(second_pos)) = (
+                    self.players[player].first_position,
+                    self.players[player].second_position,
+                ) else {
+                    self.players[player].send_error_message("Make a region selection first.");
+                    return false;
+                };
+                let dot = redpiler::dump_graph(&self.world, (first_pos, second_pos));
+                let path = redstone_graph_path(self.world.x, self.world.z);
+                if let Err(err) = std::fs::write(&path, dot) {
+                    warn!("Failed to write redstone graph: {}", err);
+                    self.players[player].send_error_message("Failed to write the redstone graph!");
+                    return false;
+                }
+                self.players[player].send_system_message(&format!(
+                    "Wrote the redstone graph of your selection to {}.",
+                    path
+                ));
+            }
+            _ => self.players[player].send_error_message("Command not found!"),
+        }
+        false
+    }
+}
+
+/// Where `/dumpgraph` writes the Graphviz DOT graph of the plot at `x`, `z`. Each plot
+/// gets its own file so graphs dumped in different plots don't overwrite each other.
+pub(super) fn redstone_graph_path(x: i32, z: i32) -> String {
+    format!("redstone_graph_p{},{}.dot", x, z)
+}
+
+bitflags! {
+    struct CommandFlags: u32 {
+        const ROOT = 0x0;
+        const LITERAL = 0x1;
+        const ARGUMENT = 0x2;
+        const EXECUTABLE = 0x4;
+        const REDIRECT = 0x8;
+        const HAS_SUGGESTIONS_TYPE = 0x10;
+    }
+}
+
+// In the future a DSL or some type of generation would be much better.
+// For more information, see https://wiki.vg/Command_Data
+/// The `DeclareCommands` packet that is sent when the player joins.
+/// This is used for command autocomplete.
+pub static DECLARE_COMMANDS: Lazy<PacketEncoder> = Lazy::new(|| {
+    CDeclareCommands {
+        nodes: &[
+            // 0: Root Node
+            Node {
+                flags: CommandFlags::ROOT.bits() as i8,
+                children: &[
+                    1, 4, 5, 6, 11, 12, 14, 16, 18, 19, 20, 21, 22, 23, 24, 26, 29, 31, 32, 34, 36,
+                    47, 49, 53, 60, 61, 63, 65, 66, 67, 71, 73, 74, 76, 78, 80, 84, 85, 86, 99,
+                    103, 106, 108, 109, 112, 115, 119, 120, 123,
+                ],
+                redirect_node: None,
+                name: None,
+                parser: None,
+                suggestions_type: None,
+            },
+            // 1: /teleport
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: &[2, 3],
+                redirect_node: None,
+                name: Some("teleport"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 2: /teleport [x, y, z]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("x, y, z"),
+                parser: Some(Parser::Vec3),
+                suggestions_type: None,
+            },
+            // 3: /teleport [player]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("player"),
+                parser: Some(Parser::Entity(3)), // Only allow one player
+                suggestions_type: None,
+            },
+            // 4: /tp
+            Node {
+                flags: (CommandFlags::REDIRECT | CommandFlags::LITERAL).bits() as i8,
+                children: &[],
+                redirect_node: Some(1),
+                name: Some("tp"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 5: /stop
+            Node {
+                flags: (CommandFlags::EXECUTABLE | CommandFlags::LITERAL).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("stop"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 6: /plot
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: &[
+                    7, 8, 9, 10, 38, 39, 40, 41, 43, 44, 46, 58, 59, 94, 96, 97, 111, 126,
+                ],
+                redirect_node: None,
+                name: Some("plot"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 7: /plot info
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("info"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 8: /plot i
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                children: &[],
+                redirect_node: Some(7),
+                name: Some("i"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 9: /plot claim
+            Node {
                 flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
                 children: &[],
                 redirect_node: None,
@@ -1212,8 +1921,539 @@ pub static DECLARE_COMMANDS: Lazy<PacketEncoder> = Lazy::new(|| {
                 parser: None,
                 suggestions_type: None,
             },
-        ],
-        root_index: 0,
-    }
-    .encode()
-});
+            // 74: /updatelimit
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[75],
+                redirect_node: None,
+                name: Some("updatelimit"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 75: /updatelimit [limit]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("limit"),
+                parser: Some(Parser::Integer(1, i32::MAX)),
+                suggestions_type: None,
+            },
+            // 76: /save-state
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[77],
+                redirect_node: None,
+                name: Some("save-state"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 77: /save-state [name]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("name"),
+                parser: Some(Parser::String(0)),
+                suggestions_type: None,
+            },
+            // 78: /reset
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[79],
+                redirect_node: None,
+                name: Some("reset"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 79: /reset [name]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("name"),
+                parser: Some(Parser::String(0)),
+                suggestions_type: None,
+            },
+            // 80: /debug
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: &[81],
+                redirect_node: None,
+                name: Some("debug"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 81: /debug power
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: &[82, 83],
+                redirect_node: None,
+                name: Some("power"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 82: /debug power on
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("on"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 83: /debug power off
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("off"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 84: /freeze
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("freeze"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 85: /step
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                children: &[],
+                redirect_node: Some(29),
+                name: Some("step"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 86: /clone
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: &[87],
+                redirect_node: None,
+                name: Some("clone"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 87: /clone <begin>
+            Node {
+                flags: (CommandFlags::ARGUMENT).bits() as i8,
+                children: &[88],
+                redirect_node: None,
+                name: Some("begin"),
+                parser: Some(Parser::BlockPos),
+                suggestions_type: None,
+            },
+            // 88: /clone <begin> <end>
+            Node {
+                flags: (CommandFlags::ARGUMENT).bits() as i8,
+                children: &[89],
+                redirect_node: None,
+                name: Some("end"),
+                parser: Some(Parser::BlockPos),
+                suggestions_type: None,
+            },
+            // 89: /clone <begin> <end> <offset>
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[90, 91, 92],
+                redirect_node: None,
+                name: Some("offset"),
+                parser: Some(Parser::BlockPos),
+                suggestions_type: None,
+            },
+            // 90: /clone <begin> <end> <offset> replace
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("replace"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 91: /clone <begin> <end> <offset> masked
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("masked"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 92: /clone <begin> <end> <offset> filtered
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: &[93],
+                redirect_node: None,
+                name: Some("filtered"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 93: /clone <begin> <end> <offset> filtered <filter>
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("filter"),
+                parser: Some(Parser::BlockState),
+                suggestions_type: None,
+            },
+            // 94: /plot home
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[95],
+                redirect_node: None,
+                name: Some("home"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 95: /plot home [index]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("index"),
+                parser: Some(Parser::Integer(1, i32::MAX)),
+                suggestions_type: None,
+            },
+            // 96: /plot h
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                children: &[],
+                redirect_node: Some(94),
+                name: Some("h"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 97: /plot trust
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: &[98],
+                redirect_node: None,
+                name: Some("trust"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 98: /plot trust [player]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("player"),
+                parser: Some(Parser::Entity(3)),
+                suggestions_type: None,
+            },
+            // 99: /tick
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: &[100],
+                redirect_node: None,
+                name: Some("tick"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 100: /tick warp
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: &[101, 102],
+                redirect_node: None,
+                name: Some("warp"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 101: /tick warp [ticks]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("ticks"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
+                suggestions_type: None,
+            },
+            // 102: /tick warp stop
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("stop"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 103: /storage
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[104, 105],
+                redirect_node: None,
+                name: Some("storage"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 104: /storage dense
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("dense"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 105: /storage sparse
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("sparse"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 106: /kit
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: &[107],
+                redirect_node: None,
+                name: Some("kit"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 107: /kit redstone
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("redstone"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 108: /dumpgraph
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("dumpgraph"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 109: /neighborupdate
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: &[110],
+                redirect_node: None,
+                name: Some("neighborupdate"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 110: /neighborupdate <pos>
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("pos"),
+                parser: Some(Parser::BlockPos),
+                suggestions_type: None,
+            },
+            // 111: /plot clear
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("clear"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 112: /instantredstone
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: &[113, 114],
+                redirect_node: None,
+                name: Some("instantredstone"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 113: /instantredstone on
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("on"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 114: /instantredstone off
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("off"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 115: /redstone
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: &[116],
+                redirect_node: None,
+                name: Some("redstone"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 116: /redstone mode
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[117, 118],
+                redirect_node: None,
+                name: Some("mode"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 117: /redstone mode java
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("java"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 118: /redstone mode fast
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("fast"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 119: /list
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("list"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 120: /setblock
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: &[121],
+                redirect_node: None,
+                name: Some("setblock"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 121: /setblock <pos>
+            Node {
+                flags: (CommandFlags::ARGUMENT).bits() as i8,
+                children: &[122],
+                redirect_node: None,
+                name: Some("pos"),
+                parser: Some(Parser::BlockPos),
+                suggestions_type: None,
+            },
+            // 122: /setblock <pos> <block>
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("block"),
+                parser: Some(Parser::BlockState),
+                suggestions_type: None,
+            },
+            // 123: /time
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: &[124],
+                redirect_node: None,
+                name: Some("time"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 124: /time set
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: &[125],
+                redirect_node: None,
+                name: Some("set"),
+                parser: None,
+                suggestions_type: None,
+            },
+            // 125: /time set <time>
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("time"),
+                parser: Some(Parser::String(0)),
+                suggestions_type: None,
+            },
+            // 126: /plot unclaim
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: &[],
+                redirect_node: None,
+                name: Some("unclaim"),
+                parser: None,
+                suggestions_type: None,
+            },
+        ],
+        root_index: 0,
+    }
+    .encode()
+});
+
+#[test]
+fn setblock_local_coordinates() {
+    let pos = PlayerPos::new(10.5, 8.0, 10.5);
+    // Facing south
+    assert_eq!(
+        parse_command_pos(&["^", "^", "^2"], pos, 0.0, 0.0),
+        Some(BlockPos::new(10, 8, 12))
+    );
+    // Facing south, the player's left is east
+    assert_eq!(
+        parse_command_pos(&["^1", "^", "^"], pos, 0.0, 0.0),
+        Some(BlockPos::new(11, 8, 10))
+    );
+    // Facing west
+    assert_eq!(
+        parse_command_pos(&["^", "^", "^2"], pos, 90.0, 0.0),
+        Some(BlockPos::new(8, 8, 10))
+    );
+    // Looking straight down
+    assert_eq!(
+        parse_command_pos(&["^", "^", "^3"], pos, 0.0, 90.0),
+        Some(BlockPos::new(10, 5, 10))
+    );
+    assert_eq!(
+        parse_command_pos(&["~1", "~", "-3"], pos, 0.0, 0.0),
+        Some(BlockPos::new(11, 8, -3))
+    );
+    assert_eq!(parse_command_pos(&["^", "~", "^"], pos, 0.0, 0.0), None);
+}
+
+#[test]
+fn redstone_kit_fills_hotbar() {
+    let slots = kit_slots("redstone").unwrap();
+    let slot_items: Vec<(u32, u32)> = slots
+        .iter()
+        .map(|(slot, item)| (*slot, item.item_type.get_id()))
+        .collect();
+    assert_eq!(
+        slot_items,
+        vec![
+            (36, Item::Redstone {}.get_id()),
+            (37, Item::Repeater {}.get_id()),
+            (38, Item::Comparator {}.get_id()),
+            (39, Item::RedstoneTorch {}.get_id()),
+            (40, Item::Lever {}.get_id()),
+            (41, Item::StoneButton {}.get_id()),
+            (42, Item::RedstoneBlock {}.get_id()),
+            (43, Item::RedstoneLamp {}.get_id()),
+            (44, Item::Target {}.get_id()),
+        ]
+    );
+    assert!(kit_slots("pistons").is_none());
+}
@@ -1,6 +1,10 @@
-use super::{Plot, PlotWorld, PLOT_SECTIONS, PLOT_WIDTH};
+use super::{PlotWorld, PLOT_SECTIONS, PLOT_WIDTH};
+use crate::config::CONFIG;
+use crate::utils::world_dir;
+use crate::world::storage::Chunk;
 use anyhow::{Context, Result};
-use mchprs_save_data::plot_data::{ChunkData, PlotData, Tps, WorldSendRate};
+use mchprs_blocks::blocks::Block;
+use mchprs_save_data::plot_data::{ChunkData, PlotData, StorageKind, Tps, WorldSendRate};
 use once_cell::sync::Lazy;
 use std::path::Path;
 use std::time::Duration;
@@ -34,14 +38,14 @@ pub fn empty_plot() -> PlotData<PLOT_SECTIONS> {
 }
 
 static EMPTY_PLOT: Lazy<PlotData<PLOT_SECTIONS>> = Lazy::new(|| {
-    let template_path = Path::new("./world/plots/pTEMPLATE");
+    let template_path = world_dir().join("plots/pTEMPLATE");
     if template_path.exists() {
         PlotData::load_from_file(template_path).expect("failed to read template plot")
     } else {
         let mut chunks = Vec::new();
         for chunk_x in 0..PLOT_WIDTH {
             for chunk_z in 0..PLOT_WIDTH {
-                chunks.push(Plot::generate_chunk(8, chunk_x, chunk_z));
+                chunks.push(Chunk::empty(chunk_x, chunk_z));
             }
         }
         let mut world = PlotWorld {
@@ -50,14 +54,24 @@ static EMPTY_PLOT: Lazy<PlotData<PLOT_SECTIONS>> = Lazy::new(|| {
             chunks,
             to_be_ticked: Vec::new(),
             packet_senders: Vec::new(),
+            tick_update_limit: 0,
+            tick_limit_reached: false,
+            instant_redstone: false,
+            rng: PlotWorld::create_rng(Some(0)),
+            clock_detector: Default::default(),
         };
+        let (floor, border) = CONFIG
+            .plot_blocks()
+            .expect("the config is validated when it is loaded");
+        world.generate_borders(floor, border);
         let chunk_data: Vec<ChunkData<PLOT_SECTIONS>> =
             world.chunks.iter_mut().map(|c| c.save()).collect();
         PlotData {
-            tps: Tps::Limited(10),
+            tps: Tps::Limited(CONFIG.default_rtps as u32),
             world_send_rate: WorldSendRate::default(),
             chunk_data,
             pending_ticks: Vec::new(),
+            storage: StorageKind::Dense,
         }
     }
 });
@@ -2,10 +2,16 @@ use once_cell::sync::Lazy;
 use rusqlite::{params, Connection};
 use std::sync::{Mutex, MutexGuard};
 
+#[cfg(not(test))]
 static CONN: Lazy<Mutex<Connection>> = Lazy::new(|| {
     Mutex::new(Connection::open("./world/plots.db").expect("Error opening plot database!"))
 });
 
+// Tests shouldn't touch the real plot database
+#[cfg(test)]
+static CONN: Lazy<Mutex<Connection>> =
+    Lazy::new(|| Mutex::new(Connection::open_in_memory().expect("Error opening plot database!")));
+
 fn lock<'a>() -> MutexGuard<'a, Connection> {
     CONN.lock().unwrap()
 }
@@ -99,6 +105,108 @@ pub fn claim_plot(plot_x: i32, plot_z: i32, uuid: &str) {
     .unwrap();
 }
 
+/// Returns the uuids of the players trusted to build in a plot, not including the owner
+pub fn get_trusted_players(plot_x: i32, plot_z: i32) -> Vec<String> {
+    let conn = lock();
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT
+                    uuid
+                FROM
+                    plot
+                JOIN
+                    userplot ON userplot.plot_id = plot.id
+                JOIN
+                    user ON user.id = userplot.user_id
+                WHERE
+                    plot_x=?1
+                    AND plot_z=?2
+                    AND is_owner=FALSE",
+        )
+        .unwrap();
+    stmt.query_map(params![plot_x, plot_z], |row| row.get(0))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+}
+
+/// Lets a player build in a claimed plot.
+/// Returns false if the player has never joined the server or can already build there.
+pub fn trust_player(plot_x: i32, plot_z: i32, name: &str) -> bool {
+    let inserted = lock()
+        .execute(
+            "INSERT INTO userplot(user_id, plot_id, is_owner)
+                SELECT user.id, plot.id, FALSE
+                FROM user, plot
+                WHERE user.name = ?1 AND plot.plot_x = ?2 AND plot.plot_z = ?3
+                    AND NOT EXISTS(
+                        SELECT * FROM userplot WHERE user_id = user.id AND plot_id = plot.id
+                    )",
+            params![name, plot_x, plot_z],
+        )
+        .unwrap();
+    inserted > 0
+}
+
+/// Frees a claimed plot so that it can be claimed again
+pub fn unclaim_plot(plot_x: i32, plot_z: i32) {
+    let conn = lock();
+    conn.execute(
+        "DELETE FROM userplot
+            WHERE plot_id IN (SELECT id FROM plot WHERE plot_x = ?1 AND plot_z = ?2)",
+        params![plot_x, plot_z],
+    )
+    .unwrap();
+
+    conn.execute(
+        "DELETE FROM plot WHERE plot_x = ?1 AND plot_z = ?2",
+        params![plot_x, plot_z],
+    )
+    .unwrap();
+}
+
+/// Remembers who last wrote the sign at a position, so its commands can be checked against them
+pub fn set_sign_placer(x: i32, y: i32, z: i32, uuid: &str) {
+    lock()
+        .execute(
+            "INSERT INTO sign(x, y, z, placer)
+                VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (x, y, z) DO UPDATE SET placer = ?4",
+            params![x, y, z, uuid],
+        )
+        .unwrap();
+}
+
+pub fn delete_sign_placer(x: i32, y: i32, z: i32) {
+    lock()
+        .execute(
+            "DELETE FROM sign WHERE x = ?1 AND y = ?2 AND z = ?3",
+            params![x, y, z],
+        )
+        .unwrap();
+}
+
+/// Deletes the placers of every sign in the columns between `(min_x, min_z)` and
+/// `(max_x, max_z)`, inclusive
+pub fn delete_sign_placers(min_x: i32, min_z: i32, max_x: i32, max_z: i32) {
+    lock()
+        .execute(
+            "DELETE FROM sign WHERE x BETWEEN ?1 AND ?3 AND z BETWEEN ?2 AND ?4",
+            params![min_x, min_z, max_x, max_z],
+        )
+        .unwrap();
+}
+
+pub fn get_sign_placer(x: i32, y: i32, z: i32) -> Option<String> {
+    lock()
+        .query_row(
+            "SELECT placer FROM sign WHERE x = ?1 AND y = ?2 AND z = ?3",
+            params![x, y, z],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+}
+
 pub fn ensure_user(uuid: &str, name: &str) {
     lock()
         .execute(
@@ -144,4 +252,16 @@ pub fn init() {
         [],
     )
     .unwrap();
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sign(
+            x INTEGER NOT NULL,
+            y INTEGER NOT NULL,
+            z INTEGER NOT NULL,
+            placer BLOB(16) NOT NULL,
+            PRIMARY KEY(x, y, z)
+        )",
+        [],
+    )
+    .unwrap();
 }
@@ -1,36 +1,44 @@
+mod clock_detector;
 pub mod commands;
 mod data;
 pub mod database;
 mod monitor;
 mod packet_handlers;
 mod scoreboard;
+mod snapshot;
+#[cfg(test)]
+mod tests;
 pub mod worldedit;
 
 use crate::chat::ChatComponent;
 use crate::config::CONFIG;
-use crate::player::{EntityId, Gamemode, PacketSender, Player, PlayerPos};
+use crate::interaction;
+use crate::player::{EntityId, Gamemode, PacketSender, Player, PlayerPos, ENTITY_IDS};
 use crate::redpiler::{Compiler, CompilerOptions};
 use crate::redstone;
 use crate::server::{BroadcastMessage, Message, PrivMessage};
-use crate::utils::HyphenatedUUID;
-use crate::world::storage::Chunk;
-use crate::world::World;
+use crate::utils::{world_dir, HyphenatedUUID};
+use crate::watchdog::Heartbeat;
+use crate::world::storage::{Chunk, StorageKind};
+use crate::world::{for_each_block_optimized, World};
 use anyhow::Context;
 use bus::BusReader;
-use mchprs_blocks::block_entities::BlockEntity;
+use clock_detector::ClockDetector;
+use mchprs_blocks::block_entities::{BlockEntity, SignBlockEntity};
 use mchprs_blocks::blocks::Block;
-use mchprs_blocks::{BlockFace, BlockPos};
+use mchprs_blocks::{BlockColorVariant, BlockFace, BlockPos, SignRotation, SignType};
 use mchprs_network::packets::clientbound::*;
 use mchprs_network::packets::SlotData;
 use mchprs_network::PlayerPacketSender;
 use mchprs_save_data::plot_data::{ChunkData, PlotData, Tps, WorldSendRate};
 use mchprs_world::{TickEntry, TickPriority};
 use monitor::TimingsMonitor;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use scoreboard::RedpilerState;
 use serde_json::json;
 use std::cmp::Ordering;
-use std::collections::HashSet;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
 use std::time::{Duration, Instant};
@@ -39,6 +47,7 @@ use tracing::{debug, error, warn};
 
 use self::data::sleep_time_for_tps;
 use self::scoreboard::Scoreboard;
+use self::snapshot::RedstoneSnapshot;
 
 /// The width of a plot (2^n)
 pub const PLOT_SCALE: u32 = 4;
@@ -53,6 +62,13 @@ pub const NUM_CHUNKS: usize = PLOT_WIDTH.pow(2) as usize;
 pub const PLOT_SECTIONS: usize = 16;
 /// The plot height in blocks
 pub const PLOT_BLOCK_HEIGHT: i32 = PLOT_SECTIONS as i32 * 16;
+/// The number of layers in a new plot's floor
+pub const PLOT_FLOOR_LAYERS: i32 = 8;
+
+/// The most ticks `/fixredstone` will run while waiting for a circuit to settle
+const FIX_REDSTONE_MAX_TICKS: u32 = 1000;
+/// The number of characters that fit on one row of a sign
+const LABEL_ROW_WIDTH: usize = 15;
 
 pub struct Plot {
     pub world: PlotWorld,
@@ -85,10 +101,56 @@ pub struct Plot {
     /// If true, the plot will remain running even if no players are on for a long time.
     always_running: bool,
     auto_redpiler: bool,
+    /// While frozen, the plot will not tick on its own. It can still be advanced with `/radv`.
+    frozen: bool,
 
     owner: Option<u128>,
+    /// Players allowed to build in this plot as well as the owner
+    trusted: HashSet<u128>,
+    /// Where players are sent when they respawn in this plot
+    spawn: BlockPos,
     async_rt: Runtime,
     scoreboard: Scoreboard,
+    /// Redstone states saved with `/save-state`, these are lost when the plot unloads
+    snapshots: HashMap<String, RedstoneSnapshot>,
+    /// Positions of the components shown as powered by `/debug power on`
+    power_overlay: Vec<BlockPos>,
+    tick_warp: Option<TickWarp>,
+}
+
+/// A `/tick warp` that is in progress. Warped ticks are run in batches between plot updates,
+/// so the plot keeps handling packets and commands and the warp can be cancelled.
+struct TickWarp {
+    ticks: u64,
+    remaining: u64,
+    start_time: Instant,
+}
+
+impl TickWarp {
+    /// How long a single batch of warped ticks may run before the plot handles everything else
+    const BATCH_TIME: Duration = Duration::from_millis(50);
+
+    fn new(ticks: u64) -> TickWarp {
+        TickWarp {
+            ticks,
+            remaining: ticks,
+            start_time: Instant::now(),
+        }
+    }
+
+    /// Runs ticks until the warp is done or the batch has run for longer than `budget`.
+    /// Returns true once every tick of the warp has been run.
+    fn run_batch(&mut self, budget: Duration, mut tick: impl FnMut()) -> bool {
+        let batch_start = Instant::now();
+        while self.remaining > 0 {
+            tick();
+            self.remaining -= 1;
+            if batch_start.elapsed() > budget {
+                break;
+            }
+        }
+        self.remaining == 0
+    }
 }
 
 pub struct PlotWorld {
@@ -97,9 +159,31 @@ pub struct PlotWorld {
     pub chunks: Vec<Chunk>,
     pub to_be_ticked: Vec<TickEntry>,
     pub packet_senders: Vec<PlayerPacketSender>,
+    /// The most scheduled ticks that will be run in a single game tick.
+    /// Anything past this is deferred to the next tick.
+    pub tick_update_limit: usize,
+    /// Whether the last tick hit `tick_update_limit`. The warning is only logged when the
+    /// plot starts hitting the limit, not on every tick until it catches up.
+    tick_limit_reached: bool,
+    /// Set by `/instantredstone`. When true, every scheduled tick runs on the next tick,
+    /// ignoring repeater, torch, comparator, and lamp delays.
+    pub instant_redstone: bool,
+    /// All random decisions made in the plot should use this, so that they can be reproduced
+    /// by setting `rng_seed`
+    pub rng: StdRng,
+    /// Counts block changes to warn about fast clocks
+    pub clock_detector: ClockDetector,
 }
 
 impl PlotWorld {
+    /// Creates the random number generator for a plot from a seed, or randomly if there is none
+    pub fn create_rng(seed: Option<i64>) -> StdRng {
+        match seed {
+            Some(seed) => StdRng::seed_from_u64(seed as u64),
+            None => StdRng::from_entropy(),
+        }
+    }
+
     fn get_chunk_index_for_chunk(&self, chunk_x: i32, chunk_z: i32) -> usize {
         let local_x = chunk_x - self.x * PLOT_WIDTH;
         let local_z = chunk_z - self.z * PLOT_WIDTH;
@@ -127,6 +211,177 @@ impl PlotWorld {
         }
     }
 
+    /// Runs all scheduled ticks that are due, up to `tick_update_limit`.
+    /// Returns the number of scheduled ticks that were run.
+    fn run_scheduled_ticks(&mut self) -> usize {
+        self.to_be_ticked
+            .sort_by_key(|e| (e.ticks_left, e.tick_priority));
+        for pending in &mut self.to_be_ticked {
+            pending.ticks_left = pending.ticks_left.saturating_sub(1);
+        }
+        let mut processed = 0;
+        let mut limit_reached = false;
+        while self.to_be_ticked.first().map_or(1, |e| e.ticks_left) == 0 {
+            if processed == self.tick_update_limit {
+                // The remaining entries keep `ticks_left == 0` so they will be first in line next tick
+                if !self.tick_limit_reached {
+                    warn!(
+                        "Plot {},{} hit the limit of {} scheduled ticks in one tick, deferring the rest",
+                        self.x, self.z, self.tick_update_limit
+                    );
+                }
+                limit_reached = true;
+                break;
+            }
+            let entry = self.to_be_ticked.remove(0);
+            redstone::tick(self.get_block(entry.pos), self, entry.pos);
+            processed += 1;
+        }
+        self.tick_limit_reached = limit_reached;
+        processed
+    }
+
+    /// Returns the position of every redstone component in the plot that is currently powered
+    fn powered_components(&self) -> Vec<BlockPos> {
+        let (first_pos, second_pos) = self.get_corners();
+        let mut powered = Vec::new();
+        for_each_block_optimized(self, first_pos, second_pos, |pos| {
+            if redstone::is_component_powered(self.get_block(pos)) {
+                powered.push(pos);
+            }
+        });
+        powered
+    }
+
+    /// Updates every redstone component between `first_pos` and `second_pos` so that states left
+    /// inconsistent by imports or manual edits are recalculated, then runs scheduled ticks until
+    /// the circuit settles or `FIX_REDSTONE_MAX_TICKS` is reached. Anything outside the plot is ignored.
+    /// Components are updated in dependency order: dust first, then the components that read
+    /// from it, then outputs. Returns the number of components updated and whether the circuit settled.
+    fn fix_redstone(&mut self, first_pos: BlockPos, second_pos: BlockPos) -> (usize, bool) {
+        let (plot_min, plot_max) = self.get_corners();
+        let first_pos = first_pos.max(plot_min).min(plot_max);
+        let second_pos = second_pos.max(plot_min).min(plot_max);
+        let mut stages: [Vec<BlockPos>; 3] = Default::default();
+        for_each_block_optimized(self, first_pos, second_pos, |pos| {
+            let stage = match self.get_block(pos) {
+                Block::RedstoneWire { .. } => 0,
+                Block::RedstoneTorch { .. }
+                | Block::RedstoneWallTorch { .. }
+                | Block::RedstoneRepeater { .. }
+                | Block::RedstoneComparator { .. } => 1,
+                Block::RedstoneLamp { .. }
+                | Block::IronTrapdoor { .. }
+                | Block::NoteBlock { .. } => 2,
+                _ => return,
+            };
+            stages[stage].push(pos);
+        });
+
+        for pos in stages.iter().flatten() {
+            redstone::update(self.get_block(*pos), self, *pos);
+        }
+        let mut ticks = 0;
+        while !self.to_be_ticked.is_empty() && ticks < FIX_REDSTONE_MAX_TICKS {
+            self.run_scheduled_ticks();
+            ticks += 1;
+        }
+        (
+            stages.iter().map(Vec::len).sum(),
+            self.to_be_ticked.is_empty(),
+        )
+    }
+
+    /// Updates only the component at `pos`, as if one of its neighbors had changed.
+    /// Its neighbors are not updated unless the component itself changes.
+    fn neighbor_update(&mut self, pos: BlockPos) {
+        redstone::update(self.get_block(pos), self, pos);
+    }
+
+    /// Places a sign at `pos` showing `text`, wrapped on word boundaries across the sign's rows.
+    /// The label is stored as a regular sign block entity so it is saved with the plot.
+    /// Returns false without placing anything if the text doesn't fit on a sign.
+    fn place_label(&mut self, pos: BlockPos, text: &str) -> bool {
+        let mut lines: Vec<String> = Vec::new();
+        for word in text.split_whitespace() {
+            match lines.last_mut() {
+                Some(line) if line.len() + 1 + word.len() <= LABEL_ROW_WIDTH => {
+                    line.push(' ');
+                    line.push_str(word);
+                }
+                _ => lines.push(word.to_owned()),
+            }
+        }
+        if lines.len() > 4 || lines.iter().any(|line| line.len() > LABEL_ROW_WIDTH) {
+            return false;
+        }
+
+        let mut rows = lines
+            .into_iter()
+            .chain(std::iter::repeat(String::new()))
+            .map(|line| json!({ "text": line }).to_string());
+        let block_entity = BlockEntity::Sign(Box::new(SignBlockEntity {
+            rows: [
+                rows.next().unwrap(),
+                rows.next().unwrap(),
+                rows.next().unwrap(),
+                rows.next().unwrap(),
+            ],
+        }));
+        self.set_block(
+            pos,
+            Block::Sign {
+                sign_type: SignType(0),
+                rotation: SignRotation(0),
+            },
+        );
+        // Send block changes before we send block entity data, otherwise it'll be ignored
+        self.flush_block_changes();
+        self.set_block_entity(pos, block_entity);
+        true
+    }
+
+    /// Lays the floor of a new plot and marks its perimeter with `border`. Anything already
+    /// in the floor layers is replaced, so running this again doesn't change anything.
+    pub fn generate_borders(&mut self, floor: Block, border: Block) {
+        let (first_pos, second_pos) = self.get_corners();
+        let floor_top = BlockPos::new(second_pos.x, PLOT_FLOOR_LAYERS - 1, second_pos.z);
+        for pos in BlockPos::cuboid(first_pos, floor_top) {
+            let on_perimeter = pos.x == first_pos.x
+                || pos.x == second_pos.x
+                || pos.z == first_pos.z
+                || pos.z == second_pos.z;
+            self.set_block(pos, if on_perimeter { border } else { floor });
+        }
+    }
+
+    /// Resets the plot to a freshly generated one: everything is removed, including block
+    /// entities and pending ticks, and the floor and borders are laid again.
+    pub fn clear(&mut self, floor: Block, border: Block) {
+        let storage_kind = self.storage_kind();
+        for chunk in &mut self.chunks {
+            *chunk = Chunk::empty(chunk.x, chunk.z);
+        }
+        self.set_storage_kind(storage_kind);
+        self.to_be_ticked.clear();
+        self.generate_borders(floor, border);
+        // The whole plot is resent to players, so there's no need for these
+        for chunk in &mut self.chunks {
+            chunk.reset_multi_blocks();
+        }
+    }
+
+    /// How the blocks of this plot are kept in memory. Every chunk uses the same storage.
+    pub fn storage_kind(&self) -> StorageKind {
+        self.chunks[0].sections[0].storage_kind()
+    }
+
+    pub fn set_storage_kind(&mut self, kind: StorageKind) {
+        for chunk in &mut self.chunks {
+            chunk.set_storage_kind(kind);
+        }
+    }
+
     pub fn get_corners(&self) -> (BlockPos, BlockPos) {
         const W: i32 = PLOT_BLOCK_WIDTH;
         let first_pos = BlockPos::new(self.x * W, 0, self.z * W);
@@ -153,12 +408,16 @@ impl World for PlotWorld {
         }
 
         let chunk = &mut self.chunks[chunk_index];
-        chunk.set_block(
+        let changed = chunk.set_block(
             (pos.x & 0xF) as u32,
             pos.y as u32,
             (pos.z & 0xF) as u32,
             block,
-        )
+        );
+        if changed {
+            self.clock_detector.record(pos);
+        }
+        changed
     }
 
     /// Returns the block state id of the block at `pos`
@@ -224,7 +483,11 @@ impl World for PlotWorld {
     fn schedule_tick(&mut self, pos: BlockPos, delay: u32, priority: TickPriority) {
         self.to_be_ticked.push(TickEntry {
             pos,
-            ticks_left: delay,
+            ticks_left: if self.instant_redstone {
+                delay.min(1)
+            } else {
+                delay
+            },
             tick_priority: priority,
         });
     }
@@ -258,6 +521,22 @@ impl World for PlotWorld {
             player.send_packet(&sound_effect_data);
         }
     }
+
+    fn block_action(&mut self, pos: BlockPos, action_id: u8, action_param: u8, block_type: i32) {
+        let block_action_data = CBlockAction {
+            x: pos.x,
+            y: pos.y,
+            z: pos.z,
+            action_id,
+            action_param,
+            block_type,
+        }
+        .encode();
+
+        for player in &self.packet_senders {
+            player.send_packet(&block_action_data);
+        }
+    }
 }
 
 impl Plot {
@@ -268,15 +547,19 @@ impl Plot {
             return;
         }
 
-        self.world
-            .to_be_ticked
-            .sort_by_key(|e| (e.ticks_left, e.tick_priority));
-        for pending in &mut self.world.to_be_ticked {
-            pending.ticks_left = pending.ticks_left.saturating_sub(1);
-        }
-        while self.world.to_be_ticked.first().map_or(1, |e| e.ticks_left) == 0 {
-            let entry = self.world.to_be_ticked.remove(0);
-            redstone::tick(self.world.get_block(entry.pos), &mut self.world, entry.pos);
+        self.world.run_scheduled_ticks();
+        let threshold = CONFIG.clock_warning_threshold.max(0) as u64;
+        if let Some(pos) = self.world.clock_detector.check(Instant::now(), threshold) {
+            warn!(
+                "Plot {},{} has been running over {} block updates per second for {} seconds, there is probably a fast clock near {}, {}, {}",
+                self.world.x,
+                self.world.z,
+                threshold,
+                clock_detector::CLOCK_WARNING_SECONDS,
+                pos.x,
+                pos.y,
+                pos.z
+            );
         }
     }
 
@@ -294,6 +577,25 @@ impl Plot {
         }
     }
 
+    /// Shows every powered component as lime stained glass to the players in the plot.
+    /// This only changes what is sent to the clients, the world itself is left untouched.
+    fn set_power_overlay(&mut self, enabled: bool) {
+        // Clear the previous overlay first so that turning it on again refreshes it
+        for pos in std::mem::take(&mut self.power_overlay) {
+            let id = self.world.get_block_raw(pos);
+            self.send_block_change(pos, id);
+        }
+        if enabled {
+            self.power_overlay = self.world.powered_components();
+            let overlay_block = Block::StainedGlass {
+                color: BlockColorVariant::Lime,
+            };
+            for pos in self.power_overlay.clone() {
+                self.send_block_change(pos, overlay_block.get_id());
+            }
+        }
+    }
+
     pub fn broadcast_chat_message(&mut self, message: String) {
         let broadcast_message = Message::ChatInfo(
             0,
@@ -450,6 +752,17 @@ impl Plot {
             "Entering plot ({}, {})",
             self.world.x, self.world.z
         ));
+        if self.frozen {
+            player.send_system_message("This plot is frozen. Use /freeze to unfreeze it.");
+        }
+        let spawn_position = CSpawnPosition {
+            x: self.spawn.x,
+            y: self.spawn.y,
+            z: self.spawn.z,
+            angle: 0.0,
+        }
+        .encode();
+        player.client.send_packet(&spawn_position);
         self.world
             .packet_senders
             .push(PlayerPacketSender::new(&player.client));
@@ -469,64 +782,85 @@ impl Plot {
         player_idx: usize,
         chunk_x: i32,
         chunk_z: i32,
-        was_loaded: bool,
         should_be_loaded: bool,
     ) {
-        if was_loaded && !should_be_loaded {
-            let unload_chunk = CUnloadChunk { chunk_x, chunk_z }.encode();
-            self.players[player_idx].client.send_packet(&unload_chunk);
-        } else if !was_loaded && should_be_loaded {
+        let player = &mut self.players[player_idx];
+        if !should_be_loaded {
+            if player.loaded_chunks.remove(&(chunk_x, chunk_z)) {
+                let unload_chunk = CUnloadChunk { chunk_x, chunk_z }.encode();
+                player.client.send_packet(&unload_chunk);
+            }
+        } else if player.loaded_chunks.insert((chunk_x, chunk_z)) {
             if !Plot::chunk_in_plot_bounds(self.world.x, self.world.z, chunk_x, chunk_z) {
-                self.players[player_idx]
+                player
                     .client
                     .send_packet(&Chunk::encode_empty_packet(chunk_x, chunk_z));
             } else {
                 let chunk_data = self.world.chunks
                     [self.world.get_chunk_index_for_chunk(chunk_x, chunk_z)]
                 .encode_packet();
-                self.players[player_idx].client.send_packet(&chunk_data);
+                player.client.send_packet(&chunk_data);
+            }
+        }
+    }
+
+    /// Returns the chunks that need to be loaded and unloaded for a client that has the
+    /// `loaded` chunks when its view is centered on the chunk `current`, as `(load, unload)`.
+    fn chunk_view_changes(
+        loaded: &HashSet<(i32, i32)>,
+        current: (i32, i32),
+        view_distance: i32,
+    ) -> (Vec<(i32, i32)>, Vec<(i32, i32)>) {
+        let (chunk_x, chunk_z) = current;
+        let unload = loaded
+            .iter()
+            .copied()
+            .filter(|&(x, z)| {
+                Self::get_chunk_distance(x, z, chunk_x, chunk_z) > view_distance as u32
+            })
+            .collect();
+        let mut load = Vec::new();
+        for x in chunk_x - view_distance..=chunk_x + view_distance {
+            for z in chunk_z - view_distance..=chunk_z + view_distance {
+                if !loaded.contains(&(x, z)) {
+                    load.push((x, z));
+                }
             }
         }
+        (load, unload)
     }
 
+    /// Sends the chunks around the player that its client doesn't have yet and unloads the
+    /// ones that went out of view. If `force_load` is set, every chunk the client has is
+    /// unloaded first so that the whole view is sent again.
     pub fn update_view_pos_for_player(&mut self, player_idx: usize, force_load: bool) {
         let view_distance = CONFIG.view_distance as i32;
         let (chunk_x, chunk_z) = self.players[player_idx].pos.chunk_pos();
-        let last_chunk_x = self.players[player_idx].last_chunk_x;
-        let last_chunk_z = self.players[player_idx].last_chunk_z;
 
         let update_view = CUpdateViewPosition { chunk_x, chunk_z }.encode();
         self.players[player_idx].client.send_packet(&update_view);
 
-        if ((last_chunk_x - chunk_x).abs() <= view_distance * 2
-            && (last_chunk_z - chunk_z).abs() <= view_distance * 2)
-            && !force_load
-        {
-            let nx = chunk_x.min(last_chunk_x) - view_distance;
-            let nz = chunk_z.min(last_chunk_z) - view_distance;
-            let px = chunk_x.max(last_chunk_x) + view_distance;
-            let pz = chunk_z.max(last_chunk_z) + view_distance;
-            for x in nx..=px {
-                for z in nz..=pz {
-                    let was_loaded = Self::get_chunk_distance(x, z, last_chunk_x, last_chunk_z)
-                        <= view_distance as u32;
-                    let should_be_loaded =
-                        Self::get_chunk_distance(x, z, chunk_x, chunk_z) <= view_distance as u32;
-                    self.set_chunk_loaded_at_player(player_idx, x, z, was_loaded, should_be_loaded);
-                }
-            }
-        } else {
-            for x in last_chunk_x - view_distance..=last_chunk_x + view_distance {
-                for z in last_chunk_z - view_distance..=last_chunk_z + view_distance {
-                    self.set_chunk_loaded_at_player(player_idx, x, z, true, false);
-                }
-            }
-            for x in chunk_x - view_distance..=chunk_x + view_distance {
-                for z in chunk_z - view_distance..=chunk_z + view_distance {
-                    self.set_chunk_loaded_at_player(player_idx, x, z, false, true);
-                }
+        if force_load {
+            let loaded: Vec<_> = self.players[player_idx]
+                .loaded_chunks
+                .iter()
+                .copied()
+                .collect();
+            for (x, z) in loaded {
+                self.set_chunk_loaded_at_player(player_idx, x, z, false);
             }
         }
+        let (load, unload) = Self::chunk_view_changes(
+            &self.players[player_idx].loaded_chunks,
+            (chunk_x, chunk_z),
+            view_distance,
+        );
+        for (x, z) in unload {
+            self.set_chunk_loaded_at_player(player_idx, x, z, false);
+        }
+        for (x, z) in load {
+            self.set_chunk_loaded_at_player(player_idx, x, z, true);
+        }
         self.players[player_idx].last_chunk_x = chunk_x;
         self.players[player_idx].last_chunk_z = chunk_z;
     }
@@ -644,12 +978,73 @@ impl Plot {
     }
 
     fn in_plot_bounds(plot_x: i32, plot_z: i32, x: i32, z: i32) -> bool {
-        Plot::chunk_in_plot_bounds(plot_x, plot_z, x >> 4, z >> 4)
+        Plot::get_plot_at(x, z) == (plot_x, plot_z)
+    }
+
+    /// Returns the coordinates of the plot in the grid that contains the block column
+    pub fn get_plot_at(x: i32, z: i32) -> (i32, i32) {
+        ((x >> 4) >> PLOT_SCALE, (z >> 4) >> PLOT_SCALE)
+    }
+
+    fn get_trusted(plot_x: i32, plot_z: i32) -> HashSet<u128> {
+        database::get_trusted_players(plot_x, plot_z)
+            .iter()
+            .map(|s| s.parse::<HyphenatedUUID>().unwrap().0)
+            .collect()
+    }
+
+    /// Whether the player is allowed to place and break blocks in this plot
+    pub fn can_build(&self, player: usize) -> bool {
+        let player = &self.players[player];
+        match self.owner {
+            Some(owner) => {
+                owner == player.uuid
+                    || self.trusted.contains(&player.uuid)
+                    || player.has_permission("plots.admin.interact.other")
+            }
+            None => player.has_permission("plots.admin.interact.unowned"),
+        }
+    }
+
+    /// Runs the lines of the command sign at `pos` as the player that wrote it, so a sign
+    /// can never do more than its writer could. The writer has to be in the plot and still
+    /// be able to build here. Returns false if there is no command sign there.
+    fn use_command_sign(&mut self, player: usize, pos: BlockPos) -> bool {
+        let commands = match self.world.get_block_entity(pos) {
+            Some(BlockEntity::Sign(sign)) => interaction::command_sign_commands(sign),
+            _ => None,
+        };
+        let Some(commands) = commands else {
+            return false;
+        };
+
+        let writer = database::get_sign_placer(pos.x, pos.y, pos.z)
+            .map(|uuid| uuid.parse::<HyphenatedUUID>().unwrap().0)
+            .and_then(|writer| self.players.iter().position(|p| p.uuid == writer));
+        match writer {
+            Some(writer) if self.can_build(writer) => {
+                self.players[writer].command_queue.extend(commands);
+            }
+            Some(_) => self.players[player]
+                .send_error_message("The player that wrote this sign can't build here anymore."),
+            None => self.players[player]
+                .send_error_message("The player that wrote this sign has to be in this plot."),
+        }
+        true
+    }
+
+    /// Forgets who wrote the signs in this plot, for when they are all gone
+    fn forget_sign_placers(&self) {
+        let (first, second) = self.world.get_corners();
+        database::delete_sign_placers(first.x, first.z, second.x, second.z);
     }
 
     pub fn claim_plot(&mut self, plot_x: i32, plot_z: i32, player: usize) {
         let player = &mut self.players[player];
         database::claim_plot(plot_x, plot_z, &format!("{:032x}", player.uuid));
+        if (plot_x, plot_z) == (self.world.x, self.world.z) {
+            self.owner = Some(player.uuid);
+        }
         let center = Plot::get_center(plot_x, plot_z);
         player.teleport(PlayerPos::new(center.0, 64.0, center.1));
         player.send_system_message(&format!("Claimed plot {},{}", plot_x, plot_z));
@@ -663,6 +1058,22 @@ impl Plot {
         )
     }
 
+    /// The default spawn position of a plot is on the ground in the center of the plot
+    pub fn get_spawn(plot_x: i32, plot_z: i32) -> BlockPos {
+        let (x, z) = Plot::get_center(plot_x, plot_z);
+        BlockPos::new(x as i32, 64, z as i32)
+    }
+
+    /// Sends the player back to the plot spawn
+    fn respawn_player(&mut self, player_idx: usize) {
+        let spawn = self.spawn;
+        self.players[player_idx].teleport(PlayerPos::new(
+            spawn.x as f64,
+            spawn.y as f64,
+            spawn.z as f64,
+        ));
+    }
+
     pub fn get_next_plot(plot_x: i32, plot_z: i32) -> (i32, i32) {
         let x = plot_x.abs();
         let z = plot_z.abs();
@@ -709,6 +1120,22 @@ impl Plot {
         }
     }
 
+    /// Saves and kicks every player and stops the plot once the current update is done
+    fn shutdown(&mut self) {
+        let mut players: Vec<Player> = self.players.drain(..).collect();
+        for player in players.iter_mut() {
+            player.save();
+            player.kick(
+                json!({
+                    "text": "Server closed"
+                })
+                .to_string(),
+            );
+        }
+        self.always_running = false;
+        self.running = false;
+    }
+
     fn handle_messages(&mut self) {
         while let Ok(message) = self.message_receiver.try_recv() {
             match message {
@@ -738,18 +1165,7 @@ impl Plot {
                     }
                 }
                 BroadcastMessage::Shutdown => {
-                    let mut players: Vec<Player> = self.players.drain(..).collect();
-                    for player in players.iter_mut() {
-                        player.save();
-                        player.kick(
-                            json!({
-                                "text": "Server closed"
-                            })
-                            .to_string(),
-                        );
-                    }
-                    self.always_running = false;
-                    self.running = false;
+                    self.shutdown();
                     return;
                 }
                 BroadcastMessage::PlayerUpdateGamemode(uuid, gamemode) => {
@@ -772,6 +1188,10 @@ impl Plot {
                     }
                     self.enter_plot(player);
                 }
+                PrivMessage::Shutdown => {
+                    self.shutdown();
+                    return;
+                }
             }
         }
     }
@@ -799,22 +1219,24 @@ impl Plot {
 
     /// Remove disconnected players
     fn remove_dc_players(&mut self) {
-        let message_sender = &mut self.message_sender;
-
         let mut disconnected_players = Vec::new();
-        self.players.retain(|player| {
-            let alive = player.client.alive();
-            if !alive {
-                player.save();
-                message_sender
-                    .send(Message::PlayerLeft(player.uuid))
-                    .unwrap();
-                disconnected_players.push(player.entity_id);
+        // Going backwards keeps the indices of the players that are left the same
+        for player_idx in (0..self.players.len()).rev() {
+            if self.players[player_idx].client.alive() {
+                continue;
             }
-            alive
-        });
+            // The packet senders are kept in the same order as the players
+            self.world.packet_senders.remove(player_idx);
+            let player = self.players.remove(player_idx);
+            player.save();
+            self.message_sender
+                .send(Message::PlayerLeft(player.uuid))
+                .unwrap();
+            disconnected_players.push(player.entity_id);
+        }
         for entity_id in disconnected_players {
             self.destroy_entity(entity_id);
+            ENTITY_IDS.lock().unwrap().free(entity_id);
         }
     }
 
@@ -866,7 +1288,26 @@ impl Plot {
             };
 
             self.last_update_time = now;
-            if batch_size != 0 {
+            if let Some(mut warp) = self.tick_warp.take() {
+                let finished = warp.run_batch(TickWarp::BATCH_TIME, || self.tick());
+                if self.redpiler.is_active() {
+                    self.redpiler.flush(&mut self.world);
+                }
+                if finished {
+                    let message = format!(
+                        "Warped {} ticks ({:?})",
+                        warp.ticks,
+                        warp.start_time.elapsed()
+                    );
+                    for player in &self.players {
+                        player.send_system_message(&message);
+                    }
+                    // Don't try to catch up on the time spent warping
+                    self.reset_timings();
+                } else {
+                    self.tick_warp = Some(warp);
+                }
+            } else if batch_size != 0 && !self.frozen {
                 // 50_000 (= 3.33 MHz) here is arbitrary.
                 // We just need a number that's not too high so we actually get around to sending block updates.
                 let batch_size = batch_size.min(50_000) as u32;
@@ -889,6 +1330,8 @@ impl Plot {
             }
 
             if self.auto_redpiler
+                && !self.frozen
+                && !self.world.instant_redstone
                 && !self.redpiler.is_active()
                 && (self.tps == Tps::Unlimited || self.timings.is_running_behind())
             {
@@ -923,30 +1366,6 @@ impl Plot {
         Runtime::new().unwrap()
     }
 
-    fn generate_chunk(layers: i32, x: i32, z: i32) -> Chunk {
-        let mut chunk = Chunk::empty(x, z);
-
-        for ry in 0..layers {
-            for rx in 0..16 {
-                for rz in 0..16 {
-                    let block_x = (x << 4) | rx;
-                    let block_z = (z << 4) | rz;
-
-                    if block_x % PLOT_BLOCK_WIDTH == 0
-                        || block_z % PLOT_BLOCK_WIDTH == 0
-                        || (block_x + 1) % PLOT_BLOCK_WIDTH == 0
-                        || (block_z + 1) % PLOT_BLOCK_WIDTH == 0
-                    {
-                        chunk.set_block(rx as u32, ry as u32, rz as u32, 4564); // Stone Bricks
-                    } else {
-                        chunk.set_block(rx as u32, ry as u32, rz as u32, 278); // Sandstone
-                    }
-                }
-            }
-        }
-        chunk
-    }
-
     fn from_data(
         plot_data: PlotData<PLOT_SECTIONS>,
         x: i32,
@@ -975,13 +1394,19 @@ impl Plot {
             let possible_scale = (chunks.len() as f64).sqrt().log2();
             error!("Note: it most likely came from a server running plot scale {}, this server is running a plot scale of {}", possible_scale, PLOT_SCALE);
         }
-        let world = PlotWorld {
+        let mut world = PlotWorld {
             x,
             z,
             chunks,
             to_be_ticked: plot_data.pending_ticks,
             packet_senders: Vec::new(),
+            tick_update_limit: CONFIG.tick_update_limit as usize,
+            tick_limit_reached: false,
+            instant_redstone: false,
+            rng: PlotWorld::create_rng(CONFIG.rng_seed),
+            clock_detector: Default::default(),
         };
+        world.set_storage_kind(plot_data.storage);
         let tps = plot_data.tps;
         let world_send_rate = plot_data.world_send_rate;
         Plot {
@@ -998,14 +1423,20 @@ impl Plot {
             locked_players: HashSet::new(),
             running: true,
             auto_redpiler: CONFIG.auto_redpiler,
+            frozen: false,
             tps,
             world_send_rate,
             always_running,
             redpiler: Default::default(),
             timings: TimingsMonitor::new(tps),
+            spawn: Plot::get_spawn(x, z),
             owner: database::get_plot_owner(x, z).map(|s| s.parse::<HyphenatedUUID>().unwrap().0),
+            trusted: Plot::get_trusted(x, z),
             async_rt: Plot::create_async_rt(),
             scoreboard: Default::default(),
+            snapshots: HashMap::new(),
+            power_overlay: Vec::new(),
+            tick_warp: None,
             world,
         }
     }
@@ -1018,12 +1449,15 @@ impl Plot {
         priv_rx: Receiver<PrivMessage>,
         always_running: bool,
     ) -> Plot {
-        let plot_path = format!("./world/plots/p{},{}", x, z);
-        if Path::new(&plot_path).exists() {
+        let plot_path = world_dir().join(format!("plots/p{},{}", x, z));
+        if plot_path.exists() {
             let data = data::load_plot(plot_path)
                 .with_context(|| format!("error loading plot {},{}", x, z))
                 .unwrap();
-            Plot::from_data(data, x, z, rx, tx, priv_rx, always_running)
+            let mut plot = Plot::from_data(data, x, z, rx, tx, priv_rx, always_running);
+            // Saved circuits stay exactly as they were until the plot is unfrozen
+            plot.frozen = CONFIG.freeze_on_load;
+            plot
         } else {
             Plot::from_data(data::empty_plot(), x, z, rx, tx, priv_rx, always_running)
         }
@@ -1038,14 +1472,15 @@ impl Plot {
             world_send_rate: self.world_send_rate,
             chunk_data,
             pending_ticks: world.to_be_ticked.clone(),
+            storage: world.storage_kind(),
         };
-        data.save_to_file(format!("./world/plots/p{},{}", world.x, world.z))
+        data.save_to_file(world_dir().join(format!("plots/p{},{}", world.x, world.z)))
             .unwrap();
 
         self.reset_timings();
     }
 
-    fn run(&mut self, initial_player: Option<Player>) {
+    fn run(&mut self, initial_player: Option<Player>, heartbeat: Heartbeat) {
         let _guard = self.async_rt.enter();
 
         if let Some(player) = initial_player {
@@ -1056,6 +1491,7 @@ impl Plot {
             // Fast path, for super high RTPS
             if self.sleep_time <= Duration::from_millis(5) && !self.players.is_empty() {
                 self.update();
+                heartbeat.beat();
                 if self.tps != Tps::Unlimited {
                     thread::yield_now();
                 }
@@ -1064,6 +1500,7 @@ impl Plot {
 
             let before = Instant::now();
             self.update();
+            heartbeat.beat();
             let delta = Instant::now().duration_since(before);
 
             if delta < self.sleep_time {
@@ -1075,12 +1512,14 @@ impl Plot {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn load_and_run(
         x: i32,
         z: i32,
         rx: BusReader<BroadcastMessage>,
         tx: Sender<Message>,
         priv_rx: Receiver<PrivMessage>,
+        heartbeat: Heartbeat,
         always_running: bool,
         initial_player: Option<Player>,
     ) {
@@ -1088,7 +1527,7 @@ impl Plot {
             .name(format!("p{},{}", x, z))
             .spawn(move || {
                 let mut plot = Plot::load(x, z, rx, tx, priv_rx, always_running);
-                plot.run(initial_player);
+                plot.run(initial_player, heartbeat);
             })
             .unwrap();
     }
@@ -1139,15 +1578,3 @@ impl Drop for Plot {
             .unwrap();
     }
 }
-
-#[test]
-fn chunk_save_and_load_test() {
-    let mut chunk = Chunk::empty(1, 1);
-    chunk.set_block(13, 63, 12, 332);
-    chunk.set_block(13, 62, 12, 331);
-    let chunk_data = chunk.save();
-    let loaded_chunk = Chunk::load(1, 1, chunk_data);
-    assert_eq!(loaded_chunk.get_block(13, 63, 12), 332);
-    assert_eq!(loaded_chunk.get_block(13, 62, 12), 331);
-    assert_eq!(loaded_chunk.get_block(13, 64, 12), 0);
-}
@@ -1,4 +1,4 @@
-use super::Plot;
+use super::{database, Plot};
 use crate::config::CONFIG;
 use crate::interaction::{self, UseOnBlockContext};
 use crate::player::{PacketSender, PlayerPos, SkinParts};
@@ -15,11 +15,24 @@ use mchprs_network::packets::SlotData;
 use serde_json::json;
 use std::fs;
 use std::path::PathBuf;
-use std::time::Instant;
 use tracing::error;
 
+/// The furthest the center of a block can be from a player's eyes for them to place against it.
+/// Creative reach is 5 blocks to the face of a block, so this leaves room for the block itself.
+const MAX_REACH: f64 = 6.0;
+
 const ERROR_IO_ONLY: &str = "This plot cannot be interacted with while redpiler is active with `--io-only`. To stop redpiler, run `/redpiler reset`.";
 
+/// Maps the hand from a serverbound Animation packet to the clientbound Entity Animation id
+fn swing_animation_id(hand: i32) -> u8 {
+    match hand {
+        // Swing off hand
+        1 => 3,
+        // Swing main arm
+        _ => 0,
+    }
+}
+
 impl Plot {
     pub(super) fn handle_packets_for_player(&mut self, player: usize) {
         let packets = self.players[player].client.receive_packets();
@@ -78,8 +91,29 @@ impl ServerBoundPacketHandler for Plot {
         self.players[player_idx].send_packet(&res.encode());
     }
 
-    fn handle_keep_alive(&mut self, _keep_alive: SKeepAlive, player_idx: usize) {
-        self.players[player_idx].last_keep_alive_received = Instant::now();
+    fn handle_client_status(&mut self, client_status: SClientStatus, player_idx: usize) {
+        if client_status.action == ClientStatusAction::PerformRespawn {
+            self.respawn_player(player_idx);
+        }
+        if let Some(statistics) = client_status_response(client_status.action) {
+            self.players[player_idx].send_packet(&statistics.encode());
+        }
+    }
+
+    fn handle_teleport_confirm(&mut self, teleport_confirm: STeleportConfirm, player: usize) {
+        // Movement is accepted again once the newest teleport is confirmed
+        self.players[player]
+            .teleports
+            .confirm(teleport_confirm.teleport_id);
+    }
+
+    fn handle_keep_alive(&mut self, keep_alive: SKeepAlive, player_idx: usize) {
+        let player = &mut self.players[player_idx];
+        if let Some(ping) = player.receive_keep_alive(keep_alive.id) {
+            let _ = self
+                .message_sender
+                .send(Message::PlayerPing(player.uuid, ping));
+        }
     }
 
     fn handle_creative_inventory_action(
@@ -139,14 +173,9 @@ impl ServerBoundPacketHandler for Plot {
     }
 
     fn handle_animation(&mut self, animation: SAnimation, player: usize) {
-        let animation_id = match animation.hand {
-            0 => 0,
-            1 => 3,
-            _ => 0,
-        };
         let entity_animation = CEntityAnimation {
             entity_id: self.players[player].entity_id as i32,
-            animation: animation_id,
+            animation: swing_animation_id(animation.hand),
         }
         .encode();
         for other_player in 0..self.players.len() {
@@ -193,6 +222,12 @@ impl ServerBoundPacketHandler for Plot {
             return;
         }
 
+        let player_pos = self.players[player].pos;
+        if !within_reach(player_pos, self.players[player].crouching, block_pos) {
+            cancel(self);
+            return;
+        }
+
         if let Some(item) = &item_in_hand {
             let has_permission = self.players[player].has_permission("worldedit.selection.pos");
             if item.item_type == (Item::WEWand {}) && has_permission {
@@ -209,23 +244,25 @@ impl ServerBoundPacketHandler for Plot {
             }
         }
 
-        if let Some(owner) = self.owner {
-            let player = &mut self.players[player];
-            if owner != player.uuid && !player.has_permission("plots.admin.interact.other") {
-                player.send_no_permission_message();
-                cancel(self);
-                return;
-            }
-        } else if !self.players[player].has_permission("plots.admin.interact.unowned") {
+        if !self.can_build(player) {
             self.players[player].send_no_permission_message();
             cancel(self);
             return;
         }
 
+        let uses_block = interaction::uses_block(
+            self.players[player].crouching,
+            self.players[player].holding_item(),
+        );
+        if uses_block && self.use_command_sign(player, block_pos) {
+            cancel(self);
+            return;
+        }
+
         if self.redpiler.is_active() {
             let block = self.world.get_block(block_pos);
             let lever_or_button = matches!(block, Block::Lever { .. } | Block::StoneButton { .. });
-            if lever_or_button && !self.players[player].crouching {
+            if lever_or_button && uses_block {
                 self.redpiler.on_use_block(block_pos);
                 return;
             } else {
@@ -249,7 +286,9 @@ impl ServerBoundPacketHandler for Plot {
                     block_face,
                     block_pos,
                     player: &mut self.players[player],
+                    cursor_x: player_block_placement.cursor_x,
                     cursor_y: player_block_placement.cursor_y,
+                    cursor_z: player_block_placement.cursor_z,
                 },
             );
             if cancelled {
@@ -259,17 +298,18 @@ impl ServerBoundPacketHandler for Plot {
             return;
         }
 
-        let block = self.world.get_block(block_pos);
-        if !self.players[player].crouching {
-            interaction::on_use(
-                block,
-                &mut self.world,
-                &mut self.players[player],
-                block_pos,
-                None,
-            );
-            self.world.flush_block_changes();
+        if !uses_block {
+            return;
         }
+        let block = self.world.get_block(block_pos);
+        interaction::on_use(
+            block,
+            &mut self.world,
+            &mut self.players[player],
+            block_pos,
+            None,
+        );
+        self.world.flush_block_changes();
     }
 
     fn handle_chat_message(&mut self, chat_message: SChatMessage, player: usize) {
@@ -310,6 +350,9 @@ impl ServerBoundPacketHandler for Plot {
     }
 
     fn handle_player_position(&mut self, player_position: SPlayerPosition, player: usize) {
+        if self.players[player].teleports.is_awaiting() {
+            return;
+        }
         let old = self.players[player].pos;
         let new = PlayerPos::new(player_position.x, player_position.y, player_position.z);
         self.players[player].pos = new;
@@ -355,6 +398,9 @@ impl ServerBoundPacketHandler for Plot {
         player_position_and_rotation: SPlayerPositionAndRotation,
         player: usize,
     ) {
+        if self.players[player].teleports.is_awaiting() {
+            return;
+        }
         let old = self.players[player].pos;
         let new = PlayerPos::new(
             player_position_and_rotation.x,
@@ -412,6 +458,9 @@ impl ServerBoundPacketHandler for Plot {
     }
 
     fn handle_player_rotation(&mut self, player_rotation: SPlayerRotation, player: usize) {
+        if self.players[player].teleports.is_awaiting() {
+            return;
+        }
         self.players[player].yaw = player_rotation.yaw;
         self.players[player].pitch = player_rotation.pitch;
         self.players[player].on_ground = player_rotation.on_ground;
@@ -441,6 +490,9 @@ impl ServerBoundPacketHandler for Plot {
     }
 
     fn handle_player_movement(&mut self, player_movement: SPlayerMovement, player: usize) {
+        if self.players[player].teleports.is_awaiting() {
+            return;
+        }
         self.players[player].on_ground = player_movement.on_ground;
     }
 
@@ -472,14 +524,7 @@ impl ServerBoundPacketHandler for Plot {
                 }
             }
 
-            if let Some(owner) = self.owner {
-                let player = &mut self.players[player];
-                if owner != player.uuid && !player.has_permission("plots.admin.interact.other") {
-                    player.send_no_permission_message();
-                    self.send_block_change(block_pos, block.get_id());
-                    return;
-                }
-            } else if !self.players[player].has_permission("plots.admin.interact.unowned") {
+            if !self.can_build(player) {
                 self.players[player].send_no_permission_message();
                 self.send_block_change(block_pos, block.get_id());
                 return;
@@ -498,6 +543,9 @@ impl ServerBoundPacketHandler for Plot {
 
             interaction::destroy(block, &mut self.world, block_pos);
             self.world.flush_block_changes();
+            if matches!(block, Block::Sign { .. } | Block::WallSign { .. }) {
+                database::delete_sign_placer(block_pos.x, block_pos.y, block_pos.z);
+            }
 
             let effect = CEffect {
                 effect_id: 2001,
@@ -532,13 +580,9 @@ impl ServerBoundPacketHandler for Plot {
     }
 
     fn handle_entity_action(&mut self, entity_action: SEntityAction, player: usize) {
-        match entity_action.action_id {
-            0 => self.players[player].crouching = true,
-            1 => self.players[player].crouching = false,
-            3 => self.players[player].sprinting = true,
-            4 => self.players[player].sprinting = false,
-            _ => {}
-        }
+        let flags = &mut self.players[player];
+        (flags.crouching, flags.sprinting) =
+            entity_action_flags(entity_action.action, flags.crouching, flags.sprinting);
         let mut bitfield = 0;
         if self.players[player].crouching {
             bitfield |= 0x02;
@@ -573,6 +617,13 @@ impl ServerBoundPacketHandler for Plot {
         }
     }
 
+    fn handle_close_window(&mut self, close_window: SCloseWindow, player: usize) {
+        let player = &mut self.players[player];
+        if player.open_window_id == Some(close_window.window_id) {
+            player.open_window_id = None;
+        }
+    }
+
     fn handle_held_item_change(&mut self, held_item_change: SHeldItemChange, player: usize) {
         let entity_equipment = CEntityEquipment {
             entity_id: self.players[player].entity_id as i32,
@@ -599,8 +650,14 @@ impl ServerBoundPacketHandler for Plot {
         self.players[player].selected_slot = held_item_change.slot as u32;
     }
 
-    fn handle_update_sign(&mut self, packet: SUpdateSign, _player: usize) {
+    fn handle_update_sign(&mut self, packet: SUpdateSign, player: usize) {
         let pos = BlockPos::new(packet.x, packet.y, packet.z);
+        if !Plot::in_plot_bounds(self.world.x, self.world.z, pos.x, pos.z)
+            || !self.can_build(player)
+        {
+            self.players[player].send_no_permission_message();
+            return;
+        }
         let mut rows = packet
             .lines
             .iter()
@@ -614,5 +671,110 @@ impl ServerBoundPacketHandler for Plot {
             ],
         }));
         self.world.set_block_entity(pos, block_entity);
+        let uuid = format!("{:032x}", self.players[player].uuid);
+        database::set_sign_placer(pos.x, pos.y, pos.z, &uuid);
     }
 }
+
+/// Returns true if a player standing at `pos` can reach the block at `block_pos`
+fn within_reach(pos: PlayerPos, crouching: bool, block_pos: BlockPos) -> bool {
+    let eye_height = if crouching { 1.27 } else { 1.62 };
+    let dx = block_pos.x as f64 + 0.5 - pos.x;
+    let dy = block_pos.y as f64 + 0.5 - (pos.y + eye_height);
+    let dz = block_pos.z as f64 + 0.5 - pos.z;
+    dx * dx + dy * dy + dz * dz <= MAX_REACH * MAX_REACH
+}
+
+/// Returns a player's `(crouching, sprinting)` flags after they perform `action`
+fn entity_action_flags(action: EntityAction, crouching: bool, sprinting: bool) -> (bool, bool) {
+    match action {
+        EntityAction::StartSneaking => (true, sprinting),
+        EntityAction::StopSneaking => (false, sprinting),
+        EntityAction::StartSprinting => (crouching, true),
+        EntityAction::StopSprinting => (crouching, false),
+        _ => (crouching, sprinting),
+    }
+}
+
+/// Returns the packet sent back for a client status action, if it needs one.
+/// No statistics are tracked, so stats requests get an empty list.
+fn client_status_response(action: ClientStatusAction) -> Option<CStatistics> {
+    match action {
+        ClientStatusAction::PerformRespawn => None,
+        ClientStatusAction::RequestStats => Some(CStatistics {
+            statistics: Vec::new(),
+        }),
+    }
+}
+
+#[test]
+fn swing_animation_forwarded() {
+    use mchprs_network::packets::PacketEncoderExt;
+    use std::io::Cursor;
+
+    let mut data = Vec::new();
+    data.write_varint(1);
+    let animation = SAnimation::decode(&mut Cursor::new(data)).unwrap();
+    assert_eq!(swing_animation_id(animation.hand), 3);
+    assert_eq!(swing_animation_id(0), 0);
+}
+
+#[test]
+fn stats_request_answered_with_statistics() {
+    use std::io::Cursor;
+
+    let status = SClientStatus::decode(&mut Cursor::new(vec![0x01])).unwrap();
+    let response = client_status_response(status.action).unwrap();
+    let mut data = Vec::new();
+    response.encode().write_uncompressed(&mut data).unwrap();
+    // Length, Statistics packet id, empty statistic count
+    assert_eq!(data, [2, 0x07, 0]);
+
+    let status = SClientStatus::decode(&mut Cursor::new(vec![0x00])).unwrap();
+    assert!(client_status_response(status.action).is_none());
+}
+
+#[test]
+fn sneaking_and_flying_tracked() {
+    use mchprs_network::packets::PacketEncoderExt;
+    use std::io::Cursor;
+
+    let entity_action = |action_id| {
+        let mut data = Vec::new();
+        data.write_varint(7);
+        data.write_varint(action_id);
+        data.write_varint(0);
+        SEntityAction::decode(&mut Cursor::new(data)).unwrap()
+    };
+    let start_sneaking = entity_action(0);
+    assert_eq!(start_sneaking.action, EntityAction::StartSneaking);
+    assert_eq!(
+        entity_action_flags(start_sneaking.action, false, true),
+        (true, true)
+    );
+    let stop_sprinting = entity_action(4).action;
+    assert_eq!(
+        entity_action_flags(stop_sprinting, true, true),
+        (true, false)
+    );
+    // Other actions leave both alone
+    let leave_bed = entity_action(2).action;
+    assert_eq!(entity_action_flags(leave_bed, true, false), (true, false));
+
+    let abilities = SPlayerAbilities::decode(&mut Cursor::new(vec![0x02])).unwrap();
+    assert!(abilities.is_flying);
+    let abilities = SPlayerAbilities::decode(&mut Cursor::new(vec![0x00])).unwrap();
+    assert!(!abilities.is_flying);
+}
+
+#[test]
+fn placement_beyond_reach_rejected() {
+    let pos = PlayerPos::new(10.5, 9.0, 10.5);
+    // The block the player is looking straight down at, and one at the edge of creative reach
+    assert!(within_reach(pos, false, BlockPos::new(10, 8, 10)));
+    assert!(within_reach(pos, true, BlockPos::new(15, 10, 10)));
+    // Too far away in any direction
+    assert!(!within_reach(pos, false, BlockPos::new(17, 10, 10)));
+    assert!(!within_reach(pos, false, BlockPos::new(10, 17, 10)));
+    assert!(!within_reach(pos, false, BlockPos::new(14, 6, 14)));
+}
@@ -0,0 +1,84 @@
+use super::PlotWorld;
+use crate::redstone;
+use crate::world::{for_each_block_optimized, World};
+use mchprs_blocks::block_entities::BlockEntity;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use mchprs_world::TickEntry;
+use std::collections::HashSet;
+
+/// An in-memory copy of the state of every redstone component in a plot.
+/// Unlike a plot save, this only stores what changes while a circuit is running,
+/// so it can be used to reset a circuit back to its resting state.
+pub struct RedstoneSnapshot {
+    components: Vec<(BlockPos, Block, Option<BlockEntity>)>,
+    pending_ticks: Vec<TickEntry>,
+}
+
+/// Returns true if the block has state that changes while redstone is running
+fn has_redstone_state(block: Block) -> bool {
+    matches!(
+        block,
+        Block::RedstoneWire { .. }
+            | Block::RedstoneTorch { .. }
+            | Block::RedstoneWallTorch { .. }
+            | Block::RedstoneRepeater { .. }
+            | Block::RedstoneComparator { .. }
+            | Block::RedstoneLamp { .. }
+            | Block::Lever { .. }
+            | Block::StoneButton { .. }
+            | Block::StonePressurePlate { .. }
+            | Block::IronTrapdoor { .. }
+            | Block::NoteBlock { .. }
+    )
+}
+
+impl RedstoneSnapshot {
+    pub fn capture(world: &PlotWorld) -> RedstoneSnapshot {
+        let (first_pos, second_pos) = world.get_corners();
+        let mut components = Vec::new();
+        for_each_block_optimized(world, first_pos, second_pos, |pos| {
+            let block = world.get_block(pos);
+            if has_redstone_state(block) {
+                components.push((pos, block, world.get_block_entity(pos).cloned()));
+            }
+        });
+        RedstoneSnapshot {
+            components,
+            pending_ticks: world.to_be_ticked.clone(),
+        }
+    }
+
+    /// Puts every component back into its captured state, then updates the
+    /// neighbors of everything that was changed. Components that have since been
+    /// replaced by something else are left alone, along with their scheduled ticks.
+    pub fn restore(&self, world: &mut PlotWorld) {
+        let mut changed = Vec::new();
+        let mut replaced = HashSet::new();
+        for (pos, block, block_entity) in &self.components {
+            if !world.get_block(*pos).compare_variant(*block) {
+                replaced.insert(*pos);
+                continue;
+            }
+            if world.set_block(*pos, *block) {
+                changed.push(*pos);
+            }
+            if let Some(block_entity) = block_entity {
+                world.set_block_entity(*pos, block_entity.clone());
+            }
+        }
+        world.to_be_ticked = self
+            .pending_ticks
+            .iter()
+            .filter(|tick| !replaced.contains(&tick.pos))
+            .cloned()
+            .collect();
+        for pos in changed {
+            redstone::update_surrounding_blocks(world, pos);
+        }
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.components.len()
+    }
+}
@@ -0,0 +1,278 @@
+use super::{plot_pos, TestClient, TestPlot};
+use crate::interaction;
+use crate::permissions::PlayerPermissionsCache;
+use crate::plot::{commands, database};
+use crate::world::storage::StorageKind;
+use crate::world::World;
+use mchprs_blocks::blocks::{Block, Lever, LeverFace, RedstoneWire};
+use mchprs_blocks::{BlockDirection, BlockFace, BlockPos, SignRotation, SignType};
+use mchprs_network::packets::protocol::clientbound::play::{CHAT_MESSAGE, TIME_UPDATE};
+use mchprs_network::packets::serverbound::{SUpdateSign, ServerBoundPacketHandler};
+use std::fs;
+use std::path::Path;
+
+/// A command that only players who can build in a plot are allowed to run there
+struct GatedCommand {
+    /// The command, with `{x} {y} {z}` standing in for the position of the targeted block
+    command: &'static str,
+    /// Sets up the plot so that running the command changes something at the position
+    setup: fn(&mut TestPlot, BlockPos),
+    /// Returns true once the command has changed the plot
+    ran: fn(&TestPlot, BlockPos) -> bool,
+}
+
+fn wire_power(test: &TestPlot, pos: BlockPos) -> u8 {
+    match test.plot.world.get_block(pos) {
+        Block::RedstoneWire { wire } => wire.power,
+        block => panic!("expected dust, found {:?}", block),
+    }
+}
+
+fn lever_powered(test: &TestPlot, pos: BlockPos) -> bool {
+    match test.plot.world.get_block(pos) {
+        Block::Lever { lever } => lever.powered,
+        block => panic!("expected a lever, found {:?}", block),
+    }
+}
+
+fn set_lever(test: &mut TestPlot, pos: BlockPos, powered: bool) {
+    let lever = Lever::new(LeverFace::Floor, BlockDirection::North, powered);
+    test.plot.world.set_block(pos, Block::Lever { lever });
+}
+
+const GATED_COMMANDS: &[GatedCommand] = &[
+    GatedCommand {
+        command: "/setblock {x} {y} {z} stone",
+        setup: |_, _| {},
+        ran: |test, pos| test.plot.world.get_block(pos) == Block::Stone {},
+    },
+    GatedCommand {
+        // Copies the block on the west into the targeted position
+        command: "/clone {west} {y} {z} {west} {y} {z} 1 0 0",
+        setup: |test, pos| {
+            let source = pos.offset(BlockFace::West);
+            test.plot.world.set_block(source, Block::Stone {});
+        },
+        ran: |test, pos| test.plot.world.get_block(pos) == Block::Stone {},
+    },
+    GatedCommand {
+        command: "/power {x} {y} {z} 9",
+        setup: |test, pos| {
+            let wire = RedstoneWire::default();
+            test.plot.world.set_block(pos, Block::RedstoneWire { wire });
+        },
+        ran: |test, pos| wire_power(test, pos) == 9,
+    },
+    GatedCommand {
+        // A lit lamp without power turns off once it is updated
+        command: "/neighborupdate {x} {y} {z}",
+        setup: |test, pos| {
+            test.plot
+                .world
+                .set_block(pos, Block::RedstoneLamp { lit: true });
+        },
+        ran: |test, _| !test.plot.world.to_be_ticked.is_empty(),
+    },
+    GatedCommand {
+        command: "/fixredstone",
+        setup: |test, pos| {
+            test.plot
+                .world
+                .set_block(pos, Block::RedstoneLamp { lit: true });
+        },
+        ran: |test, pos| test.plot.world.get_block(pos) == Block::RedstoneLamp { lit: false },
+    },
+    GatedCommand {
+        // Placed where the player is standing
+        command: "/label clock output",
+        setup: |_, _| {},
+        ran: |test, _| {
+            let pos = test.plot.players[0].pos.block_pos();
+            matches!(test.plot.world.get_block(pos), Block::Sign { .. })
+        },
+    },
+    GatedCommand {
+        command: "/reset",
+        // The owner saves the lever while it's off, then it gets turned on
+        setup: |test, pos| {
+            set_lever(test, pos, false);
+            test.run_command(0, "/save-state");
+            set_lever(test, pos, true);
+        },
+        ran: |test, pos| !lever_powered(test, pos),
+    },
+    GatedCommand {
+        command: "/dumpgraph",
+        setup: |test, pos| {
+            for player in &mut test.plot.players {
+                player.first_position = Some(pos);
+                player.second_position = Some(pos);
+            }
+            let (x, z) = (test.plot.world.x, test.plot.world.z);
+            let _ = fs::remove_file(commands::redstone_graph_path(x, z));
+        },
+        ran: |test, _| {
+            let (x, z) = (test.plot.world.x, test.plot.world.z);
+            Path::new(&commands::redstone_graph_path(x, z)).exists()
+        },
+    },
+    GatedCommand {
+        command: "/storage sparse",
+        setup: |_, _| {},
+        ran: |test, _| test.plot.world.storage_kind() == StorageKind::Sparse,
+    },
+    GatedCommand {
+        command: "/updatelimit 5",
+        setup: |_, _| {},
+        ran: |test, _| test.plot.world.tick_update_limit == 5,
+    },
+];
+
+#[test]
+fn gated_commands_only_run_for_builders() {
+    for (i, gated) in GATED_COMMANDS.iter().enumerate() {
+        let plot_x = -60 - i as i32;
+        let mut test = TestPlot::new(plot_x, 10);
+        let (owner, _) = test.join(&format!("Owner{}x10", plot_x));
+        let (stranger, stranger_client) = test.join(&format!("Stranger{}x10", plot_x));
+        test.plot.owner = Some(test.plot.players[owner].uuid);
+        // Allowed to use every command, but not to build here
+        test.plot.players[stranger].permissions_cache = Some(PlayerPermissionsCache::granting(&[
+            "plots.setblock",
+            "plots.clone",
+        ]));
+
+        let pos = plot_pos(&test, 5, 8, 5);
+        (gated.setup)(&mut test, pos);
+        let command = gated
+            .command
+            .replace("{west}", &(pos.x - 1).to_string())
+            .replace("{x}", &pos.x.to_string())
+            .replace("{y}", &pos.y.to_string())
+            .replace("{z}", &pos.z.to_string());
+        stranger_client.received();
+
+        test.run_command(stranger, &command);
+        assert!(!(gated.ran)(&test, pos), "{} ran for a stranger", command);
+        // The stranger is told why nothing happened
+        assert!(
+            stranger_client.received_ids().contains(&CHAT_MESSAGE),
+            "{} failed silently",
+            command
+        );
+
+        test.run_command(owner, &command);
+        assert!(
+            (gated.ran)(&test, pos),
+            "{} didn't run for the owner",
+            command
+        );
+    }
+}
+
+/// The times of day sent to a client, in the order they were received
+fn received_times(client: &TestClient) -> Vec<i64> {
+    client
+        .received()
+        .into_iter()
+        .filter(|(id, _)| *id == TIME_UPDATE)
+        .map(|(_, payload)| i64::from_be_bytes(payload[8..16].try_into().unwrap()))
+        .collect()
+}
+
+#[test]
+fn command_sign_runs_as_writer() {
+    let mut test = TestPlot::new(-54, 10);
+    let (owner, owner_client) = test.join("SignOwner");
+    let (writer, writer_client) = test.join("SignWriter");
+    test.plot.owner = Some(test.plot.players[owner].uuid);
+    let writer_uuid = test.plot.players[writer].uuid;
+    test.plot.trusted.insert(writer_uuid);
+    test.plot.players[writer].permissions_cache = Some(PlayerPermissionsCache::granting(&[]));
+
+    let pos = test.plot.players[owner]
+        .pos
+        .block_pos()
+        .offset(BlockFace::East);
+    test.plot.world.set_block(
+        pos,
+        Block::Sign {
+            sign_type: SignType(0),
+            rotation: SignRotation(0),
+        },
+    );
+    let write_sign = |test: &mut TestPlot, lines: [&str; 4]| {
+        let packet = SUpdateSign {
+            x: pos.x,
+            y: pos.y,
+            z: pos.z,
+            lines: lines.map(str::to_owned),
+        };
+        test.plot.handle_update_sign(packet, writer);
+    };
+
+    write_sign(&mut test, ["[cmd]", "/time set night", "", ""]);
+    owner_client.received();
+    writer_client.received();
+    test.use_block(owner, pos);
+    // Night, with the daylight cycle stopped
+    assert_eq!(received_times(&writer_client), vec![-(13000 + 24000)]);
+    assert_eq!(received_times(&owner_client), vec![]);
+
+    // The owner clicking doesn't lend the sign the owner's permissions
+    write_sign(&mut test, ["[cmd]", "/plot unclaim", "", ""]);
+    test.use_block(owner, pos);
+    assert_eq!(test.plot.owner, Some(test.plot.players[owner].uuid));
+
+    // Nothing runs once the writer isn't allowed to build here anymore
+    write_sign(&mut test, ["[cmd]", "/time set night", "", ""]);
+    test.plot.trusted.remove(&writer_uuid);
+    test.use_block(owner, pos);
+    assert_eq!(received_times(&writer_client), vec![]);
+
+    // Ordinary signs aren't used at all
+    test.plot.trusted.insert(writer_uuid);
+    write_sign(&mut test, ["Hello", "/time set night", "", ""]);
+    let block = test.plot.world.get_block(pos);
+    let result = interaction::on_use(
+        block,
+        &mut test.plot.world,
+        &mut test.plot.players[owner],
+        pos,
+        None,
+    );
+    assert!(matches!(result, interaction::ActionResult::Pass));
+    test.use_block(owner, pos);
+    assert_eq!(received_times(&writer_client), vec![]);
+}
+
+#[test]
+fn clear_forgets_old_circuits() {
+    let mut test = TestPlot::new(-49, 10);
+    let (owner, _) = test.join("ClearOwner");
+    test.plot.owner = Some(test.plot.players[owner].uuid);
+    let lever = Lever::new(LeverFace::Floor, BlockDirection::North, true);
+    let pos = plot_pos(&test, 1, 8, 1);
+    test.plot.world.set_block(pos, Block::Lever { lever });
+
+    test.run_command(owner, "/save-state");
+    test.run_command(owner, "/debug power on");
+    test.run_command(owner, "/tick warp 100000");
+    assert!(!test.plot.snapshots.is_empty());
+    assert!(!test.plot.power_overlay.is_empty());
+    assert!(test.plot.tick_warp.is_some());
+
+    let sign_pos = plot_pos(&test, 2, 8, 1);
+    database::set_sign_placer(sign_pos.x, sign_pos.y, sign_pos.z, "writer");
+
+    test.run_command(owner, "/plot clear");
+    assert_eq!(test.plot.world.get_block(pos), Block::Air {});
+    // A new sign written here later isn't attributed to the old writer
+    assert_eq!(
+        database::get_sign_placer(sign_pos.x, sign_pos.y, sign_pos.z),
+        None
+    );
+    assert!(test.plot.snapshots.is_empty());
+    assert!(test.plot.power_overlay.is_empty());
+    assert!(test.plot.tick_warp.is_none());
+}
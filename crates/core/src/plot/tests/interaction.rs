@@ -0,0 +1,103 @@
+use super::TestPlot;
+use crate::world::World;
+use mchprs_blocks::blocks::{Block, Lever, LeverFace};
+use mchprs_blocks::items::{Item, ItemStack};
+use mchprs_blocks::{BlockDirection, BlockFace, BlockPos};
+use mchprs_network::packets::serverbound::{SPlayerBlockPlacemnt, ServerBoundPacketHandler};
+
+/// A plot with a single player standing next to a lever that is off
+fn plot_with_lever(plot_x: i32, plot_z: i32) -> (TestPlot, usize, BlockPos) {
+    let mut test = TestPlot::new(plot_x, plot_z);
+    let (player, _) = test.join(&format!("LeverUser{}x{}", plot_x, plot_z));
+    let pos = test.plot.players[player]
+        .pos
+        .block_pos()
+        .offset(BlockFace::East);
+    let lever = Lever::new(LeverFace::Floor, BlockDirection::North, false);
+    test.plot.world.set_block(pos, Block::Lever { lever });
+    (test, player, pos)
+}
+
+fn lever_powered(test: &TestPlot, pos: BlockPos) -> bool {
+    match test.plot.world.get_block(pos) {
+        Block::Lever { lever } => lever.powered,
+        block => panic!("Expected a lever, found {:?}", block),
+    }
+}
+
+fn stone() -> Option<ItemStack> {
+    Some(ItemStack {
+        item_type: Item::Stone {},
+        count: 1,
+        nbt: None,
+    })
+}
+
+#[test]
+fn sneaking_with_block_places_against_lever() {
+    let (mut test, player, pos) = plot_with_lever(-40, 10);
+    test.plot.players[player].crouching = true;
+    let slot = test.plot.players[player].selected_slot as usize + 36;
+    test.plot.players[player].inventory[slot] = stone();
+
+    test.use_block(player, pos);
+    assert!(!lever_powered(&test, pos));
+    assert_eq!(
+        test.plot.world.get_block(pos.offset(BlockFace::Top)),
+        Block::Stone {}
+    );
+}
+
+#[test]
+fn sneaking_with_empty_hands_uses_lever() {
+    let (mut test, player, pos) = plot_with_lever(-41, 10);
+    test.plot.players[player].crouching = true;
+
+    // Like vanilla, the lever is flipped when there's nothing in either hand
+    test.use_block(player, pos);
+    assert!(lever_powered(&test, pos));
+
+    // Something in the off hand is enough to stop the main hand from using it
+    test.plot.players[player].inventory[45] = stone();
+    test.use_block(player, pos);
+    assert!(lever_powered(&test, pos));
+
+    test.plot.players[player].crouching = false;
+    test.use_block(player, pos);
+    assert!(!lever_powered(&test, pos));
+}
+
+#[test]
+fn clicks_outside_partial_block_are_ignored() {
+    let (mut test, player, pos) = plot_with_lever(-53, 10);
+    test.plot.players[player].crouching = true;
+    let slot = test.plot.players[player].selected_slot as usize + 36;
+    test.plot.players[player].inventory[slot] = stone();
+
+    // A full block's height above a floor lever is empty space, so the client must have
+    // thought something else was there
+    let packet = SPlayerBlockPlacemnt {
+        hand: 0,
+        x: pos.x,
+        y: pos.y,
+        z: pos.z,
+        face: 1,
+        cursor_x: 0.5,
+        cursor_y: 1.0,
+        cursor_z: 0.5,
+        inside_block: false,
+    };
+    test.plot.handle_player_block_placement(packet, player);
+    assert!(!lever_powered(&test, pos));
+    assert_eq!(
+        test.plot.world.get_block(pos.offset(BlockFace::Top)),
+        Block::Air {}
+    );
+
+    // Sneaking and clicking the top of the lever itself places against it
+    test.use_block(player, pos);
+    assert_eq!(
+        test.plot.world.get_block(pos.offset(BlockFace::Top)),
+        Block::Stone {}
+    );
+}
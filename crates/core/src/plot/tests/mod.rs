@@ -0,0 +1,212 @@
+mod commands;
+mod interaction;
+mod ownership;
+mod players;
+mod redpiler;
+mod redstone;
+mod scheduling;
+mod storage;
+mod worldedit;
+
+use super::{data, database, Plot, PlotWorld, PLOT_WIDTH};
+use crate::player::{Player, PlayerPos};
+use crate::server::{BroadcastMessage, Message, PrivMessage};
+use crate::world::storage::Chunk;
+use bus::{Bus, BusReader};
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::{BlockBox, BlockPos};
+use mchprs_network::packets::serverbound::{SPlayerBlockPlacemnt, ServerBoundPacketHandler};
+use mchprs_network::packets::{PacketDecoderExt, DEFAULT_MAX_PACKET_LENGTH};
+use mchprs_network::{PlayerConn, PlayerPacketSender};
+use std::io::{Cursor, Read};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// An empty, generated plot world at 0, 0 without a scheduled tick limit
+fn test_world() -> PlotWorld {
+    let mut chunks = Vec::new();
+    for chunk_x in 0..PLOT_WIDTH {
+        for chunk_z in 0..PLOT_WIDTH {
+            chunks.push(Chunk::empty(chunk_x, chunk_z));
+        }
+    }
+    let mut world = PlotWorld {
+        x: 0,
+        z: 0,
+        chunks,
+        to_be_ticked: Vec::new(),
+        packet_senders: Vec::new(),
+        tick_update_limit: usize::MAX,
+        tick_limit_reached: false,
+        instant_redstone: false,
+        rng: PlotWorld::create_rng(Some(0)),
+        clock_detector: Default::default(),
+    };
+    world.generate_borders(Block::Sandstone {}, Block::StoneBricks {});
+    world
+}
+
+/// A block position inside the plot, relative to its corner
+fn plot_pos(test: &TestPlot, x: i32, y: i32, z: i32) -> BlockPos {
+    let (corner, _) = test.plot.world.get_corners();
+    BlockPos::new(corner.x + x, y, corner.z + z)
+}
+
+/// A plot running without the server around it, for tests that go through the packet and
+/// command handlers. Players are put straight into the plot, so nothing is saved to disk.
+struct TestPlot {
+    plot: Plot,
+    // Kept alive so the plot can still send messages to the server
+    _broadcaster: Bus<BroadcastMessage>,
+    _messages: Receiver<Message>,
+    _priv_messages: Sender<PrivMessage>,
+}
+
+impl TestPlot {
+    fn new(plot_x: i32, plot_z: i32) -> TestPlot {
+        TestPlot::create(|rx, tx, priv_rx| {
+            Plot::from_data(data::empty_plot(), plot_x, plot_z, rx, tx, priv_rx, false)
+        })
+    }
+
+    /// Loads the plot from disk the same way the server does
+    fn load(plot_x: i32, plot_z: i32) -> TestPlot {
+        TestPlot::create(|rx, tx, priv_rx| Plot::load(plot_x, plot_z, rx, tx, priv_rx, false))
+    }
+
+    fn create(
+        make_plot: impl FnOnce(
+            BusReader<BroadcastMessage>,
+            Sender<Message>,
+            Receiver<PrivMessage>,
+        ) -> Plot,
+    ) -> TestPlot {
+        database::init();
+        let mut broadcaster = Bus::new(16);
+        let (message_sender, messages) = mpsc::channel();
+        let (priv_messages, priv_message_receiver) = mpsc::channel();
+        let plot = make_plot(broadcaster.add_rx(), message_sender, priv_message_receiver);
+        TestPlot {
+            plot,
+            _broadcaster: broadcaster,
+            _messages: messages,
+            _priv_messages: priv_messages,
+        }
+    }
+
+    /// Adds a player standing in the middle of the plot. Returns its index in the plot and the
+    /// client end of its connection.
+    fn join(&mut self, username: &str) -> (usize, TestClient) {
+        let (player, client) = self.rejoin(username);
+        let (x, z) = Plot::get_center(self.plot.world.x, self.plot.world.z);
+        self.plot.players[player].pos = PlayerPos::new(x, 8.0, z);
+        (player, client)
+    }
+
+    /// Adds a player the same way the server loads them on login, so anything saved from an
+    /// earlier session is kept
+    fn rejoin(&mut self, username: &str) -> (usize, TestClient) {
+        let (conn, client) = TestClient::connect();
+        let uuid = Player::generate_offline_uuid(username);
+        let player = Player::load_player(uuid, username.to_owned(), conn);
+        self.plot
+            .world
+            .packet_senders
+            .push(PlayerPacketSender::new(&player.client));
+        self.plot.players.push(player);
+        (self.plot.players.len() - 1, client)
+    }
+
+    /// Right-clicks the top of the block at `pos` with whatever the player is holding, and
+    /// runs any commands that queued
+    fn use_block(&mut self, player: usize, pos: BlockPos) {
+        // Aim for the middle of the top of the block's shape, like a real client would
+        let bounds = self
+            .plot
+            .world
+            .get_block(pos)
+            .interaction_box()
+            .unwrap_or(BlockBox::FULL_CUBE);
+        let packet = SPlayerBlockPlacemnt {
+            hand: 0,
+            x: pos.x,
+            y: pos.y,
+            z: pos.z,
+            face: 1,
+            cursor_x: (bounds.min.0 + bounds.max.0) / 2.0,
+            cursor_y: bounds.max.1,
+            cursor_z: (bounds.min.2 + bounds.max.2) / 2.0,
+            inside_block: false,
+        };
+        self.plot.handle_player_block_placement(packet, player);
+        self.plot.handle_commands();
+    }
+
+    /// Runs a command like it was sent in chat
+    fn run_command(&mut self, player: usize, command: &str) {
+        let mut args: Vec<&str> = command.split(' ').collect();
+        let command = args.remove(0);
+        self.plot.handle_command(player, command, args);
+    }
+}
+
+/// The client end of a test player's connection. The packets sent to it are read on another
+/// thread, so the plot never blocks on a full socket.
+struct TestClient {
+    packets: Receiver<(u32, Vec<u8>)>,
+    stream: TcpStream,
+}
+
+impl TestClient {
+    fn connect() -> (PlayerConn, TestClient) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server_end, _) = listener.accept().unwrap();
+        let conn = PlayerConn::new(0, server_end, DEFAULT_MAX_PACKET_LENGTH).unwrap();
+
+        let (sender, packets) = mpsc::channel();
+        let client_stream = stream.try_clone().unwrap();
+        thread::spawn(move || {
+            // Compression is never enabled, so every frame is just the id and the payload
+            while let Ok(length) = stream.read_varint() {
+                let mut frame = vec![0; length as usize];
+                if stream.read_exact(&mut frame).is_err() {
+                    return;
+                }
+                let mut frame = Cursor::new(frame);
+                let id = frame.read_varint().unwrap() as u32;
+                let payload = frame.get_ref()[frame.position() as usize..].to_vec();
+                if sender.send((id, payload)).is_err() {
+                    return;
+                }
+            }
+        });
+        let client = TestClient {
+            packets,
+            stream: client_stream,
+        };
+        (conn, client)
+    }
+
+    /// Everything received since the last call, as packet ids and payloads. Waits until the
+    /// connection has been quiet for a moment, since packets are read on another thread.
+    fn received(&self) -> Vec<(u32, Vec<u8>)> {
+        let mut received = Vec::new();
+        while let Ok(packet) = self.packets.recv_timeout(Duration::from_millis(100)) {
+            received.push(packet);
+        }
+        received
+    }
+
+    /// Closes the connection from the client's side, like quitting the game
+    fn disconnect(self) {
+        self.stream.shutdown(Shutdown::Both).unwrap();
+    }
+
+    /// The ids of everything received since the last call
+    fn received_ids(&self) -> Vec<u32> {
+        self.received().into_iter().map(|(id, _)| id).collect()
+    }
+}
@@ -0,0 +1,146 @@
+use super::{plot_pos, TestPlot};
+use crate::permissions::PlayerPermissionsCache;
+use crate::player::{Player, PlayerPos};
+use crate::plot::{database, Plot, PLOT_BLOCK_WIDTH, PLOT_FLOOR_LAYERS};
+use crate::world::World;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::items::{Item, ItemStack};
+use mchprs_blocks::BlockFace;
+use mchprs_network::packets::protocol::clientbound::play::CHAT_MESSAGE;
+
+#[test]
+fn plot_owner_at_coordinate() {
+    database::init();
+    let first_owner = format!("{:032x}", 0xA1u128);
+    let second_owner = format!("{:032x}", 0xA2u128);
+    database::ensure_user(&first_owner, "FirstOwner");
+    database::ensure_user(&second_owner, "SecondOwner");
+    database::claim_plot(40, 40, &first_owner);
+    database::claim_plot(41, 40, &second_owner);
+
+    // The last block of the first plot and the first block of the second
+    let edge_x = 41 * PLOT_BLOCK_WIDTH;
+    let z = 40 * PLOT_BLOCK_WIDTH + 10;
+    let (plot_x, plot_z) = Plot::get_plot_at(edge_x - 1, z);
+    assert_eq!(database::get_plot_owner(plot_x, plot_z), Some(first_owner));
+    let (plot_x, plot_z) = Plot::get_plot_at(edge_x, z);
+    assert_eq!(database::get_plot_owner(plot_x, plot_z), Some(second_owner));
+
+    database::unclaim_plot(41, 40);
+    assert_eq!(database::is_claimed(41, 40), Some(false));
+    assert_eq!(database::get_plot_owner(41, 40), None);
+}
+
+#[test]
+fn claimed_plot_only_accepts_blocks_inside() {
+    database::init();
+    let uuid = format!("{:032x}", Player::generate_offline_uuid("HomeOwner"));
+    database::ensure_user(&uuid, "HomeOwner");
+    database::claim_plot(-47, 10, &uuid);
+    assert_eq!(database::get_owned_plots("HomeOwner"), vec![(-47, 10)]);
+
+    let mut test = TestPlot::new(-47, 10);
+    let (owner, client) = test.join("HomeOwner");
+    let slot = test.plot.players[owner].selected_slot as usize + 36;
+    test.plot.players[owner].inventory[slot] = Some(ItemStack {
+        item_type: Item::Stone {},
+        count: 1,
+        nbt: None,
+    });
+    // The top of the floor on both sides of the plot's edge
+    let inside = plot_pos(&test, PLOT_BLOCK_WIDTH - 1, PLOT_FLOOR_LAYERS - 1, 5);
+    let outside = inside.offset(BlockFace::East);
+    assert_eq!(Plot::get_plot_at(inside.x, inside.z), (-47, 10));
+    assert_eq!(Plot::get_plot_at(outside.x, outside.z), (-46, 10));
+    // Standing right on the edge, so both are in reach
+    test.plot.players[owner].pos = PlayerPos::new(outside.x as f64, 8.0, outside.z as f64);
+    client.received();
+
+    test.use_block(owner, outside);
+    // The owner is told why nothing happened
+    assert!(client.received_ids().contains(&CHAT_MESSAGE));
+    assert_eq!(
+        test.plot.world.get_block(outside.offset(BlockFace::Top)),
+        Block::Air {}
+    );
+
+    test.use_block(owner, inside);
+    assert!(!client.received_ids().contains(&CHAT_MESSAGE));
+    assert_eq!(
+        test.plot.world.get_block(inside.offset(BlockFace::Top)),
+        Block::Stone {}
+    );
+}
+
+#[test]
+fn trusted_players_stored_per_plot() {
+    database::init();
+    let uuid = |name: &str| format!("{:032x}", Player::generate_offline_uuid(name));
+    let owner = uuid("TrustOwner");
+    let trusted = uuid("TrustedFriend");
+    let untrusted = uuid("Stranger");
+    database::ensure_user(&owner, "TrustOwner");
+    database::ensure_user(&trusted, "TrustedFriend");
+    database::ensure_user(&untrusted, "Stranger");
+    database::claim_plot(-45, 10, &owner);
+    database::claim_plot(-46, 10, &owner);
+
+    assert!(database::trust_player(-45, 10, "TrustedFriend"));
+    assert!(!database::trust_player(-45, 10, "TrustedFriend"));
+    assert!(!database::trust_player(-45, 10, "TrustOwner"));
+    // Players that have never joined can't be trusted
+    assert!(!database::trust_player(-45, 10, "NeverJoined"));
+
+    let trusted_players = database::get_trusted_players(-45, 10);
+    assert_eq!(trusted_players, vec![trusted]);
+    assert!(!trusted_players.contains(&untrusted));
+    // Trusting someone doesn't change the owner
+    assert_eq!(database::get_plot_owner(-45, 10), Some(owner));
+    assert!(database::get_trusted_players(-46, 10).is_empty());
+
+    for (plot_x, friend_can_build) in [(-45, true), (-46, false)] {
+        let mut test = TestPlot::new(plot_x, 10);
+        let (owner, _) = test.join("TrustOwner");
+        let (friend, _) = test.join("TrustedFriend");
+        let (stranger, _) = test.join("Stranger");
+        for player in [friend, stranger] {
+            test.plot.players[player].permissions_cache =
+                Some(PlayerPermissionsCache::granting(&[]));
+        }
+        assert!(test.plot.can_build(owner));
+        assert_eq!(test.plot.can_build(friend), friend_can_build);
+        assert!(!test.plot.can_build(stranger));
+    }
+}
+
+#[test]
+fn unclaim_only_by_owner() {
+    let mut test = TestPlot::new(-42, 10);
+    let (owner, _) = test.join("UnclaimOwner");
+    let (friend, _) = test.join("UnclaimFriend");
+    for player in [owner, friend] {
+        let player = &test.plot.players[player];
+        database::ensure_user(&format!("{:032x}", player.uuid), &player.username);
+    }
+    test.plot.players[friend].permissions_cache =
+        Some(PlayerPermissionsCache::granting(&["plots.unclaim"]));
+
+    test.run_command(owner, "/plot claim");
+    assert_eq!(test.plot.owner, Some(test.plot.players[owner].uuid));
+    test.run_command(owner, "/plot trust UnclaimFriend");
+    assert!(test.plot.can_build(friend));
+
+    // Being trusted isn't enough to give the plot away
+    test.run_command(friend, "/plot unclaim");
+    assert_eq!(test.plot.owner, Some(test.plot.players[owner].uuid));
+    assert_eq!(database::is_claimed(-42, 10), Some(true));
+
+    let sign = plot_pos(&test, 3, 8, 3);
+    database::set_sign_placer(sign.x, sign.y, sign.z, "friend");
+    test.run_command(owner, "/plot unclaim");
+    assert_eq!(test.plot.owner, None);
+    assert!(test.plot.trusted.is_empty());
+    assert_eq!(database::is_claimed(-42, 10), Some(false));
+    assert!(!test.plot.can_build(friend));
+    assert_eq!(database::get_sign_placer(sign.x, sign.y, sign.z), None);
+}
@@ -0,0 +1,141 @@
+use super::TestPlot;
+use crate::config::CONFIG;
+use crate::player::PlayerPos;
+use crate::plot::Plot;
+use mchprs_blocks::items::{Item, ItemStack};
+use mchprs_blocks::BlockPos;
+use mchprs_network::packets::protocol::clientbound::play::{CHUNK_DATA, UNLOAD_CHUNK};
+use mchprs_network::packets::serverbound::{SPlayerPosition, ServerBoundPacketHandler};
+use std::collections::HashSet;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn plot_spawn_position() {
+    assert_eq!(Plot::get_spawn(0, 0), BlockPos::new(128, 64, 128));
+    assert_eq!(Plot::get_spawn(-1, 2), BlockPos::new(-128, 64, 640));
+}
+
+#[test]
+fn chunk_view_changes_on_move() {
+    let view = |x: i32, z: i32| -> HashSet<(i32, i32)> {
+        (x - 2..=x + 2)
+            .flat_map(|x| (z - 2..=z + 2).map(move |z| (x, z)))
+            .collect()
+    };
+
+    // Moving one chunk east with a view distance of 2 loads a new column and unloads the old one
+    let (mut load, mut unload) = Plot::chunk_view_changes(&view(0, 0), (1, 0), 2);
+    let mut expected_load: Vec<(i32, i32)> = (-2..=2).map(|z| (3, z)).collect();
+    let mut expected_unload: Vec<(i32, i32)> = (-2..=2).map(|z| (-2, z)).collect();
+    for list in [
+        &mut load,
+        &mut unload,
+        &mut expected_load,
+        &mut expected_unload,
+    ] {
+        list.sort();
+    }
+    assert_eq!(load, expected_load);
+    assert_eq!(unload, expected_unload);
+
+    // Staying in the same chunk changes nothing
+    let (load, unload) = Plot::chunk_view_changes(&view(4, 4), (4, 4), 2);
+    assert!(load.is_empty() && unload.is_empty());
+
+    // Teleporting far away reloads the entire view
+    let (load, unload) = Plot::chunk_view_changes(&view(0, 0), (100, 100), 2);
+    assert_eq!(load.len(), 25);
+    assert_eq!(unload.len(), 25);
+    assert!(load.contains(&(100, 100)));
+    assert!(unload.contains(&(0, 0)));
+
+    // A client without any chunks gets its whole view
+    let (load, unload) = Plot::chunk_view_changes(&HashSet::new(), (0, 0), 2);
+    assert_eq!(load.len(), 25);
+    assert!(unload.is_empty());
+}
+
+#[test]
+fn moving_player_loads_and_unloads_chunks() {
+    let view_distance = CONFIG.view_distance as usize;
+    let side = 2 * view_distance + 1;
+    let count = |ids: &[u32], packet_id: u32| ids.iter().filter(|id| **id == packet_id).count();
+
+    let mut test = TestPlot::new(-39, 10);
+    let (player, client) = test.join("ChunkWalker");
+    test.plot.update_view_pos_for_player(player, true);
+    let ids = client.received_ids();
+    assert_eq!(count(&ids, CHUNK_DATA), side * side);
+    assert_eq!(count(&ids, UNLOAD_CHUNK), 0);
+    assert_eq!(test.plot.players[player].loaded_chunks.len(), side * side);
+
+    // Walk one chunk east
+    let pos = test.plot.players[player].pos;
+    let packet = SPlayerPosition {
+        x: pos.x + 16.0,
+        y: pos.y,
+        z: pos.z,
+        on_ground: true,
+    };
+    test.plot.handle_player_position(packet, player);
+    test.plot.update_players();
+    let ids = client.received_ids();
+    assert_eq!(count(&ids, CHUNK_DATA), side);
+    assert_eq!(count(&ids, UNLOAD_CHUNK), side);
+    let (chunk_x, chunk_z) = test.plot.players[player].pos.chunk_pos();
+    let loaded = &test.plot.players[player].loaded_chunks;
+    assert_eq!(loaded.len(), side * side);
+    assert!(loaded.contains(&(chunk_x + view_distance as i32, chunk_z)));
+    assert!(!loaded.contains(&(chunk_x - view_distance as i32 - 1, chunk_z)));
+
+    // Moving within the same chunk doesn't send anything
+    let pos = test.plot.players[player].pos;
+    let packet = SPlayerPosition {
+        x: pos.x + 1.0,
+        y: pos.y,
+        z: pos.z,
+        on_ground: true,
+    };
+    test.plot.handle_player_position(packet, player);
+    test.plot.update_players();
+    let ids = client.received_ids();
+    assert_eq!(count(&ids, CHUNK_DATA), 0);
+    assert_eq!(count(&ids, UNLOAD_CHUNK), 0);
+}
+
+#[test]
+fn position_and_inventory_restored_on_reconnect() {
+    let mut test = TestPlot::new(-43, 10);
+    let (player, client) = test.join("Reconnecting");
+    let (x, z) = Plot::get_center(-43, 10);
+    let pos = PlayerPos::new(x + 20.5, 12.0, z - 7.25);
+    test.plot.players[player].pos = pos;
+    test.plot.players[player].inventory[36] = Some(ItemStack {
+        item_type: Item::Redstone {},
+        count: 42,
+        nbt: None,
+    });
+
+    // Quitting is noticed on the next update, and the player is saved as they leave
+    client.disconnect();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while test.plot.players[player].client.alive() && Instant::now() < deadline {
+        test.plot.update_players();
+        thread::sleep(Duration::from_millis(10));
+    }
+    test.plot.remove_dc_players();
+    assert!(test.plot.players.is_empty());
+    assert!(test.plot.world.packet_senders.is_empty());
+
+    let (player, _client) = test.rejoin("Reconnecting");
+    let restored = &test.plot.players[player];
+    assert_eq!(
+        (restored.pos.x, restored.pos.y, restored.pos.z),
+        (pos.x, pos.y, pos.z)
+    );
+    let item = restored.inventory[36].as_ref().unwrap();
+    assert_eq!(item.item_type, Item::Redstone {});
+    assert_eq!(item.count, 42);
+    assert!(restored.inventory[37].is_none());
+}
@@ -0,0 +1,116 @@
+use super::test_world;
+use crate::redpiler::Compiler;
+use crate::redstone;
+use crate::world::World;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+
+#[test]
+fn dump_graph_lists_components_and_links() {
+    use mchprs_blocks::blocks::{Lever, LeverFace, RedstoneRepeater};
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    world.set_block(
+        BlockPos::new(4, 8, 4),
+        Block::Lever {
+            lever: Lever {
+                face: LeverFace::Floor,
+                facing: BlockDirection::North,
+                powered: false,
+            },
+        },
+    );
+    world.set_block(
+        BlockPos::new(5, 8, 4),
+        Block::RedstoneRepeater {
+            repeater: RedstoneRepeater {
+                delay: 2,
+                facing: BlockDirection::West,
+                locked: false,
+                powered: false,
+            },
+        },
+    );
+    world.set_block(BlockPos::new(6, 8, 4), Block::RedstoneLamp { lit: false });
+
+    let dot = crate::redpiler::dump_graph(&world, (BlockPos::new(4, 8, 4), BlockPos::new(6, 8, 4)));
+    assert!(dot.starts_with("digraph {"));
+    assert_eq!(dot.matches("label = \"Lever").count(), 1);
+    assert_eq!(dot.matches("label = \"Repeater(2)").count(), 1);
+    assert_eq!(dot.matches("label = \"Lamp").count(), 1);
+    // Lever -> repeater -> lamp
+    assert_eq!(dot.matches(" -> ").count(), 2);
+}
+
+#[test]
+fn java_and_fast_modes_agree() {
+    use mchprs_blocks::blocks::{ComparatorMode, Lever, LeverFace, RedstoneComparator};
+    use mchprs_blocks::blocks::{RedstoneRepeater, RedstoneWire, RedstoneWireSide};
+    use mchprs_blocks::BlockDirection;
+
+    // A lever that was just switched on, feeding a repeater, dust, a comparator, and a lamp
+    let lever_pos = BlockPos::new(3, 8, 4);
+    let lamp_pos = BlockPos::new(9, 8, 4);
+    let build = || {
+        let mut world = test_world();
+        let lever = Lever::new(LeverFace::Floor, BlockDirection::North, true);
+        world.set_block(lever_pos, Block::Lever { lever });
+        let repeater = RedstoneRepeater {
+            delay: 2,
+            facing: BlockDirection::West,
+            locked: false,
+            powered: false,
+        };
+        world.set_block(BlockPos::new(4, 8, 4), Block::RedstoneRepeater { repeater });
+        for x in 5..=7 {
+            let wire = RedstoneWire::new(
+                RedstoneWireSide::None,
+                RedstoneWireSide::None,
+                RedstoneWireSide::Side,
+                RedstoneWireSide::Side,
+                0,
+            );
+            world.set_block(BlockPos::new(x, 8, 4), Block::RedstoneWire { wire });
+        }
+        let comparator =
+            RedstoneComparator::new(BlockDirection::West, ComparatorMode::Compare, false);
+        world.set_block(
+            BlockPos::new(8, 8, 4),
+            Block::RedstoneComparator { comparator },
+        );
+        world.set_block(lamp_pos, Block::RedstoneLamp { lit: false });
+        redstone::update_surrounding_blocks(&mut world, lever_pos);
+        world
+    };
+
+    let mut java = build();
+    let mut fast = build();
+    let mut redpiler = Compiler::default();
+    let bounds = fast.get_corners();
+    let ticks = fast.to_be_ticked.drain(..).collect();
+    redpiler.compile(
+        &mut fast,
+        bounds,
+        Default::default(),
+        ticks,
+        Default::default(),
+    );
+
+    for tick in 1..=8 {
+        java.run_scheduled_ticks();
+        redpiler.tick();
+        redpiler.flush(&mut fast);
+        for x in 3..=9 {
+            let pos = BlockPos::new(x, 8, 4);
+            assert_eq!(
+                java.get_block(pos),
+                fast.get_block(pos),
+                "{} differs after tick {}",
+                pos,
+                tick
+            );
+        }
+    }
+    assert_eq!(java.get_block(lamp_pos), Block::RedstoneLamp { lit: true });
+}
@@ -0,0 +1,1095 @@
+use super::{plot_pos, test_world, TestClient, TestPlot};
+use crate::plot::{PlotWorld, RedstoneSnapshot};
+use crate::redstone;
+use crate::world::World;
+use mchprs_blocks::block_entities::BlockEntity;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::{BlockFace, BlockPos};
+use mchprs_network::packets::protocol::clientbound::play::BLOCK_CHANGE;
+use mchprs_network::packets::PacketDecoderExt;
+use mchprs_world::TickPriority;
+use std::io::Cursor;
+
+#[test]
+fn redstone_snapshot_restore() {
+    use mchprs_blocks::blocks::{Lever, LeverFace};
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    let lever_pos = BlockPos::new(1, 8, 1);
+    let lamp_pos = BlockPos::new(2, 8, 1);
+    let lever = Lever::new(LeverFace::Floor, BlockDirection::North, false);
+    world.set_block(lever_pos, Block::Lever { lever });
+    world.set_block(lamp_pos, Block::RedstoneLamp { lit: false });
+
+    let snapshot = RedstoneSnapshot::capture(&world);
+    assert_eq!(snapshot.component_count(), 2);
+
+    // Toggle the lever like a clock, leaving it off with the lamp waiting to turn off
+    for powered in [true, false, true, false] {
+        let lever = Lever::new(LeverFace::Floor, BlockDirection::North, powered);
+        world.set_block(lever_pos, Block::Lever { lever });
+        redstone::update_surrounding_blocks(&mut world, lever_pos);
+        world.run_scheduled_ticks();
+    }
+    world.set_block(
+        lever_pos,
+        Block::Lever {
+            lever: Lever {
+                powered: true,
+                ..lever
+            },
+        },
+    );
+    redstone::update_surrounding_blocks(&mut world, lever_pos);
+    assert_eq!(world.get_block(lamp_pos), Block::RedstoneLamp { lit: true });
+
+    snapshot.restore(&mut world);
+    assert_eq!(world.get_block(lever_pos), Block::Lever { lever });
+    assert_eq!(
+        world.get_block(lamp_pos),
+        Block::RedstoneLamp { lit: false }
+    );
+    assert!(world.to_be_ticked.is_empty());
+
+    // Building over a component since the capture isn't undone
+    world.set_block(lamp_pos, Block::Stone {});
+    snapshot.restore(&mut world);
+    assert_eq!(world.get_block(lamp_pos), Block::Stone {});
+    assert_eq!(world.get_block(lever_pos), Block::Lever { lever });
+}
+
+#[test]
+fn torch_breaks_without_support() {
+    use crate::interaction;
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    let support_pos = BlockPos::new(10, 9, 10);
+    let wall_torch_pos = BlockPos::new(11, 9, 10);
+    let torch_pos = BlockPos::new(10, 10, 10);
+    world.set_block(support_pos, Block::Stone {});
+    world.set_block(
+        wall_torch_pos,
+        Block::RedstoneWallTorch {
+            lit: true,
+            facing: BlockDirection::East,
+        },
+    );
+    world.set_block(torch_pos, Block::RedstoneTorch { lit: true });
+
+    interaction::destroy(Block::Stone {}, &mut world, support_pos);
+    assert_eq!(world.get_block(wall_torch_pos), Block::Air {});
+    assert_eq!(world.get_block(torch_pos), Block::Air {});
+}
+
+/// The blocks changed by the single block changes received since the last call
+fn received_block_changes(client: &TestClient) -> Vec<(BlockPos, u32)> {
+    client
+        .received()
+        .into_iter()
+        .filter(|(id, _)| *id == BLOCK_CHANGE)
+        .map(|(_, payload)| {
+            let mut payload = Cursor::new(payload);
+            let (x, y, z) = payload.read_position().unwrap();
+            let block_id = payload.read_varint().unwrap() as u32;
+            (BlockPos::new(x, y, z), block_id)
+        })
+        .collect()
+}
+
+#[test]
+fn power_overlay_components() {
+    use mchprs_blocks::blocks::{Lever, LeverFace};
+    use mchprs_blocks::{BlockColorVariant, BlockDirection};
+
+    let mut test = TestPlot::new(-48, 10);
+    let (player, client) = test.join("OverlayViewer");
+    let lever_pos = plot_pos(&test, 1, 8, 1);
+    let lamp_pos = plot_pos(&test, 2, 8, 1);
+    let unpowered_lamp_pos = plot_pos(&test, 5, 8, 5);
+    let lever = Block::Lever {
+        lever: Lever::new(LeverFace::Floor, BlockDirection::North, true),
+    };
+    let lamp = Block::RedstoneLamp { lit: true };
+    let world = &mut test.plot.world;
+    world.set_block(lever_pos, lever);
+    world.set_block(lamp_pos, lamp);
+    world.set_block(unpowered_lamp_pos, Block::RedstoneLamp { lit: false });
+
+    let powered = world.powered_components();
+    assert_eq!(powered.len(), 2);
+    assert!(powered.contains(&lever_pos));
+    assert!(powered.contains(&lamp_pos));
+    client.received();
+
+    test.run_command(player, "/debug power on");
+    let glass = Block::StainedGlass {
+        color: BlockColorVariant::Lime,
+    }
+    .get_id();
+    let mut changes = received_block_changes(&client);
+    changes.sort_by_key(|(pos, _)| pos.x);
+    assert_eq!(changes, vec![(lever_pos, glass), (lamp_pos, glass)]);
+    // The overlay is only shown to the players, the world doesn't change
+    assert_eq!(test.plot.world.get_block(lever_pos), lever);
+    assert_eq!(test.plot.world.get_block(lamp_pos), lamp);
+
+    test.run_command(player, "/debug power off");
+    let mut changes = received_block_changes(&client);
+    changes.sort_by_key(|(pos, _)| pos.x);
+    assert_eq!(
+        changes,
+        vec![(lever_pos, lever.get_id()), (lamp_pos, lamp.get_id())]
+    );
+}
+
+#[test]
+fn placing_identical_block_does_not_update() {
+    use crate::interaction;
+
+    let mut world = test_world();
+    let pos = BlockPos::new(1, 8, 1);
+    // An unpowered lit lamp schedules a tick as soon as it receives an update
+    world.set_block(BlockPos::new(2, 8, 1), Block::RedstoneLamp { lit: true });
+
+    interaction::place_in_world(Block::Stone {}, &mut world, pos, &None);
+    assert!(!world.to_be_ticked.is_empty());
+    world.to_be_ticked.clear();
+
+    interaction::place_in_world(Block::Stone {}, &mut world, pos, &None);
+    assert!(world.to_be_ticked.is_empty());
+}
+
+#[test]
+fn repeater_reads_through_solid_block() {
+    use mchprs_blocks::blocks::{
+        Lever, LeverFace, RedstoneRepeater, RedstoneWire, RedstoneWireSide,
+    };
+    use mchprs_blocks::BlockDirection;
+
+    let repeater = RedstoneRepeater {
+        delay: 1,
+        facing: BlockDirection::North,
+        locked: false,
+        powered: false,
+    };
+    let block_pos = BlockPos::new(6, 8, 4);
+    let repeater_pos = BlockPos::new(6, 8, 5);
+
+    // A lever on top of the block strongly powers it
+    let mut world = test_world();
+    world.set_block(block_pos, Block::Stone {});
+    let lever = Lever::new(LeverFace::Floor, BlockDirection::North, true);
+    world.set_block(block_pos.offset(BlockFace::Top), Block::Lever { lever });
+    world.set_block(repeater_pos, Block::RedstoneRepeater { repeater });
+    redstone::update(world.get_block(repeater_pos), &mut world, repeater_pos);
+    world.run_scheduled_ticks();
+    assert!(matches!(
+        world.get_block(repeater_pos),
+        Block::RedstoneRepeater { repeater } if repeater.powered
+    ));
+
+    // Dust running past the block without pointing into it does not power it
+    let mut world = test_world();
+    world.set_block(block_pos, Block::Stone {});
+    let wire = RedstoneWire::new(
+        RedstoneWireSide::None,
+        RedstoneWireSide::None,
+        RedstoneWireSide::Side,
+        RedstoneWireSide::Side,
+        15,
+    );
+    for x in 5..=7 {
+        world.set_block(BlockPos::new(x, 8, 3), Block::RedstoneWire { wire });
+    }
+    world.set_block(repeater_pos, Block::RedstoneRepeater { repeater });
+    redstone::update(world.get_block(repeater_pos), &mut world, repeater_pos);
+    world.run_scheduled_ticks();
+    assert_eq!(
+        world.get_block(repeater_pos),
+        Block::RedstoneRepeater { repeater }
+    );
+    assert!(world.to_be_ticked.is_empty());
+}
+
+#[test]
+fn comparator_side_input_only_from_redstone() {
+    use mchprs_blocks::blocks::{ComparatorMode, Lever, LeverFace, RedstoneComparator};
+    use mchprs_blocks::blocks::{RedstoneWire, RedstoneWireSide};
+    use mchprs_blocks::BlockDirection;
+
+    let comparator = RedstoneComparator {
+        facing: BlockDirection::North,
+        mode: ComparatorMode::Subtract,
+        powered: false,
+    };
+    let comparator_pos = BlockPos::new(6, 8, 5);
+    let side_pos = BlockPos::new(7, 8, 5);
+    let lever = Lever::new(LeverFace::Floor, BlockDirection::North, true);
+
+    let output_strength = |world: &mut PlotWorld| {
+        let block = world.get_block(comparator_pos);
+        redstone::update(block, world, comparator_pos);
+        world.run_scheduled_ticks();
+        match world.get_block_entity(comparator_pos) {
+            Some(BlockEntity::Comparator { output_strength }) => *output_strength,
+            _ => 0,
+        }
+    };
+
+    let mut world = test_world();
+    world.set_block(BlockPos::new(6, 8, 4), Block::Lever { lever });
+    world.set_block(comparator_pos, Block::RedstoneComparator { comparator });
+
+    // A strongly powered solid block at the side does not count as a side input
+    world.set_block(side_pos, Block::Stone {});
+    world.set_block(side_pos.offset(BlockFace::Top), Block::Lever { lever });
+    assert_eq!(output_strength(&mut world), 15);
+
+    // Dust at the side does
+    world.set_block(side_pos.offset(BlockFace::Top), Block::Air {});
+    let wire = RedstoneWire::new(
+        RedstoneWireSide::None,
+        RedstoneWireSide::None,
+        RedstoneWireSide::Side,
+        RedstoneWireSide::Side,
+        5,
+    );
+    world.set_block(side_pos, Block::RedstoneWire { wire });
+    assert_eq!(output_strength(&mut world), 10);
+}
+
+#[test]
+fn comparator_rear_input_from_dust() {
+    use mchprs_blocks::blocks::{ComparatorMode, RedstoneComparator};
+    use mchprs_blocks::blocks::{RedstoneWire, RedstoneWireSide};
+    use mchprs_blocks::BlockDirection;
+
+    let comparator = RedstoneComparator {
+        facing: BlockDirection::North,
+        mode: ComparatorMode::Compare,
+        powered: false,
+    };
+    let comparator_pos = BlockPos::new(6, 8, 5);
+    let wire_pos = BlockPos::new(6, 8, 4);
+
+    let output_strength = |world: &mut PlotWorld| {
+        let block = world.get_block(comparator_pos);
+        redstone::update(block, world, comparator_pos);
+        world.run_scheduled_ticks();
+        match world.get_block_entity(comparator_pos) {
+            Some(BlockEntity::Comparator { output_strength }) => *output_strength,
+            _ => 0,
+        }
+    };
+
+    let mut world = test_world();
+    world.set_block(comparator_pos, Block::RedstoneComparator { comparator });
+
+    // Dust running into the comparator
+    let wire = |power| {
+        RedstoneWire::new(
+            RedstoneWireSide::Side,
+            RedstoneWireSide::Side,
+            RedstoneWireSide::None,
+            RedstoneWireSide::None,
+            power,
+        )
+    };
+    world.set_block(wire_pos, Block::RedstoneWire { wire: wire(12) });
+    assert_eq!(output_strength(&mut world), 12);
+    world.set_block(wire_pos, Block::RedstoneWire { wire: wire(4) });
+    assert_eq!(output_strength(&mut world), 4);
+
+    // Dust running across the back is still read as the rear input
+    let wire = RedstoneWire::new(
+        RedstoneWireSide::None,
+        RedstoneWireSide::None,
+        RedstoneWireSide::Side,
+        RedstoneWireSide::Side,
+        12,
+    );
+    world.set_block(wire_pos, Block::RedstoneWire { wire });
+    assert_eq!(output_strength(&mut world), 12);
+}
+
+#[test]
+fn comparator_reads_comparator_output() {
+    use mchprs_blocks::blocks::{ComparatorMode, RedstoneComparator};
+    use mchprs_blocks::blocks::{RedstoneWire, RedstoneWireSide};
+    use mchprs_blocks::BlockDirection;
+
+    let first_pos = BlockPos::new(6, 8, 5);
+    let second_pos = BlockPos::new(6, 8, 6);
+    let rear_wire_pos = BlockPos::new(6, 8, 4);
+    let side_wire_pos = BlockPos::new(7, 8, 5);
+    let comparator = |mode| RedstoneComparator {
+        facing: BlockDirection::North,
+        mode,
+        powered: false,
+    };
+    let wire = |north_south, east_west, power| {
+        RedstoneWire::new(north_south, north_south, east_west, east_west, power)
+    };
+
+    let settle = |world: &mut PlotWorld| {
+        let block = world.get_block(first_pos);
+        redstone::update(block, world, first_pos);
+        for _ in 0..4 {
+            world.run_scheduled_ticks();
+        }
+    };
+    let output_strength = |world: &PlotWorld, pos| match world.get_block_entity(pos) {
+        Some(BlockEntity::Comparator { output_strength }) => *output_strength,
+        _ => 0,
+    };
+
+    let mut world = test_world();
+    let first = comparator(ComparatorMode::Compare);
+    world.set_block(first_pos, Block::RedstoneComparator { comparator: first });
+    let second = comparator(ComparatorMode::Compare);
+    world.set_block(second_pos, Block::RedstoneComparator { comparator: second });
+    let rear_wire = wire(RedstoneWireSide::Side, RedstoneWireSide::None, 12);
+    world.set_block(rear_wire_pos, Block::RedstoneWire { wire: rear_wire });
+
+    settle(&mut world);
+    assert_eq!(output_strength(&world, first_pos), 12);
+    assert_eq!(output_strength(&world, second_pos), 12);
+
+    // The second comparator reads the analog output of the first one in subtract mode
+    let first = comparator(ComparatorMode::Subtract);
+    world.set_block(first_pos, Block::RedstoneComparator { comparator: first });
+    let side_wire = wire(RedstoneWireSide::None, RedstoneWireSide::Side, 5);
+    world.set_block(side_wire_pos, Block::RedstoneWire { wire: side_wire });
+    settle(&mut world);
+    assert_eq!(output_strength(&world, first_pos), 7);
+    assert_eq!(output_strength(&world, second_pos), 7);
+
+    let side_wire = wire(RedstoneWireSide::None, RedstoneWireSide::Side, 14);
+    world.set_block(side_wire_pos, Block::RedstoneWire { wire: side_wire });
+    settle(&mut world);
+    assert_eq!(output_strength(&world, first_pos), 0);
+    assert_eq!(output_strength(&world, second_pos), 0);
+    assert!(!matches!(
+        world.get_block(second_pos),
+        Block::RedstoneComparator { comparator } if comparator.powered
+    ));
+}
+
+#[test]
+fn lamp_stays_lit_through_short_pulses() {
+    use mchprs_blocks::blocks::{Lever, LeverFace};
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    let lever_pos = BlockPos::new(1, 8, 1);
+    let lamp_pos = BlockPos::new(2, 8, 1);
+    let set_lever = |world: &mut PlotWorld, powered: bool| {
+        let lever = Lever::new(LeverFace::Floor, BlockDirection::North, powered);
+        world.set_block(lever_pos, Block::Lever { lever });
+        redstone::update_surrounding_blocks(world, lever_pos);
+    };
+
+    set_lever(&mut world, true);
+    world.set_block(lamp_pos, Block::RedstoneLamp { lit: false });
+    redstone::update(world.get_block(lamp_pos), &mut world, lamp_pos);
+    // Lamps turn on instantly
+    assert_eq!(world.get_block(lamp_pos), Block::RedstoneLamp { lit: true });
+
+    // Power is only ever off for one tick at a time, so the lamp never gets to turn off
+    for _ in 0..5 {
+        set_lever(&mut world, false);
+        world.run_scheduled_ticks();
+        assert_eq!(world.get_block(lamp_pos), Block::RedstoneLamp { lit: true });
+        set_lever(&mut world, true);
+        world.run_scheduled_ticks();
+        assert_eq!(world.get_block(lamp_pos), Block::RedstoneLamp { lit: true });
+    }
+
+    // Once power stays off, it turns off two ticks later
+    set_lever(&mut world, false);
+    world.run_scheduled_ticks();
+    assert_eq!(world.get_block(lamp_pos), Block::RedstoneLamp { lit: true });
+    world.run_scheduled_ticks();
+    assert_eq!(
+        world.get_block(lamp_pos),
+        Block::RedstoneLamp { lit: false }
+    );
+}
+
+#[test]
+fn dust_powered_by_input_blocks() {
+    use mchprs_blocks::blocks::{ButtonFace, Lever, LeverFace, RedstoneWire, StoneButton};
+    use mchprs_blocks::BlockDirection;
+
+    let wire_power = |neighbors: &[(BlockPos, Block)]| {
+        let mut world = test_world();
+        let wire_pos = BlockPos::new(2, 8, 2);
+        for (pos, block) in neighbors {
+            world.set_block(*pos, *block);
+        }
+        let wire = RedstoneWire::default();
+        world.set_block(wire_pos, Block::RedstoneWire { wire });
+        redstone::update(world.get_block(wire_pos), &mut world, wire_pos);
+        match world.get_block(wire_pos) {
+            Block::RedstoneWire { wire } => wire.power,
+            _ => unreachable!(),
+        }
+    };
+    let next_to_wire = BlockPos::new(1, 8, 2);
+
+    let lever = Lever::new(LeverFace::Floor, BlockDirection::North, true);
+    assert_eq!(wire_power(&[(next_to_wire, Block::Lever { lever })]), 15);
+    let off = Lever {
+        powered: false,
+        ..lever
+    };
+    assert_eq!(
+        wire_power(&[(next_to_wire, Block::Lever { lever: off })]),
+        0
+    );
+
+    let button = StoneButton::new(ButtonFace::Floor, BlockDirection::North, true);
+    assert_eq!(
+        wire_power(&[(next_to_wire, Block::StoneButton { button })]),
+        15
+    );
+
+    let plate = Block::StonePressurePlate { powered: true };
+    assert_eq!(wire_power(&[(next_to_wire, plate)]), 15);
+
+    // A lever strongly powers the block it's attached to
+    let wall_lever = Lever::new(LeverFace::Wall, BlockDirection::West, true);
+    assert_eq!(
+        wire_power(&[
+            (next_to_wire, Block::Stone {}),
+            (BlockPos::new(0, 8, 2), Block::Lever { lever: wall_lever })
+        ]),
+        15
+    );
+    // but not blocks it's merely next to
+    let other_lever = Lever::new(LeverFace::Wall, BlockDirection::North, true);
+    assert_eq!(
+        wire_power(&[
+            (next_to_wire, Block::Stone {}),
+            (BlockPos::new(0, 8, 2), Block::Lever { lever: other_lever })
+        ]),
+        0
+    );
+}
+
+#[test]
+fn lamp_lit_by_weak_dust() {
+    use mchprs_blocks::blocks::{Lever, LeverFace, RedstoneWire, RedstoneWireSide};
+    use mchprs_blocks::BlockDirection;
+
+    let lamp_lit_by = |neighbors: &[(BlockPos, Block)]| {
+        let mut world = test_world();
+        let lamp_pos = BlockPos::new(2, 8, 2);
+        for (pos, block) in neighbors {
+            world.set_block(*pos, *block);
+        }
+        world.set_block(lamp_pos, Block::RedstoneLamp { lit: false });
+        redstone::update(world.get_block(lamp_pos), &mut world, lamp_pos);
+        world.get_block(lamp_pos) == Block::RedstoneLamp { lit: true }
+    };
+    let wire = |east_west: RedstoneWireSide, north_south: RedstoneWireSide, power: u8| {
+        let wire = RedstoneWire::new(north_south, north_south, east_west, east_west, power);
+        Block::RedstoneWire { wire }
+    };
+
+    // Dust pointing into the lamp at power 1
+    let pointing = wire(RedstoneWireSide::Side, RedstoneWireSide::None, 1);
+    assert!(lamp_lit_by(&[(BlockPos::new(1, 8, 2), pointing)]));
+    // Unpowered dust
+    let unpowered = wire(RedstoneWireSide::Side, RedstoneWireSide::None, 0);
+    assert!(!lamp_lit_by(&[(BlockPos::new(1, 8, 2), unpowered)]));
+    // Dust running past the lamp
+    let passing = wire(RedstoneWireSide::None, RedstoneWireSide::Side, 15);
+    assert!(!lamp_lit_by(&[
+        (BlockPos::new(1, 8, 1), passing),
+        (BlockPos::new(1, 8, 2), passing),
+        (BlockPos::new(1, 8, 3), passing),
+    ]));
+    // A strongly powered block next to the lamp
+    let lever = Lever::new(LeverFace::Floor, BlockDirection::North, true);
+    assert!(lamp_lit_by(&[
+        (BlockPos::new(1, 8, 2), Block::Stone {}),
+        (BlockPos::new(1, 9, 2), Block::Lever { lever }),
+    ]));
+}
+
+#[test]
+fn adjacent_solid_blocks_do_not_chain_power() {
+    use mchprs_blocks::blocks::{Lever, LeverFace};
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    // Two solid blocks next to each other, the first one strongly powered by a lever
+    let powered_pos = BlockPos::new(1, 8, 1);
+    let unpowered_pos = BlockPos::new(2, 8, 1);
+    let lamp_pos = BlockPos::new(3, 8, 1);
+    world.set_block(powered_pos, Block::Stone {});
+    world.set_block(unpowered_pos, Block::Stone {});
+    let lever = Lever::new(LeverFace::Floor, BlockDirection::North, true);
+    world.set_block(powered_pos.offset(BlockFace::Top), Block::Lever { lever });
+    world.set_block(lamp_pos, Block::RedstoneLamp { lit: false });
+
+    // Solid blocks only read what their neighbors emit, so this terminates
+    // and the power doesn't pass from one block into the next
+    let block = world.get_block(unpowered_pos);
+    assert_eq!(
+        redstone::get_redstone_power(block, &world, unpowered_pos, BlockFace::East),
+        0
+    );
+    assert!(!redstone::redstone_lamp_should_be_lit(&world, lamp_pos));
+
+    let block = world.get_block(powered_pos);
+    assert_eq!(
+        redstone::get_redstone_power(block, &world, powered_pos, BlockFace::East),
+        15
+    );
+}
+
+#[test]
+fn wire_power_change_sends_block_state() {
+    use mchprs_blocks::blocks::{Lever, LeverFace, RedstoneWire, RedstoneWireSide};
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    let lever = Lever::new(LeverFace::Floor, BlockDirection::North, true);
+    world.set_block(BlockPos::new(1, 8, 1), Block::Lever { lever });
+    let wire = |power| {
+        let wire = RedstoneWire::new(
+            RedstoneWireSide::None,
+            RedstoneWireSide::None,
+            RedstoneWireSide::Side,
+            RedstoneWireSide::Side,
+            power,
+        );
+        Block::RedstoneWire { wire }
+    };
+    // Six dust in a line, so the last one ends up with a power of 10
+    for x in 2..=7 {
+        world.set_block(BlockPos::new(x, 8, 1), wire(0));
+    }
+    world.flush_block_changes();
+
+    let first_wire = BlockPos::new(2, 8, 1);
+    redstone::update(world.get_block(first_wire), &mut world, first_wire);
+    let last_wire = BlockPos::new(7, 8, 1);
+    assert_eq!(world.get_block(last_wire), wire(10));
+
+    let records: Vec<(u8, u8, u8, u32)> = world.chunks[0]
+        .multi_blocks()
+        .flat_map(|packet| packet.records.iter())
+        .map(|r| (r.x, r.y, r.z, r.block_id))
+        .collect();
+    // Every dust changed power, and nothing else changed
+    assert_eq!(records.len(), 6);
+    assert!(records.contains(&(7, 8, 1, 2931)));
+    assert_eq!(wire(10).get_id(), 2931);
+}
+
+#[test]
+fn fix_redstone_settles_imported_circuit() {
+    use mchprs_blocks::blocks::{
+        Lever, LeverFace, RedstoneRepeater, RedstoneWire, RedstoneWireSide,
+    };
+    use mchprs_blocks::BlockDirection;
+
+    // Blocks are set without updates, like they are when a schematic is imported
+    let mut world = test_world();
+    let lever = Lever::new(LeverFace::Floor, BlockDirection::North, true);
+    world.set_block(BlockPos::new(1, 8, 1), Block::Lever { lever });
+    let wire = |power| {
+        let wire = RedstoneWire::new(
+            RedstoneWireSide::None,
+            RedstoneWireSide::None,
+            RedstoneWireSide::Side,
+            RedstoneWireSide::Side,
+            power,
+        );
+        Block::RedstoneWire { wire }
+    };
+    world.set_block(BlockPos::new(2, 8, 1), wire(0));
+    world.set_block(BlockPos::new(3, 8, 1), wire(0));
+    let repeater = RedstoneRepeater {
+        delay: 2,
+        facing: BlockDirection::West,
+        locked: false,
+        powered: false,
+    };
+    world.set_block(BlockPos::new(4, 8, 1), Block::RedstoneRepeater { repeater });
+    world.set_block(BlockPos::new(5, 8, 1), Block::RedstoneLamp { lit: false });
+    // A torch that should have been turned off by the powered block it is attached to
+    let torch_pos = BlockPos::new(11, 8, 10);
+    world.set_block(BlockPos::new(10, 8, 10), Block::Stone {});
+    world.set_block(BlockPos::new(10, 9, 10), Block::Lever { lever });
+    world.set_block(
+        torch_pos,
+        Block::RedstoneWallTorch {
+            lit: true,
+            facing: BlockDirection::East,
+        },
+    );
+
+    let (first_pos, second_pos) = world.get_corners();
+    assert_eq!(world.fix_redstone(first_pos, second_pos), (5, true));
+
+    assert_eq!(world.get_block(BlockPos::new(2, 8, 1)), wire(15));
+    assert_eq!(world.get_block(BlockPos::new(3, 8, 1)), wire(14));
+    assert_eq!(
+        world.get_block(BlockPos::new(4, 8, 1)),
+        Block::RedstoneRepeater {
+            repeater: RedstoneRepeater {
+                powered: true,
+                ..repeater
+            }
+        }
+    );
+    assert_eq!(
+        world.get_block(BlockPos::new(5, 8, 1)),
+        Block::RedstoneLamp { lit: true }
+    );
+    assert_eq!(
+        world.get_block(torch_pos),
+        Block::RedstoneWallTorch {
+            lit: false,
+            facing: BlockDirection::East,
+        }
+    );
+}
+
+#[test]
+fn forced_wire_power_read_by_comparator() {
+    use mchprs_blocks::blocks::{ComparatorMode, RedstoneComparator, RedstoneWire};
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    let wire_pos = BlockPos::new(6, 8, 4);
+    let comparator_pos = BlockPos::new(6, 8, 5);
+    world.set_block(
+        wire_pos,
+        Block::RedstoneWire {
+            wire: RedstoneWire::default(),
+        },
+    );
+    let comparator = RedstoneComparator {
+        facing: BlockDirection::North,
+        mode: ComparatorMode::Compare,
+        powered: false,
+    };
+    world.set_block(comparator_pos, Block::RedstoneComparator { comparator });
+
+    assert!(redstone::force_power(&mut world, wire_pos, 12));
+    world.run_scheduled_ticks();
+    assert!(matches!(
+        world.get_block_entity(comparator_pos),
+        Some(BlockEntity::Comparator {
+            output_strength: 12
+        })
+    ));
+    assert!(matches!(
+        world.get_block(wire_pos),
+        Block::RedstoneWire { wire } if wire.power == 12
+    ));
+
+    // Only dust and analog outputs can be forced
+    world.set_block(BlockPos::new(1, 8, 1), Block::Stone {});
+    assert!(!redstone::force_power(
+        &mut world,
+        BlockPos::new(1, 8, 1),
+        12
+    ));
+}
+
+#[test]
+fn comparator_block_state_only_records_powered() {
+    use mchprs_blocks::block_entities::ContainerType;
+    use mchprs_blocks::blocks::{ComparatorMode, RedstoneComparator, RedstoneWire};
+    use mchprs_blocks::BlockDirection;
+
+    let comparator = RedstoneComparator {
+        facing: BlockDirection::North,
+        mode: ComparatorMode::Compare,
+        powered: false,
+    };
+    let mut world = test_world();
+    // Two barrels read by comparators, each driving a line of dust
+    for (x, comparator_override) in [(2, 5), (4, 12)] {
+        world.set_block(BlockPos::new(x, 8, 4), Block::Barrel {});
+        world.set_block_entity(
+            BlockPos::new(x, 8, 4),
+            BlockEntity::Container {
+                comparator_override,
+                inventory: Vec::new(),
+                ty: ContainerType::Barrel,
+            },
+        );
+        world.set_block(
+            BlockPos::new(x, 8, 5),
+            Block::RedstoneComparator { comparator },
+        );
+        world.set_block(
+            BlockPos::new(x, 8, 6),
+            Block::RedstoneWire {
+                wire: RedstoneWire::default(),
+            },
+        );
+        redstone::update(
+            world.get_block(BlockPos::new(x, 8, 5)),
+            &mut world,
+            BlockPos::new(x, 8, 5),
+        );
+    }
+    world.run_scheduled_ticks();
+
+    let powered_id = Block::RedstoneComparator {
+        comparator: RedstoneComparator {
+            powered: true,
+            ..comparator
+        },
+    }
+    .get_id();
+    assert_eq!(world.get_block_raw(BlockPos::new(2, 8, 5)), powered_id);
+    assert_eq!(world.get_block_raw(BlockPos::new(4, 8, 5)), powered_id);
+    assert!(matches!(
+        world.get_block(BlockPos::new(2, 8, 6)),
+        Block::RedstoneWire { wire } if wire.power == 5
+    ));
+    assert!(matches!(
+        world.get_block(BlockPos::new(4, 8, 6)),
+        Block::RedstoneWire { wire } if wire.power == 12
+    ));
+}
+
+#[test]
+fn transparent_blocks_reject_dust_and_strong_power() {
+    use crate::interaction;
+    use mchprs_blocks::blocks::{RedstoneRepeater, RedstoneWire};
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    let wire = Block::RedstoneWire {
+        wire: RedstoneWire::default(),
+    };
+    world.set_block(BlockPos::new(5, 8, 5), Block::Glass {});
+    assert!(!interaction::is_valid_position(
+        wire,
+        &world,
+        BlockPos::new(5, 9, 5)
+    ));
+    // The sandstone floor is fine
+    assert!(interaction::is_valid_position(
+        wire,
+        &world,
+        BlockPos::new(6, 8, 5)
+    ));
+
+    // A powered repeater pointing through a block at a lamp
+    let repeater = Block::RedstoneRepeater {
+        repeater: RedstoneRepeater {
+            delay: 1,
+            facing: BlockDirection::West,
+            locked: false,
+            powered: true,
+        },
+    };
+    for (z, relay) in [(10, Block::Glass {}), (12, Block::Stone {})] {
+        let lamp_pos = BlockPos::new(6, 8, z);
+        world.set_block(BlockPos::new(4, 8, z), repeater);
+        world.set_block(BlockPos::new(5, 8, z), relay);
+        world.set_block(lamp_pos, Block::RedstoneLamp { lit: false });
+        redstone::update(world.get_block(lamp_pos), &mut world, lamp_pos);
+    }
+    world.run_scheduled_ticks();
+    assert_eq!(
+        world.get_block(BlockPos::new(6, 8, 10)),
+        Block::RedstoneLamp { lit: false }
+    );
+    assert_eq!(
+        world.get_block(BlockPos::new(6, 8, 12)),
+        Block::RedstoneLamp { lit: true }
+    );
+}
+
+#[test]
+fn repeater_delay_counted_in_redstone_ticks() {
+    use mchprs_blocks::blocks::{Lever, LeverFace, RedstoneRepeater};
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    let lever_pos = BlockPos::new(4, 8, 4);
+    let repeater_pos = BlockPos::new(5, 8, 4);
+    world.set_block(
+        lever_pos,
+        Block::Lever {
+            lever: Lever {
+                face: LeverFace::Floor,
+                facing: BlockDirection::North,
+                powered: true,
+            },
+        },
+    );
+    world.set_block(
+        repeater_pos,
+        Block::RedstoneRepeater {
+            repeater: RedstoneRepeater {
+                delay: 1,
+                facing: BlockDirection::West,
+                locked: false,
+                powered: false,
+            },
+        },
+    );
+    redstone::update(world.get_block(repeater_pos), &mut world, repeater_pos);
+    assert_eq!(world.to_be_ticked[0].ticks_left, 1);
+    assert!(matches!(
+        world.get_block(repeater_pos),
+        Block::RedstoneRepeater { repeater } if !repeater.powered
+    ));
+
+    // A delay 1 repeater takes one redstone tick, which is two game ticks
+    world.run_scheduled_ticks();
+    assert!(matches!(
+        world.get_block(repeater_pos),
+        Block::RedstoneRepeater { repeater } if repeater.powered
+    ));
+}
+
+#[test]
+fn dust_settles_before_repeater_samples() {
+    use mchprs_blocks::blocks::{Lever, LeverFace, RedstoneRepeater, RedstoneWire};
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    let lever_pos = BlockPos::new(4, 8, 4);
+    let first_repeater = BlockPos::new(5, 8, 4);
+    let second_repeater = BlockPos::new(9, 8, 4);
+    let wires = [
+        BlockPos::new(6, 8, 4),
+        BlockPos::new(7, 8, 4),
+        BlockPos::new(8, 8, 4),
+    ];
+    let repeater = RedstoneRepeater {
+        delay: 1,
+        facing: BlockDirection::West,
+        locked: false,
+        powered: false,
+    };
+    world.set_block(first_repeater, Block::RedstoneRepeater { repeater });
+    world.set_block(second_repeater, Block::RedstoneRepeater { repeater });
+    for pos in wires {
+        let wire = RedstoneWire::default();
+        world.set_block(pos, Block::RedstoneWire { wire });
+    }
+    let powered = |world: &PlotWorld, pos| matches!(world.get_block(pos), Block::RedstoneRepeater { repeater } if repeater.powered);
+    let wire_powers = |world: &PlotWorld| {
+        wires.map(|pos| match world.get_block(pos) {
+            Block::RedstoneWire { wire } => wire.power,
+            _ => unreachable!(),
+        })
+    };
+
+    for on in [true, false] {
+        let lever = Lever::new(LeverFace::Floor, BlockDirection::North, on);
+        world.set_block(lever_pos, Block::Lever { lever });
+        redstone::update(world.get_block(first_repeater), &mut world, first_repeater);
+
+        // The first repeater changes, and its dust follows within the same tick,
+        // so the second repeater already saw the change and is waiting on its delay
+        world.run_scheduled_ticks();
+        assert_eq!(powered(&world, first_repeater), on);
+        let expected = if on { [15, 14, 13] } else { [0, 0, 0] };
+        assert_eq!(wire_powers(&world), expected);
+        assert_eq!(powered(&world, second_repeater), !on);
+        assert!(world.pending_tick_at(second_repeater));
+
+        world.run_scheduled_ticks();
+        assert_eq!(powered(&world, second_repeater), on);
+        assert!(world.to_be_ticked.is_empty());
+    }
+}
+
+#[test]
+fn repeater_not_powered_from_front() {
+    use mchprs_blocks::blocks::RedstoneRepeater;
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    let repeater_pos = BlockPos::new(5, 8, 4);
+    let front_pos = BlockPos::new(6, 8, 4);
+    let repeater_powered = |world: &PlotWorld| {
+        matches!(
+            world.get_block(repeater_pos),
+            Block::RedstoneRepeater { repeater } if repeater.powered
+        )
+    };
+    world.set_block(
+        repeater_pos,
+        Block::RedstoneRepeater {
+            repeater: RedstoneRepeater {
+                delay: 1,
+                facing: BlockDirection::West,
+                locked: false,
+                powered: false,
+            },
+        },
+    );
+
+    // A power source placed on the output side
+    world.set_block(front_pos, Block::RedstoneBlock {});
+    redstone::update_surrounding_blocks(&mut world, front_pos);
+    assert!(world.to_be_ticked.is_empty());
+    for _ in 0..4 {
+        world.run_scheduled_ticks();
+    }
+    assert!(!repeater_powered(&world));
+}
+
+#[test]
+fn neighbor_update_only_updates_that_block() {
+    use mchprs_blocks::blocks::{Lever, LeverFace, RedstoneRepeater};
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    // Two repeaters that were placed next to a powered lever without being updated
+    let lever_pos = BlockPos::new(5, 8, 5);
+    let poked_pos = BlockPos::new(6, 8, 5);
+    let other_pos = BlockPos::new(5, 8, 6);
+    world.set_block(
+        lever_pos,
+        Block::Lever {
+            lever: Lever {
+                face: LeverFace::Floor,
+                facing: BlockDirection::North,
+                powered: true,
+            },
+        },
+    );
+    let repeater = |facing| Block::RedstoneRepeater {
+        repeater: RedstoneRepeater {
+            delay: 1,
+            facing,
+            locked: false,
+            powered: false,
+        },
+    };
+    world.set_block(poked_pos, repeater(BlockDirection::West));
+    world.set_block(other_pos, repeater(BlockDirection::North));
+
+    world.neighbor_update(poked_pos);
+    assert_eq!(world.to_be_ticked.len(), 1);
+    assert_eq!(world.to_be_ticked[0].pos, poked_pos);
+
+    world.run_scheduled_ticks();
+    let powered = |world: &PlotWorld, pos| {
+        matches!(
+            world.get_block(pos),
+            Block::RedstoneRepeater { repeater } if repeater.powered
+        )
+    };
+    assert!(powered(&world, poked_pos));
+    assert!(!powered(&world, other_pos));
+}
+
+#[test]
+fn dust_does_not_climb_transparent_blocks() {
+    use mchprs_blocks::blocks::{RedstoneWire, RedstoneWireSide};
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    let wire_pos = BlockPos::new(5, 8, 5);
+    let climb_pos = BlockPos::new(6, 8, 5);
+    let wire = RedstoneWire::default();
+    world.set_block(wire_pos, Block::RedstoneWire { wire });
+    world.set_block(
+        climb_pos.offset(BlockFace::Top),
+        Block::RedstoneWire { wire },
+    );
+
+    world.set_block(climb_pos, Block::Stone {});
+    assert_eq!(
+        redstone::wire::get_side(&world, wire_pos, BlockDirection::East),
+        RedstoneWireSide::Up
+    );
+
+    world.set_block(climb_pos, Block::Glass {});
+    assert_eq!(
+        redstone::wire::get_side(&world, wire_pos, BlockDirection::East),
+        RedstoneWireSide::None
+    );
+}
+
+#[test]
+fn side_comparator_locks_repeater() {
+    use mchprs_blocks::blocks::{ComparatorMode, RedstoneComparator, RedstoneRepeater};
+    use mchprs_blocks::blocks::{RedstoneWire, RedstoneWireSide};
+    use mchprs_blocks::BlockDirection;
+
+    let repeater_pos = BlockPos::new(6, 8, 5);
+    let rear_wire_pos = BlockPos::new(6, 8, 4);
+    // The comparator points west, into the east side of the repeater
+    let comparator_pos = BlockPos::new(7, 8, 5);
+    let comparator_input_pos = BlockPos::new(8, 8, 5);
+    let wire = |north_south, east_west, power| {
+        RedstoneWire::new(north_south, north_south, east_west, east_west, power)
+    };
+    let update_and_settle = |world: &mut PlotWorld, pos| {
+        let block = world.get_block(pos);
+        redstone::update(block, world, pos);
+        for _ in 0..4 {
+            world.run_scheduled_ticks();
+        }
+    };
+    let repeater = |world: &PlotWorld| match world.get_block(repeater_pos) {
+        Block::RedstoneRepeater { repeater } => repeater,
+        block => panic!("expected a repeater, got {:?}", block),
+    };
+
+    let mut world = test_world();
+    let rear_wire = wire(RedstoneWireSide::Side, RedstoneWireSide::None, 15);
+    world.set_block(rear_wire_pos, Block::RedstoneWire { wire: rear_wire });
+    let placed = RedstoneRepeater {
+        delay: 1,
+        facing: BlockDirection::North,
+        locked: false,
+        powered: false,
+    };
+    world.set_block(repeater_pos, Block::RedstoneRepeater { repeater: placed });
+    world.set_block(
+        comparator_pos,
+        Block::RedstoneComparator {
+            comparator: RedstoneComparator {
+                facing: BlockDirection::East,
+                mode: ComparatorMode::Compare,
+                powered: false,
+            },
+        },
+    );
+    update_and_settle(&mut world, repeater_pos);
+    assert!(repeater(&world).powered);
+    assert!(!repeater(&world).locked);
+
+    // A weak analog signal from the comparator is enough to lock the repeater
+    let side_wire = wire(RedstoneWireSide::None, RedstoneWireSide::Side, 3);
+    world.set_block(
+        comparator_input_pos,
+        Block::RedstoneWire { wire: side_wire },
+    );
+    update_and_settle(&mut world, comparator_pos);
+    assert!(repeater(&world).locked);
+
+    // The lock holds the repeater's output after its input turns off
+    world.set_block(rear_wire_pos, Block::Air {});
+    update_and_settle(&mut world, repeater_pos);
+    assert!(repeater(&world).locked);
+    assert!(repeater(&world).powered);
+
+    world.set_block(comparator_input_pos, Block::Air {});
+    update_and_settle(&mut world, comparator_pos);
+    assert!(!repeater(&world).locked);
+    assert!(!repeater(&world).powered);
+}
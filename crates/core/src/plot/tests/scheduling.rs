@@ -0,0 +1,262 @@
+use super::{test_world, TestPlot};
+use crate::plot::{clock_detector, PlotWorld, TickWarp};
+use crate::redstone;
+use crate::world::World;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use mchprs_world::TickPriority;
+use std::time::{Duration, Instant};
+
+/// A plot world that runs at most `tick_update_limit` scheduled ticks each tick
+fn limited_world(tick_update_limit: usize) -> PlotWorld {
+    let mut world = test_world();
+    world.tick_update_limit = tick_update_limit;
+    world
+}
+
+#[test]
+fn scheduled_tick_limit() {
+    let mut world = limited_world(100);
+    // A runaway circuit that has scheduled far more ticks than the limit for the same tick
+    for i in 0..250 {
+        world.schedule_tick(BlockPos::new(i % 100, 20, i / 100), 1, TickPriority::Normal);
+    }
+
+    assert_eq!(world.run_scheduled_ticks(), 100);
+    assert_eq!(world.to_be_ticked.len(), 150);
+    assert!(world.tick_limit_reached);
+    assert_eq!(world.run_scheduled_ticks(), 100);
+    // Still behind, so this is the same episode and nothing new is logged
+    assert!(world.tick_limit_reached);
+    assert_eq!(world.run_scheduled_ticks(), 50);
+    assert!(!world.tick_limit_reached);
+    assert!(world.to_be_ticked.is_empty());
+}
+
+#[test]
+fn scheduled_tick_priority_order() {
+    // With a limit of one, only the first tick in line is run each tick
+    let mut world = limited_world(1);
+    let normal_pos = BlockPos::new(1, 20, 1);
+    let high_pos = BlockPos::new(2, 20, 1);
+    let later_high_pos = BlockPos::new(3, 20, 1);
+    world.schedule_tick(normal_pos, 1, TickPriority::Normal);
+    world.schedule_tick(high_pos, 1, TickPriority::High);
+    world.schedule_tick(later_high_pos, 1, TickPriority::High);
+
+    world.run_scheduled_ticks();
+    let remaining: Vec<BlockPos> = world.to_be_ticked.iter().map(|e| e.pos).collect();
+    assert_eq!(remaining, vec![later_high_pos, normal_pos]);
+
+    world.run_scheduled_ticks();
+    let remaining: Vec<BlockPos> = world.to_be_ticked.iter().map(|e| e.pos).collect();
+    assert_eq!(remaining, vec![normal_pos]);
+}
+
+#[test]
+fn tick_warp_matches_stepping() {
+    use mchprs_blocks::blocks::{Lever, LeverFace, RedstoneRepeater};
+    use mchprs_blocks::BlockDirection;
+
+    // A lever feeding a line of slow repeaters into a lamp
+    let circuit = || {
+        let mut world = test_world();
+        let lever = Lever::new(LeverFace::Floor, BlockDirection::North, true);
+        world.set_block(BlockPos::new(1, 8, 1), Block::Lever { lever });
+        for x in 2..7 {
+            let repeater = RedstoneRepeater {
+                delay: 4,
+                facing: BlockDirection::West,
+                locked: false,
+                powered: false,
+            };
+            world.set_block(BlockPos::new(x, 8, 1), Block::RedstoneRepeater { repeater });
+        }
+        world.set_block(BlockPos::new(7, 8, 1), Block::RedstoneLamp { lit: false });
+        let first_repeater = BlockPos::new(2, 8, 1);
+        redstone::update(world.get_block(first_repeater), &mut world, first_repeater);
+        world
+    };
+    let states = |world: &PlotWorld| -> Vec<Block> {
+        (1..=7)
+            .map(|x| world.get_block(BlockPos::new(x, 8, 1)))
+            .collect()
+    };
+
+    for ticks in [7, 100] {
+        let mut stepped = circuit();
+        for _ in 0..ticks {
+            stepped.run_scheduled_ticks();
+        }
+
+        let mut warped = circuit();
+        let mut warp = TickWarp::new(ticks);
+        // With no time budget, the warp is split into many small batches
+        while !warp.run_batch(Duration::ZERO, || {
+            warped.run_scheduled_ticks();
+        }) {}
+
+        assert_eq!(states(&warped), states(&stepped));
+        assert_eq!(warped.to_be_ticked, stepped.to_be_ticked);
+    }
+    // The signal is still travelling after 7 ticks, but reaches the lamp well before 100
+    let mut warped = circuit();
+    let mut warp = TickWarp::new(100);
+    while !warp.run_batch(TickWarp::BATCH_TIME, || {
+        warped.run_scheduled_ticks();
+    }) {}
+    assert_eq!(
+        warped.get_block(BlockPos::new(7, 8, 1)),
+        Block::RedstoneLamp { lit: true }
+    );
+}
+
+#[test]
+fn same_seed_same_random_outcomes() {
+    use crate::plot::worldedit::WorldEditPattern;
+    use std::str::FromStr;
+
+    let pattern = WorldEditPattern::from_str("50%stone,30%glass,20%sandstone").unwrap();
+    let picks = |world: &mut PlotWorld| -> Vec<Block> {
+        (0..100).map(|_| pattern.pick(&mut world.rng)).collect()
+    };
+
+    let mut first = test_world();
+    let mut second = test_world();
+    first.rng = PlotWorld::create_rng(Some(1234));
+    second.rng = PlotWorld::create_rng(Some(1234));
+    assert_eq!(picks(&mut first), picks(&mut second));
+
+    let mut other_seed = test_world();
+    other_seed.rng = PlotWorld::create_rng(Some(4321));
+    assert_ne!(picks(&mut first), picks(&mut other_seed));
+}
+
+#[test]
+fn instant_redstone_ignores_repeater_delay() {
+    use mchprs_blocks::blocks::RedstoneRepeater;
+    use mchprs_blocks::BlockDirection;
+
+    let input_pos = BlockPos::new(4, 8, 4);
+    let repeater_pos = BlockPos::new(5, 8, 4);
+    let repeater = RedstoneRepeater {
+        delay: 4,
+        facing: BlockDirection::West,
+        locked: false,
+        powered: false,
+    };
+    let powered = |world: &PlotWorld| matches!(world.get_block(repeater_pos), Block::RedstoneRepeater { repeater } if repeater.powered);
+    let power_repeater = |world: &mut PlotWorld| {
+        world.set_block(repeater_pos, Block::RedstoneRepeater { repeater });
+        world.set_block(input_pos, Block::RedstoneBlock {});
+        redstone::update(world.get_block(repeater_pos), world, repeater_pos);
+        world.run_scheduled_ticks();
+    };
+
+    let mut world = test_world();
+    power_repeater(&mut world);
+    assert!(!powered(&world));
+
+    world.set_block(input_pos, Block::Air {});
+    world.to_be_ticked.clear();
+    world.instant_redstone = true;
+    power_repeater(&mut world);
+    assert!(powered(&world));
+}
+
+#[test]
+fn fast_clock_warning() {
+    use mchprs_blocks::blocks::{RedstoneRepeater, RedstoneWire, RedstoneWireSide};
+    use mchprs_blocks::BlockDirection;
+
+    let mut world = test_world();
+    // Four repeaters around a square with dust in the corners. Every other repeater starts out
+    // powered, so each one turns on and off every redstone tick.
+    let repeaters = [
+        (BlockPos::new(5, 8, 4), BlockDirection::West),
+        (BlockPos::new(6, 8, 5), BlockDirection::North),
+        (BlockPos::new(5, 8, 6), BlockDirection::East),
+        (BlockPos::new(4, 8, 5), BlockDirection::South),
+    ];
+    for (i, (pos, facing)) in repeaters.into_iter().enumerate() {
+        let repeater = RedstoneRepeater {
+            delay: 1,
+            facing,
+            locked: false,
+            powered: i % 2 == 0,
+        };
+        world.set_block(pos, Block::RedstoneRepeater { repeater });
+    }
+    let (side, none) = (RedstoneWireSide::Side, RedstoneWireSide::None);
+    let corners = [
+        (
+            BlockPos::new(6, 8, 4),
+            RedstoneWire::new(none, side, none, side, 0),
+        ),
+        (
+            BlockPos::new(6, 8, 6),
+            RedstoneWire::new(side, none, none, side, 0),
+        ),
+        (
+            BlockPos::new(4, 8, 6),
+            RedstoneWire::new(side, none, side, none, 0),
+        ),
+        (
+            BlockPos::new(4, 8, 4),
+            RedstoneWire::new(none, side, side, none, 0),
+        ),
+    ];
+    for (pos, wire) in corners {
+        world.set_block(pos, Block::RedstoneWire { wire });
+    }
+    // Every part reacts to its neighbors, as if it had just been placed
+    for pos in corners
+        .map(|(pos, _)| pos)
+        .into_iter()
+        .chain(repeaters.map(|(pos, _)| pos))
+    {
+        redstone::update(world.get_block(pos), &mut world, pos);
+    }
+    let first_powered = |world: &PlotWorld| {
+        matches!(
+            world.get_block(repeaters[0].0),
+            Block::RedstoneRepeater { repeater } if repeater.powered
+        )
+    };
+
+    let threshold = 20;
+    let start = Instant::now();
+    assert_eq!(world.clock_detector.check(start, threshold), None);
+    for second in 1..=clock_detector::CLOCK_WARNING_SECONDS + 1 {
+        for _ in 0..20 {
+            let was_powered = first_powered(&world);
+            world.run_scheduled_ticks();
+            // A 2-tick clock, on for one redstone tick and then off for one
+            assert_ne!(first_powered(&world), was_powered);
+        }
+        let now = start + Duration::from_secs(second as u64);
+        let warning = world.clock_detector.check(now, threshold);
+        if second == clock_detector::CLOCK_WARNING_SECONDS {
+            // The clock is in the first chunk section of the plot
+            assert_eq!(warning, Some(BlockPos::new(8, 8, 8)));
+        } else {
+            assert_eq!(warning, None, "unexpected warning after {} seconds", second);
+        }
+    }
+}
+
+#[test]
+fn huge_radv_continues_in_background() {
+    let mut test = TestPlot::new(-51, 10);
+    let (player, _) = test.join("Stepper");
+    let start = Instant::now();
+    test.run_command(player, "/radv 4000000000");
+    // Only a single batch runs right away, so the plot keeps beating for the watchdog
+    assert!(start.elapsed() < Duration::from_secs(5));
+    let warp = test.plot.tick_warp.as_ref().unwrap();
+    assert_eq!(warp.ticks, 4_000_000_000);
+    assert!(warp.remaining > 0);
+
+    test.run_command(player, "/tick warp stop");
+    assert!(test.plot.tick_warp.is_none());
+}
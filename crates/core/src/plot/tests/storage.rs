@@ -0,0 +1,177 @@
+use super::{plot_pos, test_world, TestPlot};
+use crate::plot::{PLOT_BLOCK_HEIGHT, PLOT_BLOCK_WIDTH, PLOT_FLOOR_LAYERS, PLOT_WIDTH};
+use crate::world::storage::{Chunk, StorageKind};
+use crate::world::World;
+use mchprs_blocks::block_entities::BlockEntity;
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+use mchprs_world::TickPriority;
+use serde_json::json;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn chunk_save_and_load_test() {
+    let mut chunk = Chunk::empty(1, 1);
+    chunk.set_block(13, 63, 12, 332);
+    chunk.set_block(13, 62, 12, 331);
+    let chunk_data = chunk.save();
+    let loaded_chunk = Chunk::load(1, 1, chunk_data);
+    assert_eq!(loaded_chunk.get_block(13, 63, 12), 332);
+    assert_eq!(loaded_chunk.get_block(13, 62, 12), 331);
+    assert_eq!(loaded_chunk.get_block(13, 64, 12), 0);
+}
+
+#[test]
+fn saved_clock_state_preserved_until_stepped() {
+    let mut test = TestPlot::new(-44, 10);
+    let lamp_pos = plot_pos(&test, 1, 8, 1);
+    // A lamp partway through turning off, like one in a running clock
+    test.plot
+        .world
+        .set_block(lamp_pos, Block::RedstoneLamp { lit: true });
+    test.plot
+        .world
+        .schedule_tick(lamp_pos, 2, TickPriority::Normal);
+    test.plot.save();
+    let saved_ticks = test.plot.world.to_be_ticked.clone();
+    drop(test);
+
+    let mut loaded = TestPlot::load(-44, 10);
+    assert!(loaded.plot.frozen);
+    assert_eq!(
+        loaded.plot.world.get_block(lamp_pos),
+        Block::RedstoneLamp { lit: true }
+    );
+    assert_eq!(loaded.plot.world.to_be_ticked, saved_ticks);
+
+    // Frozen plots don't tick on their own, even with someone in them
+    let (player, _client) = loaded.join("SavedClock");
+    thread::sleep(Duration::from_millis(300));
+    loaded.plot.update();
+    assert_eq!(
+        loaded.plot.world.get_block(lamp_pos),
+        Block::RedstoneLamp { lit: true }
+    );
+    assert_eq!(loaded.plot.world.to_be_ticked, saved_ticks);
+
+    loaded.run_command(player, "/radv 1");
+    assert_eq!(
+        loaded.plot.world.get_block(lamp_pos),
+        Block::RedstoneLamp { lit: true }
+    );
+    loaded.run_command(player, "/radv 1");
+    assert_eq!(
+        loaded.plot.world.get_block(lamp_pos),
+        Block::RedstoneLamp { lit: false }
+    );
+}
+
+#[test]
+fn label_survives_save_and_load() {
+    let mut world = test_world();
+    let pos = BlockPos::new(3, 8, 3);
+    assert!(world.place_label(pos, "clock divider output"));
+    assert!(!world.place_label(pos, "waytoolongforasinglesignrow"));
+
+    let chunk_data: Vec<_> = world.chunks.iter_mut().map(|c| c.save()).collect();
+    let mut loaded = test_world();
+    loaded.chunks = chunk_data
+        .into_iter()
+        .enumerate()
+        .map(|(i, c)| Chunk::load(i as i32 / PLOT_WIDTH, i as i32 % PLOT_WIDTH, c))
+        .collect();
+
+    assert!(matches!(loaded.get_block(pos), Block::Sign { .. }));
+    let Some(BlockEntity::Sign(sign)) = loaded.get_block_entity(pos) else {
+        panic!("label sign block entity was not loaded");
+    };
+    assert_eq!(
+        sign.rows,
+        [
+            json!({ "text": "clock divider" }).to_string(),
+            json!({ "text": "output" }).to_string(),
+            json!({ "text": "" }).to_string(),
+            json!({ "text": "" }).to_string(),
+        ]
+    );
+}
+
+#[test]
+fn generated_borders_use_configured_blocks() {
+    let mut world = test_world();
+    world.generate_borders(Block::Glass {}, Block::Clay {});
+    let last = PLOT_BLOCK_WIDTH - 1;
+    for y in 0..PLOT_FLOOR_LAYERS {
+        assert_eq!(world.get_block(BlockPos::new(0, y, 40)), Block::Clay {});
+        assert_eq!(
+            world.get_block(BlockPos::new(last, y, last)),
+            Block::Clay {}
+        );
+        assert_eq!(world.get_block(BlockPos::new(40, y, last)), Block::Clay {});
+        assert_eq!(world.get_block(BlockPos::new(1, y, 1)), Block::Glass {});
+        assert_eq!(world.get_block(BlockPos::new(100, y, 37)), Block::Glass {});
+    }
+    assert_eq!(
+        world.get_block(BlockPos::new(100, PLOT_FLOOR_LAYERS, 37)),
+        Block::Air {}
+    );
+
+    // Running it again on a generated plot is a no-op
+    let before: Vec<_> = world.chunks.iter_mut().map(|c| c.save().sections).collect();
+    world.generate_borders(Block::Glass {}, Block::Clay {});
+    let after: Vec<_> = world.chunks.iter_mut().map(|c| c.save().sections).collect();
+    assert_eq!(before, after);
+}
+
+#[test]
+fn clear_resets_plot_to_generated_state() {
+    let mut world = test_world();
+    let build = [
+        BlockPos::new(10, PLOT_FLOOR_LAYERS, 10),
+        BlockPos::new(64, 100, 3),
+        BlockPos::new(PLOT_BLOCK_WIDTH - 1, PLOT_BLOCK_HEIGHT - 1, 0),
+    ];
+    for pos in build {
+        world.set_block(pos, Block::Stone {});
+        world.schedule_tick(pos, 4, TickPriority::Normal);
+    }
+    world.set_block(BlockPos::new(20, 3, 20), Block::Glass {});
+
+    world.clear(Block::Sandstone {}, Block::StoneBricks {});
+
+    assert!(world.to_be_ticked.is_empty());
+    for pos in build {
+        assert_eq!(world.get_block(pos), Block::Air {});
+    }
+    let (first, second) = world.get_corners();
+    let above_floor = BlockPos::new(first.x, PLOT_FLOOR_LAYERS, first.z);
+    for pos in BlockPos::cuboid(above_floor, second) {
+        assert_eq!(world.get_block_raw(pos), 0);
+    }
+    assert_eq!(
+        world.get_block(BlockPos::new(20, 3, 20)),
+        Block::Sandstone {}
+    );
+    assert_eq!(
+        world.get_block(BlockPos::new(0, 3, 20)),
+        Block::StoneBricks {}
+    );
+}
+
+#[test]
+fn storage_kind_survives_clear_and_reload() {
+    let mut test = TestPlot::new(-52, 10);
+    let (owner, _) = test.join("SparseOwner");
+    test.plot.owner = Some(test.plot.players[owner].uuid);
+
+    test.run_command(owner, "/storage sparse");
+    assert_eq!(test.plot.world.storage_kind(), StorageKind::Sparse);
+    test.run_command(owner, "/plot clear");
+    assert_eq!(test.plot.world.storage_kind(), StorageKind::Sparse);
+    test.plot.save();
+    drop(test);
+
+    let loaded = TestPlot::load(-52, 10);
+    assert_eq!(loaded.plot.world.storage_kind(), StorageKind::Sparse);
+}
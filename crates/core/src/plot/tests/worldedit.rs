@@ -0,0 +1,118 @@
+use super::test_world;
+use crate::plot::worldedit;
+use crate::world::{for_each_block_optimized, World};
+use mchprs_blocks::blocks::Block;
+use mchprs_blocks::BlockPos;
+
+#[test]
+fn clone_region_copies_blocks() {
+    use worldedit::{CloneMask, UpdateContext};
+
+    let mut world = test_world();
+    let first_pos = BlockPos::new(1, 8, 1);
+    let second_pos = BlockPos::new(3, 9, 3);
+    world.set_block(BlockPos::new(1, 8, 1), Block::RedstoneLamp { lit: false });
+    world.set_block(BlockPos::new(2, 9, 3), Block::Stone {});
+    world.set_block(BlockPos::new(3, 8, 2), Block::Glass {});
+    let offset = BlockPos::new(10, 0, 0);
+
+    let copied = worldedit::clone_region(
+        &mut world,
+        first_pos,
+        second_pos,
+        offset,
+        &CloneMask::Replace,
+        &mut UpdateContext::default(),
+    );
+    assert_eq!(copied, 18);
+    for_each_block_optimized(&world, first_pos, second_pos, |pos| {
+        assert_eq!(world.get_block(pos), world.get_block(pos + offset));
+    });
+
+    // Masked clones leave the destination alone where the source is air
+    let masked_offset = BlockPos::new(20, 0, 0);
+    world.set_block(BlockPos::new(22, 8, 1), Block::Sandstone {});
+    let copied = worldedit::clone_region(
+        &mut world,
+        first_pos,
+        second_pos,
+        masked_offset,
+        &CloneMask::Masked,
+        &mut UpdateContext::default(),
+    );
+    assert_eq!(copied, 3);
+    assert_eq!(
+        world.get_block(BlockPos::new(22, 8, 1)),
+        Block::Sandstone {}
+    );
+    assert_eq!(world.get_block(BlockPos::new(22, 9, 3)), Block::Stone {});
+}
+
+#[test]
+fn bulk_updates_flushed_once() {
+    use mchprs_blocks::blocks::{RedstoneRepeater, RedstoneWire, RedstoneWireSide};
+    use mchprs_blocks::BlockDirection;
+    use worldedit::{CloneMask, UpdateContext};
+
+    // A redstone block powering a line of dust into a repeater and a lamp.
+    // Nothing in the source is updated, so all of it starts unpowered.
+    let mut world = test_world();
+    let first_pos = BlockPos::new(1, 8, 1);
+    let second_pos = BlockPos::new(16, 8, 1);
+    world.set_block(first_pos, Block::RedstoneBlock {});
+    for x in 2..=14 {
+        let wire = RedstoneWire::new(
+            RedstoneWireSide::None,
+            RedstoneWireSide::None,
+            RedstoneWireSide::Side,
+            RedstoneWireSide::Side,
+            0,
+        );
+        world.set_block(BlockPos::new(x, 8, 1), Block::RedstoneWire { wire });
+    }
+    let repeater = RedstoneRepeater {
+        delay: 1,
+        facing: BlockDirection::West,
+        locked: false,
+        powered: false,
+    };
+    world.set_block(
+        BlockPos::new(15, 8, 1),
+        Block::RedstoneRepeater { repeater },
+    );
+    world.set_block(second_pos, Block::RedstoneLamp { lit: false });
+
+    let offset = BlockPos::new(0, 0, 4);
+    let mut updates = UpdateContext::default();
+    let copied = worldedit::clone_region(
+        &mut world,
+        first_pos,
+        second_pos,
+        offset,
+        &CloneMask::Replace,
+        &mut updates,
+    );
+    assert_eq!(copied, 16);
+    let updated = updates.flush(&mut world);
+    for _ in 0..3 {
+        world.run_scheduled_ticks();
+    }
+
+    // Each position is updated once: the 16 placed blocks, the 4 blocks around each of them,
+    // and one more block past either end. Updating every neighbor of every change would take 16 * 7.
+    assert_eq!(updated, 16 + 16 * 4 + 2);
+    for x in 2..=14 {
+        match world.get_block(BlockPos::new(x, 8, 5)) {
+            Block::RedstoneWire { wire } => assert_eq!(wire.power, 17 - x as u8),
+            block => panic!("expected dust, found {:?}", block),
+        }
+    }
+    assert!(matches!(
+        world.get_block(BlockPos::new(15, 8, 5)),
+        Block::RedstoneRepeater { repeater } if repeater.powered
+    ));
+    assert_eq!(
+        world.get_block(second_pos + offset),
+        Block::RedstoneLamp { lit: true }
+    );
+}
@@ -52,18 +52,18 @@ pub(super) fn execute_set(ctx: CommandExecuteContext<'_>) {
         ctx.player.first_position.unwrap(),
         ctx.player.second_position.unwrap(),
     );
-    for x in operation.x_range() {
-        for y in operation.y_range() {
-            for z in operation.z_range() {
-                let block_pos = BlockPos::new(x, y, z);
-                let block_id = pattern.pick().get_id();
+    let mut updates = UpdateContext::default();
+    for block_pos in operation.positions() {
+        let block_id = pattern.pick(&mut ctx.plot.rng).get_id();
 
-                if ctx.plot.set_block_raw(block_pos, block_id) {
-                    operation.update_block();
-                }
-            }
+        if ctx.plot.set_block_raw(block_pos, block_id) {
+            operation.update_block();
+            updates.mark_changed(block_pos);
         }
     }
+    if ctx.has_flag('u') {
+        updates.flush(ctx.plot);
+    }
 
     let blocks_updated = operation.blocks_updated();
 
@@ -87,18 +87,12 @@ pub(super) fn execute_replace(ctx: CommandExecuteContext<'_>) {
         ctx.player.first_position.unwrap(),
         ctx.player.second_position.unwrap(),
     );
-    for x in operation.x_range() {
-        for y in operation.y_range() {
-            for z in operation.z_range() {
-                let block_pos = BlockPos::new(x, y, z);
-
-                if filter.matches(ctx.plot.get_block(block_pos)) {
-                    let block_id = pattern.pick().get_id();
+    for block_pos in operation.positions() {
+        if filter.matches(ctx.plot.get_block(block_pos)) {
+            let block_id = pattern.pick(&mut ctx.plot.rng).get_id();
 
-                    if ctx.plot.set_block_raw(block_pos, block_id) {
-                        operation.update_block();
-                    }
-                }
+            if ctx.plot.set_block_raw(block_pos, block_id) {
+                operation.update_block();
             }
         }
     }
@@ -119,14 +113,9 @@ pub(super) fn execute_count(ctx: CommandExecuteContext<'_>) {
 
     let mut blocks_counted = 0;
     let operation = worldedit_start_operation(ctx.player);
-    for x in operation.x_range() {
-        for y in operation.y_range() {
-            for z in operation.z_range() {
-                let block_pos = BlockPos::new(x, y, z);
-                if filter.matches(ctx.plot.get_block(block_pos)) {
-                    blocks_counted += 1;
-                }
-            }
+    for block_pos in operation.positions() {
+        if filter.matches(ctx.plot.get_block(block_pos)) {
+            blocks_counted += 1;
         }
     }
 
@@ -208,6 +197,7 @@ pub(super) fn execute_move(mut ctx: CommandExecuteContext<'_>) {
         &clipboard,
         direction.offset_pos(zero_pos, move_amt as i32),
         ctx.has_flag('a'),
+        &mut UpdateContext::default(),
     );
 
     if ctx.has_flag('s') {
@@ -241,9 +231,10 @@ pub(super) fn execute_paste(ctx: CommandExecuteContext<'_>) {
             offset_z + cb.size_z as i32,
         );
         capture_undo(ctx.plot, ctx.player, first_pos, second_pos);
-        paste_clipboard(ctx.plot, cb, pos, ctx.has_flag('a'));
+        let mut updates = UpdateContext::default();
+        paste_clipboard(ctx.plot, cb, pos, ctx.has_flag('a'), &mut updates);
         if ctx.has_flag('u') {
-            update(ctx.plot, first_pos, second_pos);
+            updates.flush(ctx.plot);
         }
         ctx.player.send_worldedit_message(&format!(
             "Your clipboard was pasted. ({:?})",
@@ -271,14 +262,20 @@ pub(super) fn execute_load(ctx: CommandExecuteContext<'_>) {
         file_name.insert_str(0, &prefix);
     }
 
-    let clipboard = load_schematic(&file_name);
-    match clipboard {
-        Ok(cb) => {
-            ctx.player.worldedit_clipboard = Some(cb);
+    let loaded = load_schematic(&file_name);
+    match loaded {
+        Ok(loaded) => {
+            ctx.player.worldedit_clipboard = Some(loaded.clipboard);
             ctx.player.send_worldedit_message(&format!(
                 "The schematic was loaded to your clipboard. Do //paste to birth it into the world. ({:?})",
                 start_time.elapsed()
             ));
+            if loaded.unknown_blocks > 0 {
+                ctx.player.send_error_message(&format!(
+                    "{} unknown blocks were replaced with air.",
+                    loaded.unknown_blocks
+                ));
+            }
         }
         Err(e) => {
             if let Some(e) = e.downcast_ref::<std::io::Error>() {
@@ -351,7 +348,13 @@ pub(super) fn execute_stack(ctx: CommandExecuteContext<'_>) {
             block_pos,
             direction.offset_pos(pos2, offset),
         ));
-        paste_clipboard(ctx.plot, &clipboard, block_pos, ctx.has_flag('a'));
+        paste_clipboard(
+            ctx.plot,
+            &clipboard,
+            block_pos,
+            ctx.has_flag('a'),
+            &mut UpdateContext::default(),
+        );
     }
     let undo = WorldEditUndo {
         clipboards: undo_cbs,
@@ -399,8 +402,12 @@ pub(super) fn execute_undo(ctx: CommandExecuteContext<'_>) {
             .collect(),
         ..undo
     };
+    let mut updates = UpdateContext::default();
     for clipboard in &undo.clipboards {
-        paste_clipboard(ctx.plot, clipboard, undo.pos, false);
+        paste_clipboard(ctx.plot, clipboard, undo.pos, false, &mut updates);
+    }
+    if ctx.has_flag('u') {
+        updates.flush(ctx.plot);
     }
     ctx.player.worldedit_redo.push(redo);
 }
@@ -438,7 +445,13 @@ pub(super) fn execute_redo(ctx: CommandExecuteContext<'_>) {
         ..redo
     };
     for clipboard in &redo.clipboards {
-        paste_clipboard(ctx.plot, clipboard, redo.pos, false);
+        paste_clipboard(
+            ctx.plot,
+            clipboard,
+            redo.pos,
+            false,
+            &mut UpdateContext::default(),
+        );
     }
     ctx.player.worldedit_undo.push(undo);
 }
@@ -451,13 +464,21 @@ pub(super) fn execute_sel(ctx: CommandExecuteContext<'_>) {
     player.worldedit_send_cui("s|cuboid");
 }
 
+fn position_argument(ctx: &CommandExecuteContext<'_>) -> BlockPos {
+    BlockPos::new(
+        ctx.arguments[0].unwrap_coordinate(),
+        ctx.arguments[1].unwrap_coordinate(),
+        ctx.arguments[2].unwrap_coordinate(),
+    )
+}
+
 pub(super) fn execute_pos1(ctx: CommandExecuteContext<'_>) {
-    let pos = ctx.player.pos.block_pos();
+    let pos = position_argument(&ctx);
     ctx.player.worldedit_set_first_position(pos);
 }
 
 pub(super) fn execute_pos2(ctx: CommandExecuteContext<'_>) {
-    let pos = ctx.player.pos.block_pos();
+    let pos = position_argument(&ctx);
     ctx.player.worldedit_set_second_position(pos);
 }
 
@@ -963,7 +984,13 @@ pub(super) fn execute_rstack(ctx: CommandExecuteContext<'_>) {
             block_pos,
             pos2 + direction * offset,
         ));
-        paste_clipboard(ctx.plot, &clipboard, block_pos, !ctx.has_flag('a'));
+        paste_clipboard(
+            ctx.plot,
+            &clipboard,
+            block_pos,
+            !ctx.has_flag('a'),
+            &mut UpdateContext::default(),
+        );
     }
     let undo = WorldEditUndo {
         clipboards: undo_cbs,
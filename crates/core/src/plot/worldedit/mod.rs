@@ -3,6 +3,7 @@
 mod execute;
 mod schematic;
 
+use super::commands::parse_relative_coord;
 use super::{Plot, PlotWorld};
 use crate::player::{PacketSender, Player, PlayerPos};
 use crate::redstone;
@@ -11,12 +12,12 @@ use crate::world::{for_each_block_mut_optimized, World};
 use execute::*;
 use mchprs_blocks::block_entities::{BlockEntity, ContainerType};
 use mchprs_blocks::blocks::Block;
-use mchprs_blocks::{BlockFacing, BlockPos};
+use mchprs_blocks::{BlockFace, BlockFacing, BlockPos};
 use mchprs_utils::map;
 use once_cell::sync::Lazy;
 use rand::Rng;
 use regex::Regex;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::collections::HashMap;
 use std::fmt;
 use std::ops::RangeInclusive;
@@ -189,6 +190,10 @@ enum ArgumentType {
     Pattern,
     String,
     ContainerType,
+    /// Coordinates can be relative to the player with `~`
+    XCoordinate,
+    YCoordinate,
+    ZCoordinate,
 }
 
 #[derive(Debug, Clone)]
@@ -200,6 +205,7 @@ enum Argument {
     Mask(WorldEditPattern),
     String(String),
     ContainerType(ContainerType),
+    Coordinate(i32),
 }
 
 impl Argument {
@@ -245,6 +251,13 @@ impl Argument {
         }
     }
 
+    fn unwrap_coordinate(&self) -> i32 {
+        match self {
+            Argument::Coordinate(val) => *val,
+            _ => panic!("Argument was not a Coordinate"),
+        }
+    }
+
     fn unwrap_container_type(&self) -> ContainerType {
         match self {
             Argument::ContainerType(val) => *val,
@@ -263,6 +276,9 @@ impl Argument {
                 Argument::parse(player, desc, Some("me"))
             }
             ArgumentType::UnsignedInteger => Ok(Argument::UnsignedInteger(1)),
+            ArgumentType::XCoordinate | ArgumentType::YCoordinate | ArgumentType::ZCoordinate => {
+                Argument::parse(player, desc, Some("~"))
+            }
             _ => Err(ArgumentParseError::new(
                 arg_type,
                 "argument can't be inferred",
@@ -343,6 +359,21 @@ impl Argument {
 
                 Ok(Argument::DirectionVector(vec))
             }
+            ArgumentType::XCoordinate | ArgumentType::YCoordinate | ArgumentType::ZCoordinate => {
+                let player_pos = player.pos.block_pos();
+                let reference = match arg_type {
+                    ArgumentType::XCoordinate => player_pos.x,
+                    ArgumentType::YCoordinate => player_pos.y,
+                    _ => player_pos.z,
+                };
+                match parse_relative_coord(arg, reference) {
+                    Ok(coord) => Ok(Argument::Coordinate(coord)),
+                    Err(_) => Err(ArgumentParseError::new(
+                        arg_type,
+                        "error parsing coordinate",
+                    )),
+                }
+            }
             ArgumentType::ContainerType => match arg.parse::<ContainerType>() {
                 Ok(ty) => Ok(Argument::ContainerType(ty)),
                 Err(_) => Err(ArgumentParseError::new(
@@ -469,6 +500,11 @@ static COMMANDS: Lazy<HashMap<&'static str, WorldeditCommand>> = Lazy::new(|| {
         "/pos1" => WorldeditCommand {
             execute_fn: execute_pos1,
             description: "Set position 1",
+            arguments: &[
+                argument!("x", XCoordinate, "The x coordinate, your own if omitted"),
+                argument!("y", YCoordinate, "The y coordinate, your own if omitted"),
+                argument!("z", ZCoordinate, "The z coordinate, your own if omitted")
+            ],
             permission_node: "worldedit.selection.pos",
             mutates_world: false,
             ..Default::default()
@@ -476,6 +512,11 @@ static COMMANDS: Lazy<HashMap<&'static str, WorldeditCommand>> = Lazy::new(|| {
         "/pos2" => WorldeditCommand {
             execute_fn: execute_pos2,
             description: "Set position 2",
+            arguments: &[
+                argument!("x", XCoordinate, "The x coordinate, your own if omitted"),
+                argument!("y", YCoordinate, "The y coordinate, your own if omitted"),
+                argument!("z", ZCoordinate, "The z coordinate, your own if omitted")
+            ],
             permission_node: "worldedit.selection.pos",
             mutates_world: false,
             ..Default::default()
@@ -507,6 +548,9 @@ static COMMANDS: Lazy<HashMap<&'static str, WorldeditCommand>> = Lazy::new(|| {
             requires_positions: true,
             execute_fn: execute_set,
             description: "Sets all the blocks in the region",
+            flags: &[
+                flag!('u', None, "Also update all affected blocks"),
+            ],
             permission_node: "worldedit.region.stack",
             ..Default::default()
         },
@@ -550,6 +594,9 @@ static COMMANDS: Lazy<HashMap<&'static str, WorldeditCommand>> = Lazy::new(|| {
         "/undo" => WorldeditCommand {
             execute_fn: execute_undo,
             description: "Undoes the last action (from history)",
+            flags: &[
+                flag!('u', None, "Also update all affected blocks"),
+            ],
             permission_node: "worldedit.history.undo",
             ..Default::default()
         },
@@ -826,12 +873,13 @@ impl FromStr for WorldEditPattern {
                         .unwrap(),
                 )
             } else {
-                let block_name = pattern_match
-                    .get(5)
-                    .unwrap()
-                    .as_str()
-                    .trim_start_matches("minecraft:");
-                Block::from_name(block_name)
+                let name_match = pattern_match.get(5).unwrap();
+                // Include the property list if there is one, e.g. `repeater[delay=3]`
+                let state_end = match pattern_match.get(9) {
+                    Some(_) => pattern_match.get(7).unwrap().end(),
+                    None => name_match.end(),
+                };
+                Block::from_state(&part[name_match.start()..state_end])
                     .ok_or_else(|| PatternParseError::UnknownBlock(part.to_owned()))?
             };
 
@@ -858,13 +906,12 @@ impl WorldEditPattern {
         self.parts.iter().any(|part| part.block_id == block_id)
     }
 
-    pub fn pick(&self) -> Block {
+    pub fn pick(&self, rng: &mut impl Rng) -> Block {
         let mut weight_sum = 0.0;
         for part in &self.parts {
             weight_sum += part.weight;
         }
 
-        let mut rng = rand::thread_rng();
         let mut random = rng.gen_range(0.0..weight_sum);
 
         let mut selected = &WorldEditPatternPart {
@@ -925,6 +972,22 @@ impl WorldEditOperation {
     fn z_range(&self) -> RangeInclusive<i32> {
         self.z_range.clone()
     }
+
+    /// Every position in the selection, in the order they are stored in memory
+    fn positions(&self) -> impl Iterator<Item = BlockPos> {
+        BlockPos::cuboid(
+            BlockPos::new(
+                *self.x_range.start(),
+                *self.y_range.start(),
+                *self.z_range.start(),
+            ),
+            BlockPos::new(
+                *self.x_range.end(),
+                *self.y_range.end(),
+                *self.z_range.end(),
+            ),
+        )
+    }
 }
 
 pub fn ray_trace_block(
@@ -1037,7 +1100,13 @@ fn clear_area(plot: &mut PlotWorld, first_pos: BlockPos, second_pos: BlockPos) {
     }
 }
 
-fn paste_clipboard(plot: &mut PlotWorld, cb: &WorldEditClipboard, pos: BlockPos, ignore_air: bool) {
+fn paste_clipboard(
+    plot: &mut PlotWorld,
+    cb: &WorldEditClipboard,
+    pos: BlockPos,
+    ignore_air: bool,
+    updates: &mut UpdateContext,
+) {
     let offset_x = pos.x - cb.offset_x;
     let offset_y = pos.y - cb.offset_y;
     let offset_z = pos.z - cb.offset_z;
@@ -1060,7 +1129,10 @@ fn paste_clipboard(plot: &mut PlotWorld, cb: &WorldEditClipboard, pos: BlockPos,
                 if ignore_air && entry == 0 {
                     continue;
                 }
-                plot.set_block_raw(BlockPos::new(x, y, z), entry);
+                let pos = BlockPos::new(x, y, z);
+                if plot.set_block_raw(pos, entry) {
+                    updates.mark_changed(pos);
+                }
             }
         }
     }
@@ -1078,6 +1150,67 @@ fn paste_clipboard(plot: &mut PlotWorld, cb: &WorldEditClipboard, pos: BlockPos,
     }
 }
 
+/// Which blocks from the source region are copied by `/clone`
+pub enum CloneMask {
+    /// Copy every block, including air
+    Replace,
+    /// Copy every block except air
+    Masked,
+    /// Only copy blocks matching the pattern
+    Filtered(WorldEditPattern),
+}
+
+/// Copies the region between `first_pos` and `second_pos` onto the same region shifted by `offset`.
+/// The source is buffered before anything is placed, so the two regions are allowed to overlap.
+/// Returns the number of blocks that were copied.
+pub fn clone_region(
+    plot: &mut PlotWorld,
+    first_pos: BlockPos,
+    second_pos: BlockPos,
+    offset: BlockPos,
+    mask: &CloneMask,
+    updates: &mut UpdateContext,
+) -> usize {
+    let start_pos = first_pos.min(second_pos);
+    let cb = create_clipboard(plot, start_pos, first_pos, second_pos);
+    let dest_start = start_pos + offset;
+    let mut block_entities = Vec::new();
+    let mut copied = 0;
+    let mut i = 0;
+    for y in 0..cb.size_y as i32 {
+        for z in 0..cb.size_z as i32 {
+            for x in 0..cb.size_x as i32 {
+                let entry = cb.data.get_entry(i);
+                i += 1;
+                let copy = match mask {
+                    CloneMask::Replace => true,
+                    CloneMask::Masked => entry != 0,
+                    CloneMask::Filtered(filter) => filter.matches(Block::from_id(entry)),
+                };
+                if !copy {
+                    continue;
+                }
+                let relative_pos = BlockPos::new(x, y, z);
+                if plot.set_block_raw(dest_start + relative_pos, entry) {
+                    updates.mark_changed(dest_start + relative_pos);
+                }
+                if let Some(block_entity) = cb.block_entities.get(&relative_pos) {
+                    block_entities.push((dest_start + relative_pos, block_entity.clone()));
+                }
+                copied += 1;
+            }
+        }
+    }
+
+    // Send block changes before we send block entity data, otherwise it'll be ignored
+    plot.flush_block_changes();
+
+    for (pos, block_entity) in block_entities {
+        plot.set_block_entity(pos, block_entity);
+    }
+    copied
+}
+
 fn capture_undo(
     plot: &mut PlotWorld,
     player: &mut Player,
@@ -1142,6 +1275,34 @@ fn expand_selection(player: &mut Player, amount: BlockPos, contract: bool) {
     }
 }
 
+/// Collects the blocks changed by a bulk operation such as a paste, so that redstone is only
+/// updated once the whole operation is done. Every changed block and its neighbors get a
+/// single update, no matter how many of the blocks around them changed.
+#[derive(Default)]
+pub struct UpdateContext {
+    changed: Vec<BlockPos>,
+}
+
+impl UpdateContext {
+    pub fn mark_changed(&mut self, pos: BlockPos) {
+        self.changed.push(pos);
+    }
+
+    /// Updates the changed blocks and their neighbors. Returns the number of updates that were run.
+    pub fn flush(self, world: &mut impl World) -> usize {
+        let mut updated = FxHashSet::default();
+        for pos in self.changed {
+            let neighbors = BlockFace::values().map(|face| pos.offset(face));
+            for pos in std::iter::once(pos).chain(neighbors) {
+                if updated.insert(pos) {
+                    redstone::update(world.get_block(pos), world, pos);
+                }
+            }
+        }
+        updated.len()
+    }
+}
+
 fn update(plot: &mut PlotWorld, first_pos: BlockPos, second_pos: BlockPos) {
     for_each_block_mut_optimized(plot, first_pos, second_pos, |plot, pos| {
         let block = plot.get_block(pos);
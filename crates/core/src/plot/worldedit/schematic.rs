@@ -15,6 +15,7 @@ use rustc_hash::FxHashMap;
 use serde::Serialize;
 use std::fs::{self, File};
 use std::path::PathBuf;
+use tracing::warn;
 
 macro_rules! nbt_as {
     // I'm not sure if path is the right type here.
@@ -27,11 +28,12 @@ macro_rules! nbt_as {
     };
 }
 
+/// Parses a block from a schematic palette. Returns `None` if the block isn't known.
 fn parse_block(str: &str) -> Option<Block> {
     static RE: Lazy<Regex> =
         Lazy::new(|| Regex::new(r"(?:minecraft:)?([a-z_]+)(?:\[([a-z=,0-9]+)\])?").unwrap());
     let captures = RE.captures(str)?;
-    let mut block = Block::from_name(captures.get(1)?.as_str()).unwrap_or(Block::Air {});
+    let mut block = Block::from_name(captures.get(1)?.as_str())?;
     if let Some(properties_match) = captures.get(2) {
         let properties = properties_match
             .as_str()
@@ -43,11 +45,29 @@ fn parse_block(str: &str) -> Option<Block> {
     Some(block)
 }
 
-pub fn load_schematic(file_name: &str) -> Result<WorldEditClipboard> {
-    use nbt::Value;
+/// A schematic loaded into a clipboard
+pub struct LoadedSchematic {
+    pub clipboard: WorldEditClipboard,
+    /// How many blocks weren't known and were replaced with air
+    pub unknown_blocks: usize,
+}
 
+pub fn load_schematic(file_name: &str) -> Result<LoadedSchematic> {
     let mut file = File::open("./schems/".to_owned() + file_name)?;
     let nbt = nbt::Blob::from_gzip_reader(&mut file)?;
+    let loaded = read_schematic(&nbt)?;
+    if loaded.unknown_blocks > 0 {
+        warn!(
+            "{} unknown blocks in {} were replaced with air",
+            loaded.unknown_blocks, file_name
+        );
+    }
+    Ok(loaded)
+}
+
+fn read_schematic(nbt: &nbt::Blob) -> Result<LoadedSchematic> {
+    use nbt::Value;
+
     let size_x = nbt_as!(nbt["Width"], Value::Short) as u32;
     let size_z = nbt_as!(nbt["Length"], Value::Short) as u32;
     let size_y = nbt_as!(nbt["Height"], Value::Short) as u32;
@@ -56,17 +76,24 @@ pub fn load_schematic(file_name: &str) -> Result<WorldEditClipboard> {
     let offset_x = -nbt_as!(metadata["WEOffsetX"], Value::Int);
     let offset_y = -nbt_as!(metadata["WEOffsetY"], Value::Int);
     let offset_z = -nbt_as!(metadata["WEOffsetZ"], Value::Int);
+    // Blocks that aren't in the palette, or that we don't know, are left out
+    // and become air
     let mut palette: FxHashMap<u32, u32> = FxHashMap::default();
     for (k, v) in nbt_palette {
         let id = *nbt_as!(v, Value::Int) as u32;
-        let block = parse_block(k).with_context(|| format!("error parsing block: {}", k))?;
-        palette.insert(id, block.get_id());
+        match parse_block(k) {
+            Some(block) => {
+                palette.insert(id, block.get_id());
+            }
+            None => warn!("Unknown block {} in schematic, replacing it with air", k),
+        }
     }
     let blocks: Vec<u8> = nbt_as!(&nbt["BlockData"], Value::ByteArray)
         .iter()
         .map(|b| *b as u8)
         .collect();
     let mut data = PalettedBitBuffer::new((size_x * size_y * size_z) as usize, 9);
+    let mut unknown_blocks = 0;
     let mut i = 0;
     for y_offset in (0..size_y).map(|y| y * size_z * size_x) {
         for z_offset in (0..size_z).map(|z| z * size_x) {
@@ -81,7 +108,13 @@ pub fn load_schematic(file_name: &str) -> Result<WorldEditClipboard> {
                     }
                     i += 1;
                 }
-                let entry = *palette.get(&blockstate_id).unwrap();
+                let entry = match palette.get(&blockstate_id) {
+                    Some(entry) => *entry,
+                    None => {
+                        unknown_blocks += 1;
+                        0
+                    }
+                };
                 data.set_entry((y_offset + z_offset + x) as usize, entry);
             }
         }
@@ -100,7 +133,7 @@ pub fn load_schematic(file_name: &str) -> Result<WorldEditClipboard> {
             parsed_block_entities.insert(pos, parsed);
         }
     }
-    Ok(WorldEditClipboard {
+    let clipboard = WorldEditClipboard {
         size_x,
         size_y,
         size_z,
@@ -109,6 +142,10 @@ pub fn load_schematic(file_name: &str) -> Result<WorldEditClipboard> {
         offset_z,
         data,
         block_entities: parsed_block_entities,
+    };
+    Ok(LoadedSchematic {
+        clipboard,
+        unknown_blocks,
     })
 }
 
@@ -228,3 +265,37 @@ pub fn save_schematic(file_name: &str, clipboard: &WorldEditClipboard) -> Result
 
     Ok(())
 }
+
+#[test]
+fn unknown_blocks_replaced_with_air() {
+    use nbt::Value;
+    use std::collections::HashMap;
+
+    let palette = HashMap::from([
+        ("minecraft:stone".to_owned(), Value::Int(0)),
+        ("minecraft:not_a_block".to_owned(), Value::Int(1)),
+        ("minecraft:glass".to_owned(), Value::Int(2)),
+    ]);
+    let metadata = HashMap::from([
+        ("WEOffsetX".to_owned(), Value::Int(0)),
+        ("WEOffsetY".to_owned(), Value::Int(0)),
+        ("WEOffsetZ".to_owned(), Value::Int(0)),
+    ]);
+    let mut nbt = nbt::Blob::new();
+    nbt.insert("Width", Value::Short(3)).unwrap();
+    nbt.insert("Length", Value::Short(1)).unwrap();
+    nbt.insert("Height", Value::Short(1)).unwrap();
+    nbt.insert("Palette", Value::Compound(palette)).unwrap();
+    nbt.insert("Metadata", Value::Compound(metadata)).unwrap();
+    nbt.insert("BlockData", Value::ByteArray(vec![0, 1, 2]))
+        .unwrap();
+    nbt.insert("BlockEntities", Value::List(Vec::new()))
+        .unwrap();
+
+    let loaded = read_schematic(&nbt).unwrap();
+    assert_eq!(loaded.unknown_blocks, 1);
+    let blocks: Vec<Block> = (0..3)
+        .map(|i| Block::from_id(loaded.clipboard.data.get_entry(i)))
+        .collect();
+    assert_eq!(blocks, [Block::Stone {}, Block::Air {}, Block::Glass {}]);
+}
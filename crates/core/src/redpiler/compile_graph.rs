@@ -1,6 +1,8 @@
 use mchprs_blocks::blocks::{ComparatorMode, Instrument};
 use mchprs_blocks::BlockPos;
 use petgraph::stable_graph::{NodeIndex, StableGraph};
+use petgraph::visit::EdgeRef;
+use std::fmt::Write;
 
 pub type NodeIdx = NodeIndex;
 
@@ -122,3 +124,59 @@ impl CompileLink {
 }
 
 pub type CompileGraph = StableGraph<CompileNode, CompileLink>;
+
+/// Formats the graph in Graphviz DOT format. Edges are labeled with the signal strength lost
+/// along the link and side inputs are drawn in blue.
+pub fn to_dot(graph: &CompileGraph) -> String {
+    let mut dot = String::from("digraph {\n");
+    for idx in graph.node_indices() {
+        let node = &graph[idx];
+        let label = match node.ty {
+            NodeType::Repeater { delay, .. } => format!("Repeater({})", delay),
+            NodeType::Torch => "Torch".to_string(),
+            NodeType::Comparator { mode, .. } => format!(
+                "Comparator({})",
+                match mode {
+                    ComparatorMode::Compare => "Cmp",
+                    ComparatorMode::Subtract => "Sub",
+                }
+            ),
+            NodeType::Lamp => "Lamp".to_string(),
+            NodeType::Button => "Button".to_string(),
+            NodeType::Lever => "Lever".to_string(),
+            NodeType::PressurePlate => "PressurePlate".to_string(),
+            NodeType::Trapdoor => "Trapdoor".to_string(),
+            NodeType::Wire => "Wire".to_string(),
+            NodeType::Constant => format!("Constant({})", node.state.output_strength),
+            NodeType::NoteBlock { .. } => "NoteBlock".to_string(),
+        };
+        let pos = match node.block {
+            Some((pos, _)) => format!("{}, {}, {}", pos.x, pos.y, pos.z),
+            None => "No Pos".to_string(),
+        };
+        let _ = writeln!(
+            dot,
+            "    n{} [ label = \"{}\\n({})\" ];",
+            idx.index(),
+            label,
+            pos
+        );
+    }
+    for edge in graph.edge_references() {
+        let link = edge.weight();
+        let color = match link.ty {
+            LinkType::Side => ",color=\"blue\"",
+            LinkType::Default => "",
+        };
+        let _ = writeln!(
+            dot,
+            "    n{} -> n{} [ label = \"{}\"{} ];",
+            edge.source().index(),
+            edge.target().index(),
+            link.ss,
+            color
+        );
+    }
+    dot.push_str("}\n");
+    dot
+}
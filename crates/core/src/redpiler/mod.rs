@@ -224,6 +224,15 @@ impl Compiler {
     }
 }
 
+/// Runs the unoptimized compile passes over `bounds` and returns the redstone graph in
+/// Graphviz DOT format, without starting redpiler.
+pub fn dump_graph<W: World>(world: &W, bounds: (BlockPos, BlockPos)) -> String {
+    let input = CompilerInput { world, bounds };
+    let pass_manager = make_default_pass_manager::<W>();
+    let graph = pass_manager.run_passes(&Default::default(), &input, Default::default());
+    compile_graph::to_dot(&graph)
+}
+
 pub struct CompilerInput<'w, W: World> {
     pub world: &'w W,
     pub bounds: (BlockPos, BlockPos),
@@ -168,7 +168,7 @@ fn identify_block<W: World>(
                 NodeState::simple(powered),
             )
         }
-        block if comparator::has_override(block) => (
+        block if block.has_comparator_override() => (
             NodeType::Constant,
             NodeState::ss(comparator::get_override(block, world, pos)),
         ),
@@ -1,20 +1,23 @@
 use crate::world::World;
 use mchprs_blocks::block_entities::BlockEntity;
-use mchprs_blocks::blocks::{Block, ComparatorMode, RedstoneComparator};
+use mchprs_blocks::blocks::{Block, ComparatorMode, ComparatorSignal, RedstoneComparator};
 use mchprs_blocks::{BlockDirection, BlockFace, BlockPos};
 use mchprs_world::TickPriority;
 
-fn get_power_on_side(world: &impl World, pos: BlockPos, side: BlockDirection) -> u8 {
+/// What the comparator at `pos` reads from the block on its `side`
+fn get_signal(world: &impl World, pos: BlockPos, side: BlockDirection) -> ComparatorSignal {
     let side_pos = pos.offset(side.block_face());
-    let side_block = world.get_block(side_pos);
-    if super::is_diode(side_block) {
-        super::get_weak_power(side_block, world, side_pos, side.block_face(), false)
-    } else if let Block::RedstoneWire { wire } = side_block {
-        wire.power
-    } else if let Block::RedstoneBlock {} = side_block {
-        15
-    } else {
-        0
+    world
+        .get_block(side_pos)
+        .comparator_signal(side, world.get_block_entity(side_pos))
+}
+
+/// Side inputs only accept power from diodes, redstone dust, and redstone blocks.
+/// Unlike the rear input, a powered solid block at the side is ignored.
+fn get_power_on_side(world: &impl World, pos: BlockPos, side: BlockDirection) -> u8 {
+    match get_signal(world, pos, side) {
+        ComparatorSignal::Power(power) => power,
+        _ => 0,
     }
 }
 
@@ -25,84 +28,49 @@ fn get_power_on_sides(comp: RedstoneComparator, world: &impl World, pos: BlockPo
     )
 }
 
-pub fn has_override(block: Block) -> bool {
-    matches!(
-        block,
-        Block::Barrel { .. }
-            | Block::Furnace { .. }
-            | Block::Hopper { .. }
-            | Block::Cauldron { .. }
-            | Block::Composter { .. }
-            | Block::Cake { .. }
-    )
-}
-
+/// Returns the override of the block at `pos`, which has to have a comparator override
 pub fn get_override(block: Block, world: &impl World, pos: BlockPos) -> u8 {
-    match block {
-        Block::Barrel { .. } | Block::Furnace { .. } | Block::Hopper { .. } => {
-            match world.get_block_entity(pos) {
-                Some(BlockEntity::Container {
-                    comparator_override,
-                    ..
-                }) => *comparator_override,
-                Some(_) => unreachable!("Backing blockentity type is invalid"),
-                None => unreachable!("Backing blockentity does not exist"),
-            }
-        }
-        Block::Cauldron { level } => level,
-        Block::Composter { level } => level,
-        Block::Cake { bites } => 14 - 2 * bites,
+    // Overrides are the same from every side
+    match block.comparator_signal(BlockDirection::North, world.get_block_entity(pos)) {
+        ComparatorSignal::Override(signal) => signal,
         _ => unreachable!("Block does not override comparators"),
     }
 }
 
 pub fn get_far_input(world: &impl World, pos: BlockPos, facing: BlockDirection) -> Option<u8> {
-    let face = facing.block_face();
-    let input_pos = pos.offset(face);
+    let input_pos = pos.offset(facing.block_face());
     let input_block = world.get_block(input_pos);
-    if !input_block.is_solid() || has_override(input_block) {
+    if !input_block.is_solid() || input_block.has_comparator_override() {
         return None;
     }
 
-    let far_input_pos = input_pos.offset(face);
-    let far_input_block = world.get_block(far_input_pos);
-    if has_override(far_input_block) {
-        Some(get_override(far_input_block, world, far_input_pos))
-    } else {
-        None
+    match get_signal(world, input_pos, facing) {
+        ComparatorSignal::Override(signal) => Some(signal),
+        _ => None,
     }
 }
 
 fn calculate_input_strength(comp: RedstoneComparator, world: &impl World, pos: BlockPos) -> u8 {
+    if let ComparatorSignal::Override(signal) = get_signal(world, pos, comp.facing) {
+        return signal;
+    }
     let base_input_strength = super::diode_get_input_strength(world, pos, comp.facing);
-    let input_pos = pos.offset(comp.facing.block_face());
-    let input_block = world.get_block(input_pos);
-    if has_override(input_block) {
-        get_override(input_block, world, input_pos)
-    } else if base_input_strength < 15 && input_block.is_solid() {
-        let far_input_pos = input_pos.offset(comp.facing.block_face());
-        let far_input_block = world.get_block(far_input_pos);
-        if has_override(far_input_block) {
-            get_override(far_input_block, world, far_input_pos)
-        } else {
-            base_input_strength
+    if base_input_strength < 15 {
+        if let Some(far_input) = get_far_input(world, pos, comp.facing) {
+            return far_input;
         }
-    } else {
-        base_input_strength
     }
+    base_input_strength
 }
 
-pub fn should_be_powered(comp: RedstoneComparator, world: &impl World, pos: BlockPos) -> bool {
-    let input_strength = calculate_input_strength(comp, world, pos);
-    if input_strength == 0 {
-        false
+/// The output signal strength for a given rear input and maximum side input
+fn output_strength_for(mode: ComparatorMode, input_strength: u8, power_on_sides: u8) -> u8 {
+    if power_on_sides > input_strength {
+        0
+    } else if mode == ComparatorMode::Subtract {
+        input_strength - power_on_sides
     } else {
-        let power_on_sides = get_power_on_sides(comp, world, pos);
-        if input_strength > power_on_sides {
-            true
-        } else {
-            power_on_sides == input_strength && comp.mode == ComparatorMode::Compare
-        }
+        input_strength
     }
 }
 
@@ -112,13 +80,11 @@ fn calculate_output_strength(
     pos: BlockPos,
 ) -> u8 {
     let input_strength = calculate_input_strength(comp, world, pos);
-    if comp.mode == ComparatorMode::Subtract {
-        input_strength.saturating_sub(get_power_on_sides(comp, world, pos))
-    } else if input_strength >= get_power_on_sides(comp, world, pos) {
-        input_strength
-    } else {
-        0
-    }
+    output_strength_for(
+        comp.mode,
+        input_strength,
+        get_power_on_sides(comp, world, pos),
+    )
 }
 
 // This is exactly the same as it is in the RedstoneRepeater struct.
@@ -135,6 +101,19 @@ fn on_state_change(comp: RedstoneComparator, world: &mut impl World, pos: BlockP
     }
 }
 
+/// Sets the output of the comparator without looking at its inputs. It keeps this output until it is next updated.
+pub fn force_output(
+    mut comp: RedstoneComparator,
+    world: &mut impl World,
+    pos: BlockPos,
+    output_strength: u8,
+) {
+    world.set_block_entity(pos, BlockEntity::Comparator { output_strength });
+    comp.powered = output_strength > 0;
+    world.set_block(pos, Block::RedstoneComparator { comparator: comp });
+    on_state_change(comp, world, pos);
+}
+
 pub fn update(comp: RedstoneComparator, world: &mut impl World, pos: BlockPos) {
     if world.pending_tick_at(pos) {
         return;
@@ -146,7 +125,8 @@ pub fn update(comp: RedstoneComparator, world: &mut impl World, pos: BlockPos) {
         } else {
             0
         };
-    if output_strength != old_strength || comp.powered != should_be_powered(comp, world, pos) {
+    // The block state only records whether there is output, the strength is in the block entity
+    if output_strength != old_strength || comp.powered != (output_strength > 0) {
         let front_block = world.get_block(pos.offset(comp.facing.opposite().block_face()));
         let priority = if super::is_diode(front_block) {
             TickPriority::High
@@ -174,15 +154,42 @@ pub fn tick(mut comp: RedstoneComparator, world: &mut impl World, pos: BlockPos)
                 output_strength: new_strength,
             },
         );
-        let should_be_powered = should_be_powered(comp, world, pos);
-        let powered = comp.powered;
-        if powered && !should_be_powered {
-            comp.powered = false;
-            world.set_block(pos, Block::RedstoneComparator { comparator: comp });
-        } else if !powered && should_be_powered {
-            comp.powered = true;
+        let powered = new_strength > 0;
+        if comp.powered != powered {
+            comp.powered = powered;
             world.set_block(pos, Block::RedstoneComparator { comparator: comp });
         }
         on_state_change(comp, world, pos);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subtract_equal_inputs() {
+        assert_eq!(output_strength_for(ComparatorMode::Subtract, 15, 15), 0);
+    }
+
+    #[test]
+    fn subtract_partial() {
+        assert_eq!(output_strength_for(ComparatorMode::Subtract, 15, 4), 11);
+        assert_eq!(output_strength_for(ComparatorMode::Subtract, 3, 9), 0);
+    }
+
+    #[test]
+    fn compare_no_side() {
+        assert_eq!(output_strength_for(ComparatorMode::Compare, 15, 0), 15);
+    }
+
+    #[test]
+    fn compare_side_greater() {
+        assert_eq!(output_strength_for(ComparatorMode::Compare, 7, 9), 0);
+    }
+
+    #[test]
+    fn compare_equal_inputs() {
+        assert_eq!(output_strength_for(ComparatorMode::Compare, 9, 9), 9);
+    }
+}
@@ -345,6 +345,36 @@ pub fn update_surrounding_blocks(world: &mut impl World, pos: BlockPos) {
     }
 }
 
+/// Forces redstone dust or a comparator to output `power`, for probing the logic it feeds into.
+/// Returns false if the block at `pos` isn't something that can be forced.
+pub fn force_power(world: &mut impl World, pos: BlockPos, power: u8) -> bool {
+    match world.get_block(pos) {
+        Block::RedstoneWire { wire } => wire::force_power(wire, world, pos, power),
+        Block::RedstoneComparator { comparator } => {
+            comparator::force_output(comparator, world, pos, power)
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Returns true if the block is a redstone component that is currently powered or outputting power
+pub fn is_component_powered(block: Block) -> bool {
+    match block {
+        Block::RedstoneWire { wire } => wire.power > 0,
+        Block::RedstoneTorch { lit } | Block::RedstoneWallTorch { lit, .. } => lit,
+        Block::RedstoneRepeater { repeater } => repeater.powered,
+        Block::RedstoneComparator { comparator } => comparator.powered,
+        Block::RedstoneLamp { lit } => lit,
+        Block::Lever { lever } => lever.powered,
+        Block::StoneButton { button } => button.powered,
+        Block::StonePressurePlate { powered }
+        | Block::IronTrapdoor { powered, .. }
+        | Block::NoteBlock { powered, .. } => powered,
+        _ => false,
+    }
+}
+
 pub fn is_diode(block: Block) -> bool {
     matches!(
         block,
@@ -11,6 +11,9 @@ const PITCHES_TABLE: [f32; 25] = [
     1.4142135, 1.4983071, 1.587401, 1.6817929, 1.7817974, 1.8877486, 2.0,
 ];
 
+/// The note block's id in the block registry, used by the Block Action packet
+const NOTE_BLOCK_TYPE: i32 = 80;
+
 pub fn is_noteblock_unblocked(world: &impl World, pos: BlockPos) -> bool {
     matches!(world.get_block(pos.offset(BlockFace::Top)), Block::Air {})
 }
@@ -27,4 +30,87 @@ pub fn play_note(world: &mut impl World, pos: BlockPos, instrument: Instrument,
         3.0,
         PITCHES_TABLE[note as usize],
     );
+    // The client shows the note particle when it receives the block action
+    world.block_action(pos, instrument.get_id() as u8, note as u8, NOTE_BLOCK_TYPE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::storage::Chunk;
+    use mchprs_blocks::block_entities::BlockEntity;
+    use mchprs_network::packets::clientbound::{CBlockAction, ClientBoundPacket};
+    use mchprs_world::TickPriority;
+
+    /// Only records what a note block sends to clients
+    #[derive(Default)]
+    struct RecordingWorld {
+        block_actions: Vec<(BlockPos, u8, u8, i32)>,
+    }
+
+    impl World for RecordingWorld {
+        fn get_block_raw(&self, _pos: BlockPos) -> u32 {
+            0
+        }
+
+        fn set_block_raw(&mut self, _pos: BlockPos, _block: u32) -> bool {
+            false
+        }
+
+        fn delete_block_entity(&mut self, _pos: BlockPos) {}
+
+        fn get_block_entity(&self, _pos: BlockPos) -> Option<&BlockEntity> {
+            None
+        }
+
+        fn set_block_entity(&mut self, _pos: BlockPos, _block_entity: BlockEntity) {}
+
+        fn get_chunk(&self, _x: i32, _z: i32) -> Option<&Chunk> {
+            None
+        }
+
+        fn get_chunk_mut(&mut self, _x: i32, _z: i32) -> Option<&mut Chunk> {
+            None
+        }
+
+        fn schedule_tick(&mut self, _pos: BlockPos, _delay: u32, _priority: TickPriority) {}
+
+        fn pending_tick_at(&mut self, _pos: BlockPos) -> bool {
+            false
+        }
+
+        fn block_action(
+            &mut self,
+            pos: BlockPos,
+            action_id: u8,
+            action_param: u8,
+            block_type: i32,
+        ) {
+            self.block_actions
+                .push((pos, action_id, action_param, block_type));
+        }
+    }
+
+    #[test]
+    fn playing_note_sends_block_action() {
+        let mut world = RecordingWorld::default();
+        let pos = BlockPos::new(3, 10, -7);
+        play_note(&mut world, pos, Instrument::Bell, 7);
+        assert_eq!(world.block_actions, [(pos, 6, 7, NOTE_BLOCK_TYPE)]);
+
+        let (pos, action_id, action_param, block_type) = world.block_actions[0];
+        let packet = CBlockAction {
+            x: pos.x,
+            y: pos.y,
+            z: pos.z,
+            action_id,
+            action_param,
+            block_type,
+        };
+        let mut data = Vec::new();
+        packet.encode().write_uncompressed(&mut data).unwrap();
+        // Length, packet id, 8 byte position, instrument, pitch, block type
+        assert_eq!(data[..2], [12, 0x0B]);
+        assert_eq!(data[10..], [6, 7, 80]);
+    }
 }
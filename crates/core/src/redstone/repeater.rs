@@ -60,6 +60,7 @@ fn schedule_tick(
     world.schedule_tick(pos, rep.delay as u32, priority);
 }
 
+/// Only the block behind the repeater is an input, never the one it outputs into
 fn should_be_powered(rep: RedstoneRepeater, world: &impl World, pos: BlockPos) -> bool {
     super::diode_get_input_strength(world, pos, rep.facing) > 0
 }
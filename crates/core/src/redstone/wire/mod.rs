@@ -73,6 +73,8 @@ pub fn on_neighbor_changed(
     wire
 }
 
+/// Dust recomputes its power right away instead of scheduling a tick, so a repeater
+/// sampling its input later in the same tick already sees the new power, like in vanilla.
 pub fn on_neighbor_updated(mut wire: RedstoneWire, world: &mut impl World, pos: BlockPos) {
     let new_power = calculate_power(world, pos);
 
@@ -83,6 +85,27 @@ pub fn on_neighbor_updated(mut wire: RedstoneWire, world: &mut impl World, pos:
     }
 }
 
+/// Sets the power of the wire without looking at what is powering it. It keeps this power until
+/// it is next updated. Other dust is not updated, because it would immediately pull this wire
+/// back to its real power level.
+pub fn force_power(mut wire: RedstoneWire, world: &mut impl World, pos: BlockPos, power: u8) {
+    wire.power = power;
+    world.set_block(pos, Block::RedstoneWire { wire });
+    for direction in &BlockFace::values() {
+        let neighbor_pos = pos.offset(*direction);
+        for update_pos in [
+            neighbor_pos,
+            neighbor_pos.offset(BlockFace::Top),
+            neighbor_pos.offset(BlockFace::Bottom),
+        ] {
+            let block = world.get_block(update_pos);
+            if !matches!(block, Block::RedstoneWire { .. }) {
+                super::update(block, world, update_pos);
+            }
+        }
+    }
+}
+
 pub fn on_use(wire: RedstoneWire, world: &mut impl World, pos: BlockPos) -> ActionResult {
     if is_dot(wire) || is_cross(wire) {
         let mut new_wire = if is_cross(wire) {
@@ -101,26 +124,6 @@ pub fn on_use(wire: RedstoneWire, world: &mut impl World, pos: BlockPos) -> Acti
     ActionResult::Pass
 }
 
-fn can_connect_to(block: Block, side: BlockDirection) -> bool {
-    match block {
-        Block::RedstoneWire { .. }
-        | Block::RedstoneComparator { .. }
-        | Block::RedstoneTorch { .. }
-        | Block::RedstoneBlock { .. }
-        | Block::RedstoneWallTorch { .. }
-        | Block::StonePressurePlate { .. }
-        | Block::TripwireHook { .. }
-        | Block::StoneButton { .. }
-        | Block::Target { .. }
-        | Block::Lever { .. } => true,
-        Block::RedstoneRepeater { repeater } => {
-            repeater.facing == side || repeater.facing == side.opposite()
-        }
-        Block::Observer { facing } => facing == side.block_facing(),
-        _ => false,
-    }
-}
-
 fn can_connect_diagonal_to(block: Block) -> bool {
     matches!(block, Block::RedstoneWire { .. })
 }
@@ -139,14 +142,16 @@ pub fn get_side(world: &impl World, pos: BlockPos, side: BlockDirection) -> Reds
     let neighbor_pos = pos.offset(side.block_face());
     let neighbor = world.get_block(neighbor_pos);
 
-    if can_connect_to(neighbor, side) {
+    if neighbor.can_connect_redstone(side) {
         return RedstoneWireSide::Side;
     }
 
     let up_pos = pos.offset(BlockFace::Top);
     let up = world.get_block(up_pos);
 
+    // Like in `calculate_power`, dust only climbs up opaque blocks
     if !up.is_solid()
+        && !neighbor.is_transparent()
         && can_connect_diagonal_to(world.get_block(neighbor_pos.offset(BlockFace::Top)))
     {
         RedstoneWireSide::Up
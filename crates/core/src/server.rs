@@ -4,20 +4,21 @@ use crate::permissions;
 use crate::player::{Gamemode, PacketSender, Player};
 use crate::plot::commands::DECLARE_COMMANDS;
 use crate::plot::{self, database, Plot, PLOT_BLOCK_HEIGHT};
-use crate::utils::HyphenatedUUID;
+use crate::utils::{world_dir, HyphenatedUUID};
+use crate::watchdog::Watchdog;
 use backtrace::Backtrace;
 use bus::Bus;
 use mchprs_network::packets::clientbound::{
     CDisconnectLogin, CHeldItemChange, CJoinGame, CJoinGameBiomeEffects,
     CJoinGameBiomeEffectsMoodSound, CJoinGameBiomeElement, CJoinGameDimensionCodec,
-    CJoinGameDimensionElement, CLoginSuccess, CPlayerInfo, CPlayerInfoAddPlayer,
-    CPlayerPositionAndLook, CPluginMessage, CPong, CResponse, CSetCompression, CTimeUpdate,
+    CJoinGameDimensionElement, CLegacyStatusResponse, CLoginSuccess, CPlayerInfo,
+    CPlayerInfoAddPlayer, CPlayerPositionAndLook, CPluginMessage, CPong, CResponse, CTimeUpdate,
     CWindowItems, ClientBoundPacket,
 };
 use mchprs_network::packets::serverbound::{
-    SHandshake, SLoginStart, SPing, SRequest, ServerBoundPacketHandler,
+    SHandshake, SLegacyPing, SLoginStart, SPing, SRequest, ServerBoundPacketHandler,
 };
-use mchprs_network::packets::{PacketEncoderExt, SlotData};
+use mchprs_network::packets::{protocol, PacketEncoderExt, SlotData};
 use mchprs_network::{NetworkServer, NetworkState, PlayerPacketSender};
 use mchprs_utils::map;
 use rustc_hash::FxHashMap;
@@ -29,9 +30,8 @@ use std::sync::mpsc::{self, Receiver, Sender};
 use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
-pub const MC_VERSION: &str = "1.18.2";
+pub use mchprs_network::packets::protocol::{MC_VERSION, PROTOCOL_VERSION};
 pub const MC_DATA_VERSION: i32 = 2975;
-pub const PROTOCOL_VERSION: i32 = 758;
 
 /// `Message` gets send from a plot thread to the server thread.
 #[derive(Debug)]
@@ -49,6 +49,10 @@ pub enum Message {
     PlayerTeleportOther(Player, String),
     /// This message is sent to the server thread when a player changes their gamemode.
     PlayerUpdateGamemode(u128, Gamemode),
+    /// This message is sent to the server thread when a player's ping is measured.
+    PlayerPing(u128, Duration),
+    /// This message is sent to the server thread when a player runs /list.
+    ListPlayers(PlayerPacketSender),
     /// This message is sent to the server thread when a plot unloads itself.
     PlotUnload(i32, i32),
     /// This message is sent to the server thread when a player runs /whitelist add.
@@ -57,6 +61,9 @@ pub enum Message {
     WhitelistRemove(u128, PlayerPacketSender),
     /// This message is sent to the server thread when a player runs /stop.
     Shutdown,
+    /// This message is sent to the server thread by the watchdog when plots have
+    /// stopped updating. It contains the positions of the stuck plots.
+    WatchdogTimeout(Vec<(i32, i32)>),
 }
 
 /// `BroadcastMessage` gets broadcasted from the server thread to all the plot threads.
@@ -86,6 +93,8 @@ pub enum BroadcastMessage {
 pub enum PrivMessage {
     PlayerEnterPlot(Player),
     PlayerTeleportOther(Player, String),
+    /// Sent instead of the `Shutdown` broadcast when the broadcast queue is full
+    Shutdown,
 }
 
 /// This is the data that gets sent in the `PlayerJoinedInfo` broadcast message.
@@ -102,6 +111,8 @@ struct PlayerListEntry {
     plot_z: i32,
     username: String,
     gamemode: Gamemode,
+    /// The round trip time of the player's last keep alive
+    ping: Option<Duration>,
 }
 
 struct PlotListEntry {
@@ -124,7 +135,125 @@ pub struct MinecraftServer {
     plot_sender: Sender<Message>,
     online_players: FxHashMap<u128, PlayerListEntry>,
     running_plots: Vec<PlotListEntry>,
+    watchdog: Watchdog,
     whitelist: Option<Vec<WhitelistEntry>>,
+    /// The base64 encoded `server-icon.png` shown in the server list
+    favicon: Option<String>,
+}
+
+/// Tells every running plot to shut down and waits for them to save and unload.
+fn shutdown_plots(
+    broadcaster: &mut Bus<BroadcastMessage>,
+    running_plots: &mut Vec<PlotListEntry>,
+    receiver: &Receiver<Message>,
+) {
+    // A stuck plot never reads its broadcasts, so the queue may be full and broadcasting
+    // would block forever. The plots that are still running are told directly instead.
+    if broadcaster
+        .try_broadcast(BroadcastMessage::Shutdown)
+        .is_err()
+    {
+        for plot in running_plots.iter() {
+            let _ = plot.priv_message_sender.send(PrivMessage::Shutdown);
+        }
+    }
+    while !running_plots.is_empty() {
+        while let Ok(message) = receiver.try_recv() {
+            if let Message::PlotUnload(plot_x, plot_z) = message {
+                running_plots.retain(|p| p.plot_x != plot_x || p.plot_z != plot_z);
+            }
+            std::thread::sleep(Duration::from_millis(2));
+        }
+    }
+}
+
+/// Reads and base64 encodes the server icon if it exists and is a 64x64 png.
+fn load_favicon(path: impl AsRef<Path>) -> Option<String> {
+    let path = path.as_ref();
+    let data = fs::read(path).ok()?;
+    match png_dimensions(&data) {
+        Some((64, 64)) => Some(base64::encode(data)),
+        Some((width, height)) => {
+            warn!(
+                "{} must be 64x64 but is {}x{}, it will not be used",
+                path.display(),
+                width,
+                height
+            );
+            None
+        }
+        None => {
+            warn!("{} is not a valid png, it will not be used", path.display());
+            None
+        }
+    }
+}
+
+/// Returns the width and height from the png's IHDR chunk
+fn png_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    // The IHDR chunk must come first: 4 byte length, 4 byte type, then width and height
+    if data.len() < 24 || !data.starts_with(SIGNATURE) || &data[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(data[16..20].try_into().unwrap());
+    let height = u32::from_be_bytes(data[20..24].try_into().unwrap());
+    Some((width, height))
+}
+
+/// Lists the online players and their ping, as shown by `/list`
+fn player_list_message(online_players: &FxHashMap<u128, PlayerListEntry>) -> String {
+    let mut players: Vec<&PlayerListEntry> = online_players.values().collect();
+    players.sort_by(|a, b| a.username.cmp(&b.username));
+    let players: Vec<String> = players
+        .into_iter()
+        .map(|player| match player.ping {
+            Some(ping) => format!("{} ({} ms)", player.username, ping.as_millis()),
+            None => format!("{} (ping unknown)", player.username),
+        })
+        .collect();
+    format!(
+        "There are {} players online: {}",
+        players.len(),
+        players.join(", ")
+    )
+}
+
+/// Returns the disconnect packet sent to clients trying to log in with a protocol
+/// version this server doesn't support
+fn version_rejection(protocol_version: i32) -> Option<CDisconnectLogin> {
+    if protocol::is_supported(protocol_version) {
+        return None;
+    }
+    Some(CDisconnectLogin {
+        reason: json!({
+            "text": format!("Unsupported version! This server is running Minecraft {}.", MC_VERSION)
+        })
+        .to_string(),
+    })
+}
+
+/// Builds the server list status. `motd` may use `&` color and formatting codes.
+fn status_json(motd: &str, online_players: usize, favicon: Option<&str>) -> serde_json::Value {
+    let mut status = json!({
+        "version": {
+            "name": MC_VERSION,
+            "protocol": PROTOCOL_VERSION
+        },
+        "players": {
+            "max": CONFIG.max_players,
+            "online": online_players,
+            "sample": []
+        },
+        "description": {
+            "text": "",
+            "extra": ChatComponent::from_legacy_text(motd)
+        }
+    });
+    if let Some(favicon) = favicon {
+        status["favicon"] = format!("data:image/png;base64,{}", favicon).into();
+    }
+    status
 }
 
 impl MinecraftServer {
@@ -139,14 +268,12 @@ impl MinecraftServer {
         let start_time = Instant::now();
 
         // Create world folders if they don't exist yet
-        fs::create_dir_all("./world/players").unwrap();
-        fs::create_dir_all("./world/plots").unwrap();
+        fs::create_dir_all(world_dir().join("players")).unwrap();
+        fs::create_dir_all(world_dir().join("plots")).unwrap();
         fs::create_dir_all("./schems").unwrap();
 
         plot::database::init();
 
-        let bind_addr = CONFIG.bind_address.clone();
-
         // Create thread messaging structs
         let (plot_tx, server_rx) = mpsc::channel();
         let bus = Bus::new(100);
@@ -173,15 +300,29 @@ impl MinecraftServer {
 
         // Create server struct
         let mut server = MinecraftServer {
-            network: NetworkServer::new(bind_addr),
+            network: NetworkServer::new(
+                CONFIG
+                    .network_config()
+                    .expect("the config is validated when it is loaded"),
+            ),
             broadcaster: bus,
             receiver: server_rx,
             plot_sender: plot_tx,
             online_players: FxHashMap::default(),
             running_plots: Vec::new(),
+            watchdog: Watchdog::default(),
             whitelist,
+            favicon: load_favicon("server-icon.png"),
         };
 
+        if CONFIG.watchdog_timeout > 0 {
+            let timeout = Duration::from_secs(CONFIG.watchdog_timeout as u64);
+            server
+                .watchdog
+                .clone()
+                .spawn(timeout, server.plot_sender.clone());
+        }
+
         // Load the spawn area plot on server start
         // This plot should be always active
         let (spawn_tx, spawn_rx) = mpsc::channel();
@@ -191,6 +332,7 @@ impl MinecraftServer {
             server.broadcaster.add_rx(),
             server.plot_sender.clone(),
             spawn_rx,
+            server.watchdog.register(0, 0),
             true,
             None,
         );
@@ -228,24 +370,29 @@ impl MinecraftServer {
         }
     }
 
-    fn graceful_shutdown(&mut self) {
-        info!("Commencing graceful shutdown...");
-        self.broadcaster.broadcast(BroadcastMessage::Shutdown);
-        // Wait for all plots to save and unload
-        while !self.running_plots.is_empty() {
-            while let Ok(message) = self.receiver.try_recv() {
-                if let Message::PlotUnload(plot_x, plot_z) = message {
-                    self.handle_plot_unload(plot_x, plot_z);
-                }
-                std::thread::sleep(Duration::from_millis(2));
-            }
+    /// Shuts down after the watchdog found stuck plots. The stuck plots can't be saved,
+    /// but every other plot is. Exits with a failure so a supervisor can restart the server.
+    fn watchdog_shutdown(&mut self, stuck: Vec<(i32, i32)>) {
+        for (plot_x, plot_z) in stuck {
+            error!("Plot {},{} is stuck and will not be saved", plot_x, plot_z);
+            self.handle_plot_unload(plot_x, plot_z);
         }
+        self.graceful_shutdown(1);
+    }
+
+    fn graceful_shutdown(&mut self, exit_code: i32) {
+        info!("Commencing graceful shutdown...");
+        shutdown_plots(
+            &mut self.broadcaster,
+            &mut self.running_plots,
+            &self.receiver,
+        );
 
         if let Some(whitelist) = &self.whitelist {
             fs::write("whitelist.json", serde_json::to_string(whitelist).unwrap()).unwrap();
         }
 
-        std::process::exit(0);
+        std::process::exit(exit_code);
     }
 
     fn send_player_to_plot(&mut self, player: Player, new_entry: bool) {
@@ -257,6 +404,7 @@ impl MinecraftServer {
                 plot_z,
                 username: player.username.clone(),
                 gamemode: player.gamemode,
+                ping: player.ping,
             };
             self.online_players.insert(player.uuid, player_list_entry);
         } else {
@@ -275,6 +423,7 @@ impl MinecraftServer {
                 self.broadcaster.add_rx(),
                 self.plot_sender.clone(),
                 priv_rx,
+                self.watchdog.register(plot_x, plot_z),
                 false,
                 Some(player),
             );
@@ -299,9 +448,7 @@ impl MinecraftServer {
         let clients = &mut self.network.handshaking_clients;
         let username = login_start.name;
         clients[client_idx].username = Some(username.clone());
-        let set_compression = CSetCompression { threshold: 256 }.encode();
-        clients[client_idx].send_packet(&set_compression);
-        clients[client_idx].set_compressed(true);
+        clients[client_idx].enable_compression();
 
         if let Some(whitelist) = &self.whitelist {
             // uuid will only be present if bungeecord is enabled in config
@@ -335,7 +482,7 @@ impl MinecraftServer {
 
         let client = clients.remove(client_idx);
 
-        let player = Player::load_player(uuid, username, client.into());
+        let mut player = Player::load_player(uuid, username, client.into());
 
         let dimension = CJoinGameDimensionElement {
             natural: 1,
@@ -446,7 +593,7 @@ impl MinecraftServer {
             yaw: player.yaw,
             pitch: player.pitch,
             flags: 0,
-            teleport_id: 0,
+            teleport_id: player.teleports.start(),
             dismount_vehicle: false,
         }
         .encode();
@@ -515,6 +662,7 @@ impl MinecraftServer {
         player.client.send_packet(&time_update);
 
         player.update_player_abilities();
+        player.send_full_health();
 
         self.plot_sender
             .send(Message::PlayerJoined(player))
@@ -559,7 +707,10 @@ impl MinecraftServer {
                 self.send_player_to_plot(player, false);
             }
             Message::Shutdown => {
-                self.graceful_shutdown();
+                self.graceful_shutdown(0);
+            }
+            Message::WatchdogTimeout(stuck) => {
+                self.watchdog_shutdown(stuck);
             }
             Message::PlayerTeleportOther(player, other_username) => {
                 let username_lower = other_username.to_lowercase();
@@ -602,6 +753,14 @@ impl MinecraftServer {
                 self.broadcaster
                     .broadcast(BroadcastMessage::PlayerUpdateGamemode(uuid, gamemode));
             }
+            Message::PlayerPing(uuid, ping) => {
+                if let Some(player) = self.online_players.get_mut(&uuid) {
+                    player.ping = Some(ping);
+                }
+            }
+            Message::ListPlayers(sender) => {
+                sender.send_system_message(&player_list_message(&self.online_players));
+            }
             Message::WhitelistAdd(uuid, username, sender) => {
                 if let Some(whitelist) = &mut self.whitelist {
                     let msg = format!("{} was sucessfully added to the whitelist.", &username);
@@ -684,16 +843,17 @@ impl ServerBoundPacketHandler for MinecraftServer {
             // TODO: Handle invalid next state
             _ => return,
         };
-        if next_state == NetworkState::Login && handshake.protocol_version != PROTOCOL_VERSION {
-            warn!("A player tried to connect using the wrong version");
-            let disconnect = CDisconnectLogin {
-                reason: json!({ "text": format!("Version mismatch, I'm on {}!", MC_VERSION) })
-                    .to_string(),
-            }
-            .encode();
-            client.send_packet(&disconnect);
+        if next_state != NetworkState::Login {
+            return;
+        }
+        if let Some(disconnect) = version_rejection(handshake.protocol_version) {
+            warn!(
+                "A player tried to connect using an unsupported protocol version ({})",
+                handshake.protocol_version
+            );
+            client.send_packet(&disconnect.encode());
             client.close_connection();
-        } else if next_state == NetworkState::Login && CONFIG.bungeecord {
+        } else if CONFIG.bungeecord {
             let split: Vec<&str> = handshake.server_address.split('\u{0}').collect();
             if split.len() == 3 || split.len() == 4 {
                 client.uuid = u128::from_str_radix(split[2], 16).ok();
@@ -714,26 +874,32 @@ impl ServerBoundPacketHandler for MinecraftServer {
     fn handle_request(&mut self, _request: SRequest, client_idk: usize) {
         let client = &mut self.network.handshaking_clients[client_idk];
         let response = CResponse {
-            json_response: json!({
-                "version": {
-                    "name": MC_VERSION,
-                    "protocol": PROTOCOL_VERSION
-                },
-                "players": {
-                    "max": CONFIG.max_players,
-                    "online": self.online_players.len(),
-                    "sample": []
-                },
-                "description": {
-                    "text": CONFIG.motd
-                }
-            })
+            json_response: status_json(
+                &CONFIG.motd,
+                self.online_players.len(),
+                self.favicon.as_deref(),
+            )
             .to_string(),
         }
         .encode();
         client.send_packet(&response);
     }
 
+    fn handle_legacy_ping(&mut self, _legacy_ping: SLegacyPing, client_idx: usize) {
+        let client = &mut self.network.handshaking_clients[client_idx];
+        let response = CLegacyStatusResponse {
+            // Modern servers report this to tell legacy clients they're incompatible
+            protocol: 127,
+            version: MC_VERSION.to_owned(),
+            motd: CONFIG.motd.clone(),
+            online_players: self.online_players.len() as i64,
+            max_players: CONFIG.max_players,
+        }
+        .encode();
+        client.send_raw(&response);
+        client.close_connection();
+    }
+
     fn handle_ping(&mut self, ping: SPing, client_idx: usize) {
         let client = &mut self.network.handshaking_clients[client_idx];
         let pong = CPong {
@@ -747,3 +913,120 @@ impl ServerBoundPacketHandler for MinecraftServer {
         self.handle_player_login(client_idx, login_start);
     }
 }
+
+#[test]
+fn status_json_favicon() {
+    let status = status_json("", 0, None);
+    assert!(status.get("favicon").is_none());
+
+    let status = status_json("", 0, Some("aWNvbg=="));
+    assert_eq!(status["favicon"], "data:image/png;base64,aWNvbg==");
+}
+
+#[test]
+fn status_motd_color_codes() {
+    let status = status_json("&aHello &bWorld", 0, None);
+    let description = &status["description"];
+    assert_eq!(description["text"], "");
+    assert_eq!(
+        description["extra"],
+        json!([
+            { "text": "Hello ", "color": "green" },
+            { "text": "World", "color": "aqua" }
+        ])
+    );
+}
+
+#[test]
+fn png_dimensions_from_header() {
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend_from_slice(&64u32.to_be_bytes());
+    png.extend_from_slice(&32u32.to_be_bytes());
+    assert_eq!(png_dimensions(&png), Some((64, 32)));
+    assert_eq!(png_dimensions(b"not a png"), None);
+}
+
+#[test]
+fn mismatched_protocol_version_rejected() {
+    let rejection = version_rejection(PROTOCOL_VERSION - 1).unwrap();
+    assert!(rejection.reason.contains(MC_VERSION));
+    assert!(version_rejection(PROTOCOL_VERSION + 1).is_some());
+    assert!(version_rejection(PROTOCOL_VERSION).is_none());
+}
+
+#[test]
+fn list_shows_players_and_ping() {
+    let mut online_players = FxHashMap::default();
+    online_players.insert(
+        1,
+        PlayerListEntry {
+            plot_x: 0,
+            plot_z: 0,
+            username: "Steve".to_string(),
+            gamemode: Gamemode::Creative,
+            ping: Some(Duration::from_millis(42)),
+        },
+    );
+    online_players.insert(
+        2,
+        PlayerListEntry {
+            plot_x: 3,
+            plot_z: -1,
+            username: "Alex".to_string(),
+            gamemode: Gamemode::Spectator,
+            ping: Some(Duration::from_millis(7)),
+        },
+    );
+    assert_eq!(
+        player_list_message(&online_players),
+        "There are 2 players online: Alex (7 ms), Steve (42 ms)"
+    );
+
+    online_players.get_mut(&2).unwrap().ping = None;
+    assert_eq!(
+        player_list_message(&online_players),
+        "There are 2 players online: Alex (ping unknown), Steve (42 ms)"
+    );
+}
+
+#[test]
+fn shutdown_skips_stuck_plots() {
+    use std::thread;
+
+    database::init();
+    let plot_path = world_dir().join("plots/p-50,10");
+    let _ = fs::remove_file(&plot_path);
+    let mut broadcaster = Bus::new(1);
+    // Never read from, like the broadcasts of a plot stuck in a tick
+    let _stuck_plot = broadcaster.add_rx();
+    let (plot_sender, receiver) = mpsc::channel();
+    let (priv_message_sender, priv_rx) = mpsc::channel();
+    let watchdog = Watchdog::default();
+    Plot::load_and_run(
+        -50,
+        10,
+        broadcaster.add_rx(),
+        plot_sender,
+        priv_rx,
+        watchdog.register(-50, 10),
+        true,
+        None,
+    );
+    broadcaster.broadcast(BroadcastMessage::PlayerLeft(0));
+    let mut running_plots = vec![PlotListEntry {
+        plot_x: -50,
+        plot_z: 10,
+        priv_message_sender,
+    }];
+
+    let (done_tx, done_rx) = mpsc::channel();
+    thread::spawn(move || {
+        shutdown_plots(&mut broadcaster, &mut running_plots, &receiver);
+        done_tx.send(()).unwrap();
+    });
+    done_rx
+        .recv_timeout(Duration::from_secs(10))
+        .expect("shutdown waited on the stuck plot");
+    // The plot that was still running saved before it unloaded
+    assert!(plot_path.exists());
+}
@@ -1,8 +1,29 @@
 use serde::de::Visitor;
 use serde::{Deserialize, Serialize};
 use std::num::ParseIntError;
+use std::path::PathBuf;
 use std::str::FromStr;
 
+/// The directory holding the saved plots and players
+#[cfg(not(test))]
+pub fn world_dir() -> PathBuf {
+    PathBuf::from("./world")
+}
+
+// Tests save into a world of their own instead of a real one
+#[cfg(test)]
+pub fn world_dir() -> PathBuf {
+    use once_cell::sync::Lazy;
+
+    static DIR: Lazy<PathBuf> = Lazy::new(|| {
+        let dir = std::env::temp_dir().join(format!("mchprs-test-world-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("players")).unwrap();
+        std::fs::create_dir_all(dir.join("plots")).unwrap();
+        dir
+    });
+    DIR.clone()
+}
+
 #[derive(Debug)]
 pub struct HyphenatedUUID(pub u128);
 
@@ -0,0 +1,163 @@
+//! Detects plot threads that stopped updating, for example because of a redstone loop
+//! that escaped the tick update limit. Instead of hanging forever, the server saves the
+//! plots that are still responsive and exits so a supervisor can restart it.
+
+use crate::server::Message;
+use rustc_hash::FxHashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::error;
+
+/// The longest the watchdog thread sleeps between checks
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+type HeartbeatMap = FxHashMap<(i32, i32), Arc<AtomicU64>>;
+
+/// Keeps track of when every running plot last finished an update.
+#[derive(Clone)]
+pub struct Watchdog {
+    start: Instant,
+    plots: Arc<Mutex<HeartbeatMap>>,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Watchdog {
+            start: Instant::now(),
+            plots: Default::default(),
+        }
+    }
+}
+
+impl Watchdog {
+    /// Starts watching the plot at `x`, `z`. The plot stops being watched when the
+    /// returned heartbeat is dropped.
+    pub fn register(&self, x: i32, z: i32) -> Heartbeat {
+        let heartbeat = Heartbeat {
+            start: self.start,
+            last_beat: Default::default(),
+            plots: self.plots.clone(),
+            pos: (x, z),
+        };
+        heartbeat.beat();
+        self.plots
+            .lock()
+            .unwrap()
+            .insert((x, z), heartbeat.last_beat.clone());
+        heartbeat
+    }
+
+    /// Returns the plots that haven't finished an update in more than `timeout` before `now`.
+    pub fn stuck_plots(&self, now: Instant, timeout: Duration) -> Vec<(i32, i32)> {
+        let now = now.saturating_duration_since(self.start).as_millis() as u64;
+        let timeout = timeout.as_millis() as u64;
+        let mut stuck: Vec<(i32, i32)> = self
+            .plots
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, last_beat)| {
+                now.saturating_sub(last_beat.load(Ordering::Relaxed)) > timeout
+            })
+            .map(|(&pos, _)| pos)
+            .collect();
+        stuck.sort_unstable();
+        stuck
+    }
+
+    /// Spawns the watchdog thread. Once a plot is stuck for longer than `timeout`,
+    /// the server is sent `Message::WatchdogTimeout` and the thread stops.
+    pub fn spawn(self, timeout: Duration, sender: Sender<Message>) {
+        let check_interval = MAX_CHECK_INTERVAL.min(timeout / 2);
+        thread::Builder::new()
+            .name("Watchdog".to_string())
+            .spawn(move || loop {
+                thread::sleep(check_interval);
+                let stuck = self.stuck_plots(Instant::now(), timeout);
+                if stuck.is_empty() {
+                    continue;
+                }
+                for (x, z) in &stuck {
+                    error!(
+                        "Plot {},{} has not finished an update in over {:?}!",
+                        x, z, timeout
+                    );
+                }
+                error!("The server will save and shut down");
+                let _ = sender.send(Message::WatchdogTimeout(stuck));
+                return;
+            })
+            .unwrap();
+    }
+}
+
+/// Held by a plot thread, which reports to the watchdog by calling `beat` after every update.
+pub struct Heartbeat {
+    start: Instant,
+    /// Milliseconds since the watchdog was created
+    last_beat: Arc<AtomicU64>,
+    plots: Arc<Mutex<HeartbeatMap>>,
+    pos: (i32, i32),
+}
+
+impl Heartbeat {
+    pub fn beat(&self) {
+        let elapsed = self.start.elapsed().as_millis() as u64;
+        self.last_beat.store(elapsed, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        let mut plots = self.plots.lock().unwrap();
+        // The plot might have already been loaded again with a new heartbeat
+        let registered = plots
+            .get(&self.pos)
+            .map_or(false, |last_beat| Arc::ptr_eq(last_beat, &self.last_beat));
+        if registered {
+            plots.remove(&self.pos);
+        }
+    }
+}
+
+#[test]
+fn stale_heartbeat_reported_as_stuck() {
+    let watchdog = Watchdog::default();
+    let timeout = Duration::from_secs(60);
+    let stuck = watchdog.register(1, 2);
+    let alive = watchdog.register(3, 4);
+
+    let now = Instant::now();
+    assert!(watchdog.stuck_plots(now, timeout).is_empty());
+
+    // Only the plot that keeps beating is fine once the deadline passes
+    let later = now + Duration::from_secs(61);
+    assert_eq!(watchdog.stuck_plots(later, timeout), vec![(1, 2), (3, 4)]);
+    alive.last_beat.store(
+        (later - watchdog.start).as_millis() as u64,
+        Ordering::Relaxed,
+    );
+    assert_eq!(watchdog.stuck_plots(later, timeout), vec![(1, 2)]);
+
+    // Unloaded plots are no longer watched
+    drop(stuck);
+    assert!(watchdog.stuck_plots(later, timeout).is_empty());
+}
+
+#[test]
+fn stuck_tick_triggers_shutdown() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let watchdog = Watchdog::default();
+    let heartbeat = watchdog.register(5, -5);
+    watchdog.spawn(Duration::from_millis(50), tx);
+
+    // The plot never beats again, just like a plot stuck in a tick
+    match rx.recv_timeout(Duration::from_secs(5)) {
+        Ok(Message::WatchdogTimeout(stuck)) => assert_eq!(stuck, vec![(5, -5)]),
+        other => panic!("expected a watchdog timeout, got {:?}", other),
+    }
+    drop(heartbeat);
+}
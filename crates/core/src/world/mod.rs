@@ -44,7 +44,8 @@ pub trait World {
     /// Returns None if the chunk does not exist in this world.
     fn get_chunk_mut(&mut self, x: i32, z: i32) -> Option<&mut Chunk>;
 
-    /// Schedules a tick in the world with `delay` and `pritority`
+    /// Schedules a tick in the world with `delay` and `pritority`.
+    /// `delay` is in redstone ticks (two game ticks each), since plots run one redstone tick at a time.
     fn schedule_tick(&mut self, pos: BlockPos, delay: u32, priority: TickPriority);
 
     /// Returns true if there is a tick entry with `pos`
@@ -64,6 +65,11 @@ pub trait World {
         pitch: f32,
     ) {
     }
+
+    /// Sends a block action to animate the block at `pos` on clients.
+    /// `block_type` is the block's registry id, not its state id.
+    #[allow(unused_variables)]
+    fn block_action(&mut self, pos: BlockPos, action_id: u8, action_param: u8, block_type: i32) {}
 }
 
 // TODO: I have no idea how to deduplicate this in a sane way
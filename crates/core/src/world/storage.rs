@@ -2,6 +2,7 @@ use crate::plot::{PLOT_BLOCK_HEIGHT, PLOT_SECTIONS};
 use itertools::Itertools;
 use mchprs_blocks::block_entities::BlockEntity;
 use mchprs_blocks::BlockPos;
+pub use mchprs_save_data::plot_data::StorageKind;
 use mchprs_save_data::plot_data::{ChunkData, ChunkSectionData};
 
 use mchprs_network::packets::clientbound::{
@@ -239,11 +240,22 @@ impl PalettedBitBuffer {
     }
 }
 
+enum SectionBlocks {
+    Dense {
+        buffer: PalettedBitBuffer,
+        changed_blocks: Box<[i16; 16 * 16 * 16]>,
+    },
+    Sparse {
+        /// Keyed by the position of the block inside the section
+        blocks: FxHashMap<BlockPos, u32>,
+        changed_blocks: FxHashMap<BlockPos, u32>,
+    },
+}
+
 pub struct ChunkSection {
-    buffer: PalettedBitBuffer,
+    blocks: SectionBlocks,
     block_count: u32,
     multi_block: CMultiBlockChange,
-    changed_blocks: [i16; 16 * 16 * 16],
     changed: bool,
 }
 
@@ -252,12 +264,31 @@ impl ChunkSection {
         ((y << 8) | (z << 4) | x) as usize
     }
 
+    fn get_pos(idx: usize) -> BlockPos {
+        BlockPos::new(
+            (idx & 0xF) as i32,
+            (idx >> 8) as i32,
+            ((idx & 0xF0) >> 4) as i32,
+        )
+    }
+
     fn get_block(&self, x: u32, y: u32, z: u32) -> u32 {
-        let idx = ChunkSection::get_index(x, y, z);
-        if self.changed_blocks[idx] >= 0 {
-            self.changed_blocks[idx] as u32
-        } else {
-            self.buffer.get_entry(idx)
+        match &self.blocks {
+            SectionBlocks::Dense {
+                buffer,
+                changed_blocks,
+            } => {
+                let idx = ChunkSection::get_index(x, y, z);
+                if changed_blocks[idx] >= 0 {
+                    changed_blocks[idx] as u32
+                } else {
+                    buffer.get_entry(idx)
+                }
+            }
+            SectionBlocks::Sparse { blocks, .. } => {
+                let pos = BlockPos::new(x as i32, y as i32, z as i32);
+                blocks.get(&pos).copied().unwrap_or(0)
+            }
         }
     }
 
@@ -269,11 +300,26 @@ impl ChunkSection {
         } else if old_block != 0 && block == 0 {
             self.block_count -= 1;
         }
-        let idx = ChunkSection::get_index(x, y, z);
         let changed = old_block != block;
         if changed {
             self.changed = true;
-            self.changed_blocks[idx] = block as i16;
+            match &mut self.blocks {
+                SectionBlocks::Dense { changed_blocks, .. } => {
+                    changed_blocks[ChunkSection::get_index(x, y, z)] = block as i16;
+                }
+                SectionBlocks::Sparse {
+                    blocks,
+                    changed_blocks,
+                } => {
+                    let pos = BlockPos::new(x as i32, y as i32, z as i32);
+                    if block == 0 {
+                        blocks.remove(&pos);
+                    } else {
+                        blocks.insert(pos, block);
+                    }
+                    changed_blocks.insert(pos, block);
+                }
+            }
         }
         changed
     }
@@ -290,7 +336,10 @@ impl ChunkSection {
         let buffer =
             PalettedBitBuffer::load(data.entries, bits_per_entry, loaded_longs, palette, 9);
         ChunkSection {
-            buffer,
+            blocks: SectionBlocks::Dense {
+                buffer,
+                changed_blocks: Box::new([-1; 16 * 16 * 16]),
+            },
             block_count: data.block_count as u32,
             multi_block: CMultiBlockChange {
                 chunk_x: 0,
@@ -298,29 +347,50 @@ impl ChunkSection {
                 chunk_z: 0,
                 records: Vec::new(),
             },
-            changed_blocks: [-1; 16 * 16 * 16],
             changed: false,
         }
     }
 
+    /// Builds a dense buffer holding every block in a sparse section.
+    /// Blocks are added in index order so the palette doesn't depend on the map's order.
+    fn sparse_buffer(blocks: &FxHashMap<BlockPos, u32>) -> PalettedBitBuffer {
+        let mut buffer = PalettedBitBuffer::new(4096, 9);
+        let entries = blocks
+            .iter()
+            .map(|(pos, block)| {
+                let idx = ChunkSection::get_index(pos.x as u32, pos.y as u32, pos.z as u32);
+                (idx, *block)
+            })
+            .sorted_unstable();
+        for (idx, block) in entries {
+            buffer.set_entry(idx, block);
+        }
+        buffer
+    }
+
     fn save(&mut self) -> Option<ChunkSectionData> {
         self.flush();
-        if self.buffer.use_palette && self.buffer.palette.len() == 1 && self.buffer.palette[0] == 0
-        {
+        let sparse_buffer;
+        let buffer = match &self.blocks {
+            SectionBlocks::Dense { buffer, .. } => buffer,
+            SectionBlocks::Sparse { blocks, .. } => {
+                sparse_buffer = ChunkSection::sparse_buffer(blocks);
+                &sparse_buffer
+            }
+        };
+        if buffer.use_palette && buffer.palette.len() == 1 && buffer.palette[0] == 0 {
             // chunk section is completely air
             return None;
         }
 
-        let longs: Vec<i64> = self
-            .buffer
+        let longs: Vec<i64> = buffer
             .data
             .longs
             .clone()
             .into_iter()
             .map(|x| x as i64)
             .collect();
-        let palette: Vec<i32> = self
-            .buffer
+        let palette: Vec<i32> = buffer
             .palette
             .clone()
             .into_iter()
@@ -329,24 +399,32 @@ impl ChunkSection {
         Some(ChunkSectionData {
             data: longs,
             palette,
-            bits_per_block: self.buffer.data.bits_per_entry as i8,
+            bits_per_block: buffer.data.bits_per_entry as i8,
             block_count: self.block_count as i32,
-            entries: self.buffer.entries(),
+            entries: buffer.entries(),
         })
     }
 
     fn compress(&mut self) {
-        let mut new_buffer = PalettedBitBuffer::new(4096, 9);
-        for i in 0..4096 {
-            new_buffer.set_entry(i, self.buffer.get_entry(i));
+        if let SectionBlocks::Dense { buffer, .. } = &mut self.blocks {
+            let mut new_buffer = PalettedBitBuffer::new(4096, 9);
+            for i in 0..4096 {
+                new_buffer.set_entry(i, buffer.get_entry(i));
+            }
+            *buffer = new_buffer;
         }
-        self.buffer = new_buffer;
     }
 
     fn encode_packet(&self) -> CChunkDataSection {
+        let block_states = match &self.blocks {
+            SectionBlocks::Dense { buffer, .. } => buffer.encode_packet(),
+            SectionBlocks::Sparse { blocks, .. } => {
+                ChunkSection::sparse_buffer(blocks).encode_packet()
+            }
+        };
         CChunkDataSection {
             block_count: self.block_count as i16,
-            block_states: self.buffer.encode_packet(),
+            block_states,
             biomes: PalettedContainer {
                 bits_per_entry: 0,
                 data_array: vec![],
@@ -356,10 +434,17 @@ impl ChunkSection {
     }
 
     fn flush(&mut self) {
-        if self.changed {
-            for (i, block) in self.changed_blocks.iter().enumerate() {
+        if !self.changed {
+            return;
+        }
+        if let SectionBlocks::Dense {
+            buffer,
+            changed_blocks,
+        } = &mut self.blocks
+        {
+            for (i, block) in changed_blocks.iter().enumerate() {
                 if *block >= 0 {
-                    self.buffer.set_entry(i, *block as u32);
+                    buffer.set_entry(i, *block as u32);
                 }
             }
         }
@@ -370,19 +455,38 @@ impl ChunkSection {
         self.multi_block.chunk_y = chunk_y;
         self.multi_block.chunk_z = chunk_z;
         if self.changed {
-            for (i, block) in self.changed_blocks.iter().enumerate() {
-                if *block >= 0 {
-                    self.buffer.set_entry(i, *block as u32);
-                    self.multi_block.records.push(C3BMultiBlockChangeRecord {
-                        block_id: *block as u32,
-                        x: (i & 0xF) as u8,
-                        y: (i >> 8) as u8,
-                        z: ((i & 0xF0) >> 4) as u8,
-                    });
+            let records = &mut self.multi_block.records;
+            match &mut self.blocks {
+                SectionBlocks::Dense {
+                    buffer,
+                    changed_blocks,
+                } => {
+                    for (i, block) in changed_blocks.iter().enumerate() {
+                        if *block >= 0 {
+                            buffer.set_entry(i, *block as u32);
+                            let pos = ChunkSection::get_pos(i);
+                            records.push(C3BMultiBlockChangeRecord {
+                                block_id: *block as u32,
+                                x: pos.x as u8,
+                                y: pos.y as u8,
+                                z: pos.z as u8,
+                            });
+                        }
+                    }
+                    **changed_blocks = [-1; 16 * 16 * 16];
+                }
+                SectionBlocks::Sparse { changed_blocks, .. } => {
+                    for (pos, block) in changed_blocks.drain() {
+                        records.push(C3BMultiBlockChangeRecord {
+                            block_id: block,
+                            x: pos.x as u8,
+                            y: pos.y as u8,
+                            z: pos.z as u8,
+                        });
+                    }
                 }
             }
             self.changed = false;
-            self.changed_blocks = [-1; 16 * 16 * 16];
         }
         &self.multi_block
     }
@@ -390,12 +494,90 @@ impl ChunkSection {
     pub fn block_count(&self) -> u32 {
         self.block_count
     }
+
+    pub fn storage_kind(&self) -> StorageKind {
+        match self.blocks {
+            SectionBlocks::Dense { .. } => StorageKind::Dense,
+            SectionBlocks::Sparse { .. } => StorageKind::Sparse,
+        }
+    }
+
+    /// Roughly how many bytes this section has allocated outside of itself
+    fn heap_usage(&self) -> usize {
+        match &self.blocks {
+            SectionBlocks::Dense { buffer, .. } => {
+                buffer.data.longs.capacity() * mem::size_of::<u64>()
+                    + buffer.palette.capacity() * mem::size_of::<u32>()
+                    + mem::size_of::<[i16; 16 * 16 * 16]>()
+            }
+            SectionBlocks::Sparse {
+                blocks,
+                changed_blocks,
+            } => {
+                // Each map slot also has a control byte
+                let slot_size = mem::size_of::<(BlockPos, u32)>() + 1;
+                (blocks.capacity() + changed_blocks.capacity()) * slot_size
+            }
+        }
+    }
+
+    /// Converts the section to keep its blocks in `kind` storage.
+    /// Changes that haven't been sent to clients yet are kept.
+    fn set_storage_kind(&mut self, kind: StorageKind) {
+        if self.storage_kind() == kind {
+            return;
+        }
+        self.blocks = match &self.blocks {
+            SectionBlocks::Dense {
+                buffer,
+                changed_blocks,
+            } => {
+                let mut blocks = FxHashMap::default();
+                let mut changed = FxHashMap::default();
+                for (i, changed_block) in changed_blocks.iter().enumerate() {
+                    let pos = ChunkSection::get_pos(i);
+                    let block = if *changed_block >= 0 {
+                        changed.insert(pos, *changed_block as u32);
+                        *changed_block as u32
+                    } else {
+                        buffer.get_entry(i)
+                    };
+                    if block != 0 {
+                        blocks.insert(pos, block);
+                    }
+                }
+                SectionBlocks::Sparse {
+                    blocks,
+                    changed_blocks: changed,
+                }
+            }
+            SectionBlocks::Sparse {
+                blocks,
+                changed_blocks,
+            } => {
+                // Blocks that are still waiting to be sent go back into the changed buffer
+                let buffer = ChunkSection::sparse_buffer(blocks);
+                let mut changed = Box::new([-1; 16 * 16 * 16]);
+                for (pos, block) in changed_blocks {
+                    let idx = ChunkSection::get_index(pos.x as u32, pos.y as u32, pos.z as u32);
+                    changed[idx] = *block as i16;
+                }
+                SectionBlocks::Dense {
+                    buffer,
+                    changed_blocks: changed,
+                }
+            }
+        };
+    }
 }
 
 impl Default for ChunkSection {
     fn default() -> ChunkSection {
         ChunkSection {
-            buffer: PalettedBitBuffer::new(4096, 9),
+            blocks: SectionBlocks::Dense {
+                buffer: PalettedBitBuffer::new(4096, 9),
+                changed_blocks: Box::new([-1; 16 * 16 * 16]),
+            },
             block_count: 0,
             multi_block: CMultiBlockChange {
                 chunk_x: 0,
@@ -403,7 +585,6 @@ impl Default for ChunkSection {
                 chunk_z: 0,
                 records: Vec::new(),
             },
-            changed_blocks: [-1; 16 * 16 * 16],
             changed: false,
         }
     }
@@ -572,6 +753,23 @@ impl Chunk {
         }
     }
 
+    /// Converts every section in the chunk to keep its blocks in `kind` storage
+    pub fn set_storage_kind(&mut self, kind: StorageKind) {
+        for section in &mut self.sections {
+            section.set_storage_kind(kind);
+        }
+    }
+
+    /// Roughly how many bytes the blocks in this chunk take up in memory
+    pub fn memory_usage(&self) -> usize {
+        mem::size_of::<Chunk>()
+            + self
+                .sections
+                .iter()
+                .map(ChunkSection::heap_usage)
+                .sum::<usize>()
+    }
+
     pub fn multi_blocks(&mut self) -> impl Iterator<Item = &CMultiBlockChange> {
         let x = self.x;
         let z = self.z;
@@ -591,3 +789,45 @@ impl Chunk {
         }
     }
 }
+
+#[test]
+fn sparse_storage_matches_dense() {
+    fn build(chunk: &mut Chunk) -> Vec<(u8, u8, u8, u32)> {
+        // A line of dust on sandstone with a gap, like a small sparse circuit
+        for x in 0..16 {
+            chunk.set_block(x, 1, 4, 278);
+            chunk.set_block(x, 2, 4, 2931);
+        }
+        chunk.set_block(7, 2, 4, 0);
+        chunk.set_block(3, 40, 9, 4564);
+        let mut records: Vec<_> = chunk
+            .multi_blocks()
+            .flat_map(|multi_block| &multi_block.records)
+            .map(|r| (r.x, r.y, r.z, r.block_id))
+            .collect();
+        records.sort_unstable();
+        records
+    }
+
+    let mut dense = Chunk::empty(0, 0);
+    let mut sparse = Chunk::empty(0, 0);
+    sparse.set_storage_kind(StorageKind::Sparse);
+    assert_eq!(build(&mut dense), build(&mut sparse));
+    assert!(sparse.memory_usage() < dense.memory_usage());
+
+    for y in 0..PLOT_BLOCK_HEIGHT as u32 {
+        for z in 0..16 {
+            for x in 0..16 {
+                assert_eq!(dense.get_block(x, y, z), sparse.get_block(x, y, z));
+            }
+        }
+    }
+    for (dense_section, sparse_section) in dense.sections.iter().zip(&sparse.sections) {
+        assert_eq!(dense_section.block_count(), sparse_section.block_count());
+    }
+    assert_eq!(dense.save().sections, sparse.save().sections);
+
+    sparse.set_storage_kind(StorageKind::Dense);
+    assert_eq!(sparse.sections[0].storage_kind(), StorageKind::Dense);
+    assert_eq!(dense.save().sections, sparse.save().sections);
+}
@@ -1,38 +1,111 @@
 mod nbt_map;
 pub mod packets;
 
-use packets::serverbound::ServerBoundPacket;
-use packets::{read_packet, PacketEncoder};
+use packets::clientbound::{CSetCompression, ClientBoundPacket};
+use packets::serverbound::{SLegacyPing, ServerBoundPacket};
+use packets::{
+    decode_packet_data, frame_packet_data, packet_log_line, FrameBuffer, HandshakeKind,
+    PacketDecodeError, PacketEncoder,
+};
+use std::io::{self, ErrorKind, Read, Write};
 use std::net::{Shutdown, TcpListener, TcpStream};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{mpsc, Arc};
 use std::thread;
-use tracing::warn;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// The most packets taken from a single client each time its packets are received.
+/// Anything past this waits until the next time, so a client flooding the server with
+/// packets can't hold up everyone else.
+const MAX_PACKETS_PER_RECEIVE: usize = 512;
+
+/// A connection to a client. On a real server this is a `TcpStream`, but anything
+/// that can be read from on one thread while being written to from others works,
+/// which lets the network layer run over an in-memory pipe in tests.
+pub trait Transport: Read + Send + Sized + 'static {
+    /// Writes all of `data`. This only takes `&self` since packets are sent from
+    /// several handles to the same connection.
+    fn write_all_shared(&self, data: &[u8]) -> io::Result<()>;
+
+    /// Creates another handle to the same connection
+    fn try_clone(&self) -> io::Result<Self>;
+
+    /// Closes the connection in both directions
+    fn shutdown(&self) -> io::Result<()>;
+}
+
+impl Transport for TcpStream {
+    fn write_all_shared(&self, data: &[u8]) -> io::Result<()> {
+        (&*self).write_all(data)
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+
+    fn shutdown(&self) -> io::Result<()> {
+        TcpStream::shutdown(self, Shutdown::Both)
+    }
+}
+
+/// Adapts a shared reference to a `Transport` into a `Write`
+struct TransportWriter<'a, S>(&'a S);
+
+impl<S: Transport> Write for TransportWriter<'_, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write_all_shared(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
 
 #[derive(Debug)]
-pub struct PlayerPacketSender {
-    stream: Option<TcpStream>,
+pub struct PlayerPacketSender<S = TcpStream> {
+    stream: Option<S>,
+    compression_threshold: Option<usize>,
 }
 
-impl PlayerPacketSender {
-    pub fn new(conn: &PlayerConn) -> PlayerPacketSender {
+impl<S: Transport> PlayerPacketSender<S> {
+    pub fn new(conn: &PlayerConn<S>) -> PlayerPacketSender<S> {
         let stream = conn.client.stream.try_clone().ok();
         if stream.is_none() {
             warn!("Creating PlayerPacketSender with dead stream")
         }
-        PlayerPacketSender { stream }
+        PlayerPacketSender {
+            stream,
+            compression_threshold: conn.client.compression_threshold,
+        }
     }
 
     pub fn send_packet(&self, data: &PacketEncoder) {
         if let Some(stream) = &self.stream {
-            // Going to assume stream is compressed since it should be after login
-            let _ = data.write_compressed(stream);
+            let stream = TransportWriter(stream);
+            // Going to assume stream is compressed if enabled since it should be after login
+            let _ = match self.compression_threshold {
+                Some(threshold) => data.write_compressed(stream, threshold),
+                None => data.write_uncompressed(stream),
+            };
         }
     }
 }
 
+/// How much of each inbound packet is logged, for debugging protocol issues
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PacketLog {
+    #[default]
+    Off,
+    /// The state, id, and length of each packet
+    Headers,
+    /// Everything in `Headers` along with a hex dump of the payload
+    Payloads,
+}
+
 /// The minecraft protocol has these 4 different states.
-#[derive(PartialEq, Eq, Clone)]
+#[derive(PartialEq, Eq, Clone, Debug)]
 pub enum NetworkState {
     Handshake,
     Status,
@@ -40,13 +113,14 @@ pub enum NetworkState {
     Play,
 }
 
-pub struct HandshakingConn {
-    client: NetworkClient,
+pub struct HandshakingConn<S = TcpStream> {
+    client: NetworkClient<S>,
     pub username: Option<String>,
     pub uuid: Option<u128>,
+    connected_at: Instant,
 }
 
-impl HandshakingConn {
+impl<S: Transport> HandshakingConn<S> {
     pub fn send_packet(&self, data: &PacketEncoder) {
         self.client.send_packet(data);
     }
@@ -55,8 +129,21 @@ impl HandshakingConn {
         self.client.receive_packets(&mut true)
     }
 
-    pub fn set_compressed(&self, compressed: bool) {
-        self.client.compressed.store(compressed, Ordering::Relaxed)
+    pub fn send_raw(&self, data: &[u8]) {
+        self.client.send_raw(data);
+    }
+
+    /// Sends Set Compression and switches the connection over to compressed framing.
+    /// Does nothing if compression is disabled.
+    pub fn enable_compression(&self) {
+        if let Some(threshold) = self.client.compression_threshold {
+            let set_compression = CSetCompression {
+                threshold: threshold as i32,
+            }
+            .encode();
+            self.client.send_packet(&set_compression);
+            self.client.compressed.store(true, Ordering::Relaxed)
+        }
     }
 
     pub fn close_connection(&self) {
@@ -64,8 +151,8 @@ impl HandshakingConn {
     }
 }
 
-impl From<HandshakingConn> for PlayerConn {
-    fn from(conn: HandshakingConn) -> Self {
+impl<S> From<HandshakingConn<S>> for PlayerConn<S> {
+    fn from(conn: HandshakingConn<S>) -> Self {
         PlayerConn {
             client: conn.client,
             alive: true,
@@ -73,12 +160,29 @@ impl From<HandshakingConn> for PlayerConn {
     }
 }
 
-pub struct PlayerConn {
-    client: NetworkClient,
+pub struct PlayerConn<S = TcpStream> {
+    client: NetworkClient<S>,
     alive: bool,
 }
 
-impl PlayerConn {
+impl<S: Transport> PlayerConn<S> {
+    /// Wraps a connection to a client that is already in the play state. Compression stays
+    /// disabled, since it can only be turned on during login.
+    pub fn new(id: u32, stream: S, max_packet_length: usize) -> io::Result<PlayerConn<S>> {
+        let client = NetworkClient::new(
+            id,
+            stream,
+            None,
+            max_packet_length,
+            PacketLog::Off,
+            NetworkState::Play,
+        )?;
+        Ok(PlayerConn {
+            client,
+            alive: true,
+        })
+    }
+
     pub fn send_packet(&self, data: &PacketEncoder) {
         self.client.send_packet(data);
     }
@@ -97,38 +201,114 @@ impl PlayerConn {
     }
 }
 
-/// This handles the TCP stream.
-pub struct NetworkClient {
+/// This handles the connection to a client, which is usually a TCP stream.
+pub struct NetworkClient<S = TcpStream> {
     /// All NetworkClients are identified by this id.
     /// If the client is a player, the player's entitiy id becomes the same.
     pub id: u32,
-    stream: TcpStream,
+    stream: S,
     packets: mpsc::Receiver<Box<dyn ServerBoundPacket>>,
     compressed: Arc<AtomicBool>,
+    /// `None` if compression is disabled
+    compression_threshold: Option<usize>,
 }
 
-impl NetworkClient {
+impl<S: Transport> NetworkClient<S> {
+    /// Wraps `stream` and starts a thread decoding the packets read from it, starting in `state`
+    fn new(
+        id: u32,
+        stream: S,
+        compression_threshold: Option<usize>,
+        max_packet_length: usize,
+        packet_log: PacketLog,
+        state: NetworkState,
+    ) -> io::Result<NetworkClient<S>> {
+        let (packet_sender, packet_receiver) = mpsc::channel();
+        let compressed = Arc::new(AtomicBool::new(false));
+        let client_stream = stream.try_clone()?;
+        let client_compressed = compressed.clone();
+        thread::spawn(move || {
+            NetworkClient::listen(
+                client_stream,
+                packet_sender,
+                client_compressed,
+                max_packet_length,
+                packet_log,
+                state,
+            );
+        });
+        Ok(NetworkClient {
+            id,
+            stream,
+            packets: packet_receiver,
+            compressed,
+            compression_threshold,
+        })
+    }
+
     fn listen(
-        mut stream: TcpStream,
+        mut stream: S,
         sender: mpsc::Sender<Box<dyn ServerBoundPacket>>,
         compressed: Arc<AtomicBool>,
+        max_packet_length: usize,
+        packet_log: PacketLog,
+        mut state: NetworkState,
     ) {
-        let mut state = NetworkState::Handshake;
+        let mut frames = FrameBuffer::default();
+        let mut read_buf = [0; 4096];
         loop {
-            let packet = match read_packet(&mut stream, &compressed, &mut state) {
-                Ok(packet) => packet,
-                // This will cause the client to disconnect
-                Err(_) => return,
-            };
-            if sender.send(packet).is_err() {
+            // The first byte decides whether this is a modern client at all, so
+            // it has to be checked before anything is parsed as a frame.
+            if state == NetworkState::Handshake
+                && frames.peek_handshake() == Some(HandshakeKind::LegacyPing)
+            {
+                // The server will respond and close the connection, nothing else
+                // needs to be read from this client.
+                let _ = sender.send(Box::new(SLegacyPing));
                 return;
             }
+
+            loop {
+                let frame = match frames.next_frame(max_packet_length) {
+                    Ok(Some(frame)) => frame,
+                    Ok(None) => break,
+                    Err(PacketDecodeError::PacketTooLarge(len)) => {
+                        warn!("Client sent a packet that was too large ({} bytes)", len);
+                        return;
+                    }
+                    // This will cause the client to disconnect
+                    Err(_) => return,
+                };
+                let data = match frame_packet_data(frame, &compressed, max_packet_length) {
+                    Ok(data) => data,
+                    Err(_) => return,
+                };
+                if packet_log != PacketLog::Off {
+                    let payload = packet_log == PacketLog::Payloads;
+                    debug!("{}", packet_log_line(&state, &data, payload));
+                }
+                let packet = match decode_packet_data(data, &mut state) {
+                    Ok(packet) => packet,
+                    Err(_) => return,
+                };
+                if sender.send(packet).is_err() {
+                    return;
+                }
+            }
+
+            match stream.read(&mut read_buf) {
+                // The stream was closed
+                Ok(0) => return,
+                Ok(len) => frames.extend(&read_buf[..len]),
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(_) => return,
+            }
         }
     }
 
     pub fn receive_packets(&self, alive: &mut bool) -> Vec<Box<dyn ServerBoundPacket>> {
         let mut packets = Vec::new();
-        loop {
+        while packets.len() < MAX_PACKETS_PER_RECEIVE {
             let packet = self.packets.try_recv();
             match packet {
                 Ok(packet) => packets.push(packet),
@@ -143,57 +323,99 @@ impl NetworkClient {
     }
 
     pub fn send_packet(&self, data: &PacketEncoder) {
-        if self.compressed.load(Ordering::Relaxed) {
-            let _ = data.write_compressed(&self.stream);
-        } else {
-            let _ = data.write_uncompressed(&self.stream);
-        }
+        let stream = TransportWriter(&self.stream);
+        let _ = match self.compression_threshold {
+            Some(threshold) if self.compressed.load(Ordering::Relaxed) => {
+                data.write_compressed(stream, threshold)
+            }
+            _ => data.write_uncompressed(stream),
+        };
+    }
+
+    /// Writes `data` to the stream without any framing or compression.
+    pub fn send_raw(&self, data: &[u8]) {
+        let _ = self.stream.write_all_shared(data);
     }
 
     pub fn close_connection(&self) {
-        let _ = self.stream.shutdown(Shutdown::Both);
+        let _ = self.stream.shutdown();
     }
 }
 
+/// Settings for the network portion of the server
+#[derive(Clone, Debug)]
+pub struct NetworkConfig {
+    pub bind_address: String,
+    /// Clients sending packets longer than this will be disconnected
+    pub max_packet_length: usize,
+    /// At most this many new clients are queued until the next `update`,
+    /// any connections past that are closed
+    pub max_pending_connections: usize,
+    /// How long a client may take to reach the play state before it is disconnected
+    pub login_timeout: Duration,
+    /// Once compression is enabled, packets at least this many bytes long are compressed.
+    /// `None` disables compression entirely.
+    pub compression_threshold: Option<usize>,
+    pub packet_log: PacketLog,
+}
+
 /// This represents the network portion of a minecraft server
 pub struct NetworkServer {
     client_receiver: mpsc::Receiver<NetworkClient>,
     /// These clients are either in the handshake, login, or ping state, once they shift to play, they will be moved to a plot
     pub handshaking_clients: Vec<HandshakingConn>,
+    /// How long a client may take to reach the play state before it is disconnected
+    login_timeout: Duration,
 }
 
 impl NetworkServer {
-    fn listen(bind_address: &str, sender: mpsc::Sender<NetworkClient>) {
-        let listener = TcpListener::bind(bind_address).unwrap();
-
+    fn listen(
+        listener: TcpListener,
+        max_packet_length: usize,
+        compression_threshold: Option<usize>,
+        packet_log: PacketLog,
+        sender: mpsc::SyncSender<NetworkClient>,
+    ) {
         for (index, stream) in listener.incoming().enumerate() {
-            let stream = stream.unwrap();
-            let (packet_sender, packet_receiver) = mpsc::channel();
-            let compressed = Arc::new(AtomicBool::new(false));
-            let client_stream = stream.try_clone().unwrap();
-            let client_compressed = compressed.clone();
-            thread::spawn(move || {
-                NetworkClient::listen(client_stream, packet_sender, client_compressed);
-            });
-            sender
-                .send(NetworkClient {
-                    // The index will increment after each client making it unique. We'll just use this as the enitity id.
-                    id: index as u32,
-                    stream,
-                    packets: packet_receiver,
-                    compressed,
-                })
-                .unwrap();
+            let client = NetworkClient::new(
+                // The index will increment after each client making it unique. We'll just use this as the enitity id.
+                index as u32,
+                stream.unwrap(),
+                compression_threshold,
+                max_packet_length,
+                packet_log,
+                NetworkState::Handshake,
+            )
+            .unwrap();
+            match sender.try_send(client) {
+                Ok(()) => {}
+                Err(mpsc::TrySendError::Full(client)) => {
+                    // Closing the stream also stops the thread reading from it
+                    warn!("Too many pending connections, closing new connection");
+                    client.close_connection();
+                }
+                Err(mpsc::TrySendError::Disconnected(_)) => return,
+            }
         }
     }
 
     /// Creates a new `NetworkServer`. The server will then start accepting TCP clients.
-    pub fn new(bind_address: String) -> NetworkServer {
-        let (sender, receiver) = mpsc::channel();
-        thread::spawn(move || NetworkServer::listen(&bind_address, sender));
+    pub fn new(config: NetworkConfig) -> NetworkServer {
+        let listener = TcpListener::bind(&config.bind_address).unwrap();
+        let (sender, receiver) = mpsc::sync_channel(config.max_pending_connections);
+        thread::spawn(move || {
+            NetworkServer::listen(
+                listener,
+                config.max_packet_length,
+                config.compression_threshold,
+                config.packet_log,
+                sender,
+            )
+        });
         NetworkServer {
             client_receiver: receiver,
             handshaking_clients: Vec::new(),
+            login_timeout: config.login_timeout,
         }
     }
 
@@ -204,6 +426,7 @@ impl NetworkServer {
                     client,
                     username: None,
                     uuid: None,
+                    connected_at: Instant::now(),
                 }),
                 Err(mpsc::TryRecvError::Empty) => break,
                 Err(mpsc::TryRecvError::Disconnected) => {
@@ -211,5 +434,475 @@ impl NetworkServer {
                 }
             }
         }
+
+        // Stops clients that never finish logging in from holding on to a connection forever
+        let login_timeout = self.login_timeout;
+        self.handshaking_clients.retain(|conn| {
+            if conn.connected_at.elapsed() < login_timeout {
+                return true;
+            }
+            debug!("Client {} took too long to log in", conn.client.id);
+            conn.close_connection();
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use packets::PacketEncoderExt;
+    use std::collections::VecDeque;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{Condvar, Mutex};
+
+    /// Bytes travelling in one direction of a `MemoryStream`
+    #[derive(Debug, Default)]
+    struct Pipe {
+        buf: Mutex<VecDeque<u8>>,
+        readable: Condvar,
+        closed: AtomicBool,
+    }
+
+    impl Pipe {
+        fn close(&self) {
+            self.closed.store(true, Ordering::SeqCst);
+            // Taking the lock makes sure a reader can't miss the notification
+            let _buf = self.buf.lock().unwrap();
+            self.readable.notify_all();
+        }
+    }
+
+    /// One end of an in-memory connection, standing in for a `TcpStream`
+    #[derive(Debug, Clone)]
+    struct MemoryStream {
+        incoming: Arc<Pipe>,
+        outgoing: Arc<Pipe>,
+    }
+
+    impl MemoryStream {
+        fn pair() -> (MemoryStream, MemoryStream) {
+            let (a, b) = (Arc::new(Pipe::default()), Arc::new(Pipe::default()));
+            let first = MemoryStream {
+                incoming: a.clone(),
+                outgoing: b.clone(),
+            };
+            let second = MemoryStream {
+                incoming: b,
+                outgoing: a,
+            };
+            (first, second)
+        }
+    }
+
+    impl Read for MemoryStream {
+        /// Blocks until there is something to read, or returns 0 once the pipe is closed
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            let mut buf = self.incoming.buf.lock().unwrap();
+            while buf.is_empty() && !self.incoming.closed.load(Ordering::SeqCst) {
+                buf = self.incoming.readable.wait(buf).unwrap();
+            }
+            let len = out.len().min(buf.len());
+            for (byte, read) in out.iter_mut().zip(buf.drain(..len)) {
+                *byte = read;
+            }
+            Ok(len)
+        }
+    }
+
+    impl Transport for MemoryStream {
+        fn write_all_shared(&self, data: &[u8]) -> io::Result<()> {
+            if self.outgoing.closed.load(Ordering::SeqCst) {
+                return Err(ErrorKind::BrokenPipe.into());
+            }
+            self.outgoing.buf.lock().unwrap().extend(data);
+            self.outgoing.readable.notify_all();
+            Ok(())
+        }
+
+        fn try_clone(&self) -> io::Result<Self> {
+            Ok(self.clone())
+        }
+
+        fn shutdown(&self) -> io::Result<()> {
+            self.incoming.close();
+            self.outgoing.close();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn excess_connections_closed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::sync_channel(2);
+        thread::spawn(move || {
+            NetworkServer::listen(listener, 2097151, Some(256), PacketLog::Off, sender)
+        });
+
+        let streams: Vec<TcpStream> = (0..5).map(|_| TcpStream::connect(addr).unwrap()).collect();
+        let mut buf = [0; 1];
+        // The connections past the bound are closed instead of waiting in the queue
+        for mut stream in &streams[2..] {
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let closed = match stream.read(&mut buf) {
+                Ok(len) => len == 0,
+                Err(err) => err.kind() == ErrorKind::ConnectionReset,
+            };
+            assert!(closed);
+        }
+        for mut stream in &streams[..2] {
+            stream
+                .set_read_timeout(Some(Duration::from_millis(100)))
+                .unwrap();
+            let err = stream.read(&mut buf).unwrap_err();
+            assert!(matches!(
+                err.kind(),
+                ErrorKind::WouldBlock | ErrorKind::TimedOut
+            ));
+        }
+
+        let queued: Vec<u32> = receiver.try_iter().map(|client| client.id).collect();
+        assert_eq!(queued, vec![0, 1]);
+    }
+
+    #[test]
+    fn stalled_login_disconnected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sender, client_receiver) = mpsc::sync_channel(2);
+        thread::spawn(move || {
+            NetworkServer::listen(listener, 2097151, Some(256), PacketLog::Off, sender)
+        });
+        let mut server = NetworkServer {
+            client_receiver,
+            handshaking_clients: Vec::new(),
+            login_timeout: Duration::from_millis(200),
+        };
+
+        // A handshake asking to log in, and then nothing else
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(&handshake_frame(2)).unwrap();
+
+        while server.handshaking_clients.is_empty() {
+            server.update();
+            thread::sleep(Duration::from_millis(10));
+        }
+        server.update();
+        assert_eq!(server.handshaking_clients.len(), 1);
+
+        thread::sleep(Duration::from_millis(250));
+        server.update();
+        assert!(server.handshaking_clients.is_empty());
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        assert_eq!(stream.read(&mut [0; 1]).unwrap(), 0);
+    }
+
+    fn handshake_frame(next_state: i32) -> Vec<u8> {
+        let mut handshake = Vec::new();
+        handshake.write_varint(0x00);
+        handshake.write_varint(758);
+        handshake.write_string(255, "localhost");
+        handshake.write_unsigned_short(25565);
+        handshake.write_varint(next_state);
+        let mut frame = Vec::new();
+        frame.write_varint(handshake.len() as i32);
+        frame.extend_from_slice(&handshake);
+        frame
+    }
+
+    #[test]
+    fn flooding_client_does_not_starve_others() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sender, client_receiver) = mpsc::sync_channel(2);
+        thread::spawn(move || {
+            NetworkServer::listen(listener, 2097151, Some(256), PacketLog::Off, sender)
+        });
+        let mut server = NetworkServer {
+            client_receiver,
+            handshaking_clients: Vec::new(),
+            login_timeout: Duration::from_secs(60),
+        };
+
+        // A status handshake followed by far more status requests than are taken at once
+        let mut flood = handshake_frame(1);
+        for _ in 0..MAX_PACKETS_PER_RECEIVE * 2 {
+            // Length 1, Request packet id
+            flood.extend_from_slice(&[1, 0x00]);
+        }
+        let mut busy_stream = TcpStream::connect(addr).unwrap();
+        busy_stream.write_all(&flood).unwrap();
+        while server.handshaking_clients.is_empty() {
+            server.update();
+            thread::sleep(Duration::from_millis(10));
+        }
+        let mut quiet_stream = TcpStream::connect(addr).unwrap();
+        quiet_stream.write_all(&handshake_frame(1)).unwrap();
+        while server.handshaking_clients.len() < 2 {
+            server.update();
+            thread::sleep(Duration::from_millis(10));
+        }
+        // Give the reading threads time to decode everything
+        thread::sleep(Duration::from_millis(500));
+
+        // One update cycle, servicing each client once
+        let received: Vec<usize> = server
+            .handshaking_clients
+            .iter()
+            .map(|conn| conn.receive_packets().len())
+            .collect();
+        assert_eq!(received, vec![MAX_PACKETS_PER_RECEIVE, 1]);
+
+        // The rest of the flood is still there for later cycles
+        let busy = &server.handshaking_clients[0];
+        assert_eq!(busy.receive_packets().len(), MAX_PACKETS_PER_RECEIVE);
+        assert_eq!(busy.receive_packets().len(), 1);
+    }
+
+    #[test]
+    fn disabled_compression_never_negotiated() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sender, client_receiver) = mpsc::sync_channel(2);
+        thread::spawn(move || {
+            NetworkServer::listen(listener, 2097151, None, PacketLog::Off, sender)
+        });
+        let mut server = NetworkServer {
+            client_receiver,
+            handshaking_clients: Vec::new(),
+            login_timeout: Duration::from_secs(60),
+        };
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(&handshake_frame(2)).unwrap();
+        while server.handshaking_clients.is_empty() {
+            server.update();
+            thread::sleep(Duration::from_millis(10));
+        }
+        let conn = &server.handshaking_clients[0];
+        conn.enable_compression();
+        let pong = packets::clientbound::CPong { payload: 1 }.encode();
+        conn.send_packet(&pong);
+
+        // Only the pong arrives, without Set Compression or a Data Length field
+        let mut expected = Vec::new();
+        pong.write_uncompressed(&mut expected).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut received = vec![0; expected.len()];
+        stream.read_exact(&mut received).unwrap();
+        assert_eq!(received, expected);
+    }
+
+    /// Connects a client to `NetworkClient::listen`, sends `bytes`, and returns what
+    /// the first packet it produced was handled as
+    fn first_packet_handled_as(bytes: &[u8]) -> &'static str {
+        use packets::serverbound::{SHandshake, ServerBoundPacketHandler};
+
+        #[derive(Default)]
+        struct FirstPacket(&'static str);
+
+        impl ServerBoundPacketHandler for FirstPacket {
+            fn handle_handshake(&mut self, _packet: SHandshake, _player_idx: usize) {
+                self.0 = "handshake";
+            }
+            fn handle_legacy_ping(&mut self, _packet: SLegacyPing, _player_idx: usize) {
+                self.0 = "legacy_ping";
+            }
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (conn, _) = listener.accept().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let compressed = Arc::new(AtomicBool::new(false));
+        thread::spawn(move || {
+            NetworkClient::listen(
+                conn,
+                sender,
+                compressed,
+                2097151,
+                PacketLog::Off,
+                NetworkState::Handshake,
+            )
+        });
+
+        stream.write_all(bytes).unwrap();
+        let packet = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        let mut handled = FirstPacket::default();
+        packet.handle(&mut handled, 0);
+        handled.0
+    }
+
+    #[test]
+    fn first_byte_selects_handshake_parser() {
+        assert_eq!(first_packet_handled_as(&handshake_frame(1)), "handshake");
+        // The 1.6 ping, whose `0xFE 0x01` start could otherwise be read as a frame length
+        let mut legacy_ping = vec![0xFE, 0x01, 0xFA];
+        legacy_ping.extend_from_slice(&[0; 8]);
+        assert_eq!(first_packet_handled_as(&legacy_ping), "legacy_ping");
+    }
+
+    #[test]
+    fn status_flow_over_memory_transport() {
+        use packets::clientbound::{CPong, CResponse};
+        use packets::serverbound::{SHandshake, SPing, SRequest, ServerBoundPacketHandler};
+
+        #[derive(Default)]
+        struct StatusPackets(Vec<String>);
+
+        impl ServerBoundPacketHandler for StatusPackets {
+            fn handle_handshake(&mut self, handshake: SHandshake, _player_idx: usize) {
+                self.0.push(format!("handshake {}", handshake.next_state));
+            }
+            fn handle_request(&mut self, _request: SRequest, _player_idx: usize) {
+                self.0.push("request".to_owned());
+            }
+            fn handle_ping(&mut self, ping: SPing, _player_idx: usize) {
+                self.0.push(format!("ping {}", ping.payload));
+            }
+        }
+
+        let (server_end, mut client_end) = MemoryStream::pair();
+        let conn = HandshakingConn {
+            client: NetworkClient::new(
+                0,
+                server_end,
+                Some(256),
+                2097151,
+                PacketLog::Off,
+                NetworkState::Handshake,
+            )
+            .unwrap(),
+            username: None,
+            uuid: None,
+            connected_at: Instant::now(),
+        };
+
+        let mut ping = Vec::new();
+        ping.write_varint(0x01);
+        ping.write_long(42);
+        let mut sent = handshake_frame(1);
+        sent.extend_from_slice(&[0x01, 0x00]);
+        sent.write_varint(ping.len() as i32);
+        sent.extend_from_slice(&ping);
+        client_end.write_all_shared(&sent).unwrap();
+
+        let mut handled = StatusPackets::default();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while handled.0.len() < 3 && Instant::now() < deadline {
+            for packet in conn.receive_packets() {
+                packet.handle(&mut handled, 0);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(handled.0, ["handshake 1", "request", "ping 42"]);
+
+        let response = CResponse {
+            json_response: "{}".to_owned(),
+        }
+        .encode();
+        let pong = CPong { payload: 42 }.encode();
+        conn.send_packet(&response);
+        conn.send_packet(&pong);
+
+        let mut expected = Vec::new();
+        response.write_uncompressed(&mut expected).unwrap();
+        pong.write_uncompressed(&mut expected).unwrap();
+        let mut received = vec![0; expected.len()];
+        client_end.read_exact(&mut received).unwrap();
+        assert_eq!(received, expected);
+
+        conn.close_connection();
+        assert_eq!(client_end.read(&mut [0; 1]).unwrap(), 0);
+    }
+
+    /// A `MemoryStream` that counts how many times the client's reader thread read from it
+    #[derive(Debug, Clone)]
+    struct CountingStream {
+        stream: MemoryStream,
+        reads: Arc<AtomicUsize>,
+    }
+
+    impl Read for CountingStream {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.stream.read(out)
+        }
+    }
+
+    impl Transport for CountingStream {
+        fn write_all_shared(&self, data: &[u8]) -> io::Result<()> {
+            self.stream.write_all_shared(data)
+        }
+
+        fn try_clone(&self) -> io::Result<Self> {
+            Ok(self.clone())
+        }
+
+        fn shutdown(&self) -> io::Result<()> {
+            self.stream.shutdown()
+        }
+    }
+
+    #[test]
+    fn only_clients_with_data_are_read() {
+        // Returns the server end of a connection and its read counter, along with the client end
+        let connect = || {
+            let (server_end, client_end) = MemoryStream::pair();
+            let reads = Arc::new(AtomicUsize::new(0));
+            let stream = CountingStream {
+                stream: server_end,
+                reads: reads.clone(),
+            };
+            let client = NetworkClient::new(
+                0,
+                stream,
+                None,
+                2097151,
+                PacketLog::Off,
+                NetworkState::Handshake,
+            )
+            .unwrap();
+            (client, reads, client_end)
+        };
+        let wait_for_reads = |reads: &AtomicUsize, count: usize| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while reads.load(Ordering::SeqCst) < count && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+        };
+
+        let (idle, idle_reads, _idle_end) = connect();
+        let (active, active_reads, active_end) = connect();
+        // Both reader threads start out blocked on their first read
+        wait_for_reads(&idle_reads, 1);
+        wait_for_reads(&active_reads, 1);
+
+        active_end.write_all_shared(&handshake_frame(1)).unwrap();
+        let mut received = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while received.is_empty() && Instant::now() < deadline {
+            assert!(idle.receive_packets(&mut true).is_empty());
+            received = active.receive_packets(&mut true);
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(received.len(), 1);
+        for _ in 0..100 {
+            assert!(idle.receive_packets(&mut true).is_empty());
+        }
+
+        // The active client was read once more for its packet, and is now waiting again
+        wait_for_reads(&active_reads, 2);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(active_reads.load(Ordering::SeqCst), 2);
+        // No matter how many cycles ran, the idle client was never read again
+        assert_eq!(idle_reads.load(Ordering::SeqCst), 1);
     }
 }
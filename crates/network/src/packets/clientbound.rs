@@ -1,3 +1,4 @@
+use super::protocol::clientbound;
 use super::{PacketEncoder, PacketEncoderExt, PalettedContainer, SlotData};
 use crate::nbt_map::NBTMap;
 use bitvec::bits;
@@ -19,7 +20,34 @@ impl ClientBoundPacket for CResponse {
     fn encode(&self) -> PacketEncoder {
         let mut buf = Vec::new();
         buf.write_string(32767, &self.json_response);
-        PacketEncoder::new(buf, 0x00)
+        PacketEncoder::new(buf, clientbound::status::RESPONSE)
+    }
+}
+
+/// The response to a legacy (pre-netty) server list ping. Unlike every other packet,
+/// this is not length-prefixed, so it is written to the stream as-is.
+pub struct CLegacyStatusResponse {
+    pub protocol: i32,
+    pub version: String,
+    pub motd: String,
+    pub online_players: i64,
+    pub max_players: i64,
+}
+
+impl CLegacyStatusResponse {
+    pub fn encode(&self) -> Vec<u8> {
+        let status = format!(
+            "\u{a7}1\0{}\0{}\0{}\0{}\0{}",
+            self.protocol, self.version, self.motd, self.online_players, self.max_players
+        );
+        let chars: Vec<u16> = status.encode_utf16().collect();
+        // Kick packet
+        let mut buf = vec![0xFF];
+        buf.write_unsigned_short(chars.len() as u16);
+        for c in chars {
+            buf.write_unsigned_short(c);
+        }
+        buf
     }
 }
 
@@ -33,7 +61,7 @@ impl ClientBoundPacket for CDisconnectLogin {
     fn encode(&self) -> PacketEncoder {
         let mut buf = Vec::new();
         buf.write_string(32767, &self.reason);
-        PacketEncoder::new(buf, 0x00)
+        PacketEncoder::new(buf, clientbound::login::DISCONNECT)
     }
 }
 
@@ -45,7 +73,7 @@ impl ClientBoundPacket for CPong {
     fn encode(&self) -> PacketEncoder {
         let mut buf = Vec::new();
         buf.write_long(self.payload);
-        PacketEncoder::new(buf, 0x01)
+        PacketEncoder::new(buf, clientbound::status::PONG)
     }
 }
 
@@ -59,7 +87,7 @@ impl ClientBoundPacket for CLoginSuccess {
         let mut buf = Vec::new();
         buf.write_uuid(self.uuid);
         buf.write_string(16, &self.username);
-        PacketEncoder::new(buf, 0x02)
+        PacketEncoder::new(buf, clientbound::login::LOGIN_SUCCESS)
     }
 }
 
@@ -71,7 +99,7 @@ impl ClientBoundPacket for CSetCompression {
     fn encode(&self) -> PacketEncoder {
         let mut buf = Vec::new();
         buf.write_varint(self.threshold);
-        PacketEncoder::new(buf, 0x03)
+        PacketEncoder::new(buf, clientbound::login::SET_COMPRESSION)
     }
 }
 
@@ -105,7 +133,7 @@ impl ClientBoundPacket for CSpawnEntity {
         buf.write_short(self.velocity_x);
         buf.write_short(self.velocity_y);
         buf.write_short(self.velocity_z);
-        PacketEncoder::new(buf, 0x00)
+        PacketEncoder::new(buf, clientbound::play::SPAWN_ENTITY)
     }
 }
 
@@ -139,7 +167,7 @@ impl ClientBoundPacket for CSpawnLivingEntity {
         buf.write_short(self.velocity_x);
         buf.write_short(self.velocity_y);
         buf.write_short(self.velocity_z);
-        PacketEncoder::new(buf, 0x02)
+        PacketEncoder::new(buf, clientbound::play::SPAWN_LIVING_ENTITY)
     }
 }
 
@@ -163,7 +191,7 @@ impl ClientBoundPacket for CSpawnPlayer {
         buf.write_double(self.z);
         buf.write_byte(((self.yaw / 360f32 * 256f32) as i32 % 256) as i8);
         buf.write_byte(((self.pitch / 360f32 * 256f32) as i32 % 256) as i8);
-        PacketEncoder::new(buf, 0x04)
+        PacketEncoder::new(buf, clientbound::play::SPAWN_PLAYER)
     }
 }
 
@@ -179,7 +207,30 @@ impl ClientBoundPacket for CEntityAnimation {
         let mut buf = Vec::new();
         buf.write_varint(self.entity_id);
         buf.write_unsigned_byte(self.animation);
-        PacketEncoder::new(buf, 0x06)
+        PacketEncoder::new(buf, clientbound::play::ENTITY_ANIMATION)
+    }
+}
+
+pub struct CStatistic {
+    pub category_id: i32,
+    pub statistic_id: i32,
+    pub value: i32,
+}
+
+pub struct CStatistics {
+    pub statistics: Vec<CStatistic>,
+}
+
+impl ClientBoundPacket for CStatistics {
+    fn encode(&self) -> PacketEncoder {
+        let mut buf = Vec::new();
+        buf.write_varint(self.statistics.len() as i32);
+        for statistic in &self.statistics {
+            buf.write_varint(statistic.category_id);
+            buf.write_varint(statistic.statistic_id);
+            buf.write_varint(statistic.value);
+        }
+        PacketEncoder::new(buf, clientbound::play::STATISTICS)
     }
 }
 
@@ -197,7 +248,27 @@ impl ClientBoundPacket for CBlockEntityData {
         buf.write_position(self.x, self.y, self.z);
         buf.write_varint(self.ty);
         buf.write_nbt_blob(&self.nbt);
-        PacketEncoder::new(buf, 0x0A)
+        PacketEncoder::new(buf, clientbound::play::BLOCK_ENTITY_DATA)
+    }
+}
+
+pub struct CBlockAction {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub action_id: u8,
+    pub action_param: u8,
+    pub block_type: i32,
+}
+
+impl ClientBoundPacket for CBlockAction {
+    fn encode(&self) -> PacketEncoder {
+        let mut buf = Vec::new();
+        buf.write_position(self.x, self.y, self.z);
+        buf.write_unsigned_byte(self.action_id);
+        buf.write_unsigned_byte(self.action_param);
+        buf.write_varint(self.block_type);
+        PacketEncoder::new(buf, clientbound::play::BLOCK_ACTION)
     }
 }
 
@@ -213,7 +284,7 @@ impl ClientBoundPacket for CBlockChange {
         let mut buf = Vec::new();
         buf.write_position(self.x, self.y, self.z);
         buf.write_varint(self.block_id);
-        PacketEncoder::new(buf, 0x0C)
+        PacketEncoder::new(buf, clientbound::play::BLOCK_CHANGE)
     }
 }
 
@@ -229,7 +300,7 @@ impl ClientBoundPacket for CChatMessage {
         buf.write_string(32767, &self.message);
         buf.write_byte(self.position);
         buf.write_uuid(self.sender);
-        PacketEncoder::new(buf, 0x0F)
+        PacketEncoder::new(buf, clientbound::play::CHAT_MESSAGE)
     }
 }
 
@@ -260,7 +331,7 @@ impl ClientBoundPacket for CTabComplete {
             }
         }
 
-        PacketEncoder::new(buf, 0x11)
+        PacketEncoder::new(buf, clientbound::play::TAB_COMPLETE)
     }
 }
 
@@ -345,7 +416,7 @@ impl<'a> ClientBoundPacket for CDeclareCommands<'a> {
             }
         }
         buf.write_varint(self.root_index);
-        PacketEncoder::new(buf, 0x12)
+        PacketEncoder::new(buf, clientbound::play::DECLARE_COMMANDS)
     }
 }
 
@@ -366,7 +437,7 @@ impl ClientBoundPacket for CWindowItems {
             buf.write_slot_data(slot_data);
         }
         buf.write_slot_data(&self.carried_item);
-        PacketEncoder::new(buf, 0x14)
+        PacketEncoder::new(buf, clientbound::play::WINDOW_ITEMS)
     }
 }
 
@@ -384,7 +455,7 @@ impl ClientBoundPacket for CSetSlot {
         buf.write_varint(self.state_id);
         buf.write_short(self.slot);
         buf.write_slot_data(&self.slot_data);
-        PacketEncoder::new(buf, 0x16)
+        PacketEncoder::new(buf, clientbound::play::SET_SLOT)
     }
 }
 
@@ -398,7 +469,7 @@ impl ClientBoundPacket for CPluginMessage {
         let mut buf = Vec::new();
         buf.write_string(32767, &self.channel);
         buf.write_bytes(&self.data);
-        PacketEncoder::new(buf, 0x18)
+        PacketEncoder::new(buf, clientbound::play::PLUGIN_MESSAGE)
     }
 }
 
@@ -410,7 +481,7 @@ impl ClientBoundPacket for CDisconnect {
     fn encode(&self) -> PacketEncoder {
         let mut buf = Vec::new();
         buf.write_string(32767, &self.reason);
-        PacketEncoder::new(buf, 0x1A)
+        PacketEncoder::new(buf, clientbound::play::DISCONNECT)
     }
 }
 
@@ -425,7 +496,7 @@ impl ClientBoundPacket for CUnloadChunk {
         let mut buf = Vec::new();
         buf.write_int(self.chunk_x);
         buf.write_int(self.chunk_z);
-        PacketEncoder::new(buf, 0x1D)
+        PacketEncoder::new(buf, clientbound::play::UNLOAD_CHUNK)
     }
 }
 
@@ -445,7 +516,7 @@ impl ClientBoundPacket for CChangeGameState {
             CChangeGameStateReason::ChangeGamemode => buf.write_unsigned_byte(3),
         }
         buf.write_float(self.value);
-        PacketEncoder::new(buf, 0x1E)
+        PacketEncoder::new(buf, clientbound::play::CHANGE_GAME_STATE)
     }
 }
 
@@ -457,7 +528,7 @@ impl ClientBoundPacket for CKeepAlive {
     fn encode(&self) -> PacketEncoder {
         let mut buf = Vec::new();
         buf.write_long(self.id);
-        PacketEncoder::new(buf, 0x21)
+        PacketEncoder::new(buf, clientbound::play::KEEP_ALIVE)
     }
 }
 
@@ -558,7 +629,7 @@ impl ClientBoundPacket for CChunkData {
         // Block Light array count
         buf.write_varint(0);
 
-        PacketEncoder::new(buf, 0x22)
+        PacketEncoder::new(buf, clientbound::play::CHUNK_DATA)
     }
 }
 
@@ -578,7 +649,7 @@ impl ClientBoundPacket for CEffect {
         buf.write_position(self.x, self.y, self.z);
         buf.write_int(self.data);
         buf.write_bool(self.disable_relative_volume);
-        PacketEncoder::new(buf, 0x23)
+        PacketEncoder::new(buf, clientbound::play::EFFECT)
     }
 }
 
@@ -704,7 +775,7 @@ impl ClientBoundPacket for CJoinGame {
         buf.write_boolean(self.enable_respawn_screen);
         buf.write_boolean(self.is_debug);
         buf.write_boolean(self.is_flat);
-        PacketEncoder::new(buf, 0x26)
+        PacketEncoder::new(buf, clientbound::play::JOIN_GAME)
     }
 }
 
@@ -718,7 +789,7 @@ impl ClientBoundPacket for COpenSignEditor {
     fn encode(&self) -> PacketEncoder {
         let mut buf = Vec::new();
         buf.write_position(self.pos_x, self.pos_y, self.pos_z);
-        PacketEncoder::new(buf, 0x2F)
+        PacketEncoder::new(buf, clientbound::play::OPEN_SIGN_EDITOR)
     }
 }
 
@@ -738,7 +809,7 @@ impl ClientBoundPacket for CEntityPosition {
         buf.write_short(self.delta_y);
         buf.write_short(self.delta_z);
         buf.write_bool(self.on_ground);
-        PacketEncoder::new(buf, 0x29)
+        PacketEncoder::new(buf, clientbound::play::ENTITY_POSITION)
     }
 }
 
@@ -762,7 +833,7 @@ impl ClientBoundPacket for CEntityPositionAndRotation {
         buf.write_byte(((self.yaw / 360f32 * 256f32) as i32 % 256) as i8);
         buf.write_byte(((self.pitch / 360f32 * 256f32) as i32 % 256) as i8);
         buf.write_bool(self.on_ground);
-        PacketEncoder::new(buf, 0x2A)
+        PacketEncoder::new(buf, clientbound::play::ENTITY_POSITION_AND_ROTATION)
     }
 }
 
@@ -780,7 +851,7 @@ impl ClientBoundPacket for CEntityRotation {
         buf.write_byte(((self.yaw / 360f32 * 256f32) as i32 % 256) as i8);
         buf.write_byte(((self.pitch / 360f32 * 256f32) as i32 % 256) as i8);
         buf.write_bool(self.on_ground);
-        PacketEncoder::new(buf, 0x2B)
+        PacketEncoder::new(buf, clientbound::play::ENTITY_ROTATION)
     }
 }
 
@@ -796,7 +867,7 @@ impl ClientBoundPacket for COpenWindow {
         buf.write_varint(self.window_id);
         buf.write_varint(self.window_type);
         buf.write_string(32767, &self.window_title);
-        PacketEncoder::new(buf, 0x2E)
+        PacketEncoder::new(buf, clientbound::play::OPEN_WINDOW)
     }
 }
 
@@ -812,7 +883,7 @@ impl ClientBoundPacket for CPlayerAbilities {
         buf.write_unsigned_byte(self.flags);
         buf.write_float(self.fly_speed);
         buf.write_float(self.fov_modifier);
-        PacketEncoder::new(buf, 0x32)
+        PacketEncoder::new(buf, clientbound::play::PLAYER_ABILITIES)
     }
 }
 
@@ -878,7 +949,7 @@ impl ClientBoundPacket for CPlayerInfo {
                 }
             }
         }
-        PacketEncoder::new(buf, 0x36)
+        PacketEncoder::new(buf, clientbound::play::PLAYER_INFO)
     }
 }
 
@@ -904,7 +975,7 @@ impl ClientBoundPacket for CPlayerPositionAndLook {
         buf.write_unsigned_byte(self.flags);
         buf.write_varint(self.teleport_id);
         buf.write_bool(self.dismount_vehicle);
-        PacketEncoder::new(buf, 0x38)
+        PacketEncoder::new(buf, clientbound::play::PLAYER_POSITION_AND_LOOK)
     }
 }
 
@@ -919,7 +990,7 @@ impl ClientBoundPacket for CDestroyEntities {
         for &entity_id in &self.entity_ids {
             buf.write_varint(entity_id);
         }
-        PacketEncoder::new(buf, 0x3A)
+        PacketEncoder::new(buf, clientbound::play::DESTROY_ENTITIES)
     }
 }
 
@@ -933,7 +1004,7 @@ impl ClientBoundPacket for CEntityHeadLook {
         let mut buf = Vec::new();
         buf.write_varint(self.entity_id);
         buf.write_byte(((self.yaw / 360f32 * 256f32) as i32 % 256) as i8);
-        PacketEncoder::new(buf, 0x3E)
+        PacketEncoder::new(buf, clientbound::play::ENTITY_HEAD_LOOK)
     }
 }
 
@@ -970,7 +1041,23 @@ impl ClientBoundPacket for CMultiBlockChange {
             buf.write_varlong(long as i64);
         }
 
-        PacketEncoder::new(buf, 0x3F)
+        PacketEncoder::new(buf, clientbound::play::MULTI_BLOCK_CHANGE)
+    }
+}
+
+pub struct CSpawnPosition {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub angle: f32,
+}
+
+impl ClientBoundPacket for CSpawnPosition {
+    fn encode(&self) -> PacketEncoder {
+        let mut buf = Vec::new();
+        buf.write_position(self.x, self.y, self.z);
+        buf.write_float(self.angle);
+        PacketEncoder::new(buf, clientbound::play::SPAWN_POSITION)
     }
 }
 
@@ -982,7 +1069,7 @@ impl ClientBoundPacket for CHeldItemChange {
     fn encode(&self) -> PacketEncoder {
         let mut buf = Vec::new();
         buf.write_byte(self.slot);
-        PacketEncoder::new(buf, 0x48)
+        PacketEncoder::new(buf, clientbound::play::HELD_ITEM_CHANGE)
     }
 }
 
@@ -996,7 +1083,7 @@ impl ClientBoundPacket for CUpdateViewPosition {
         let mut buf = Vec::new();
         buf.write_varint(self.chunk_x);
         buf.write_varint(self.chunk_z);
-        PacketEncoder::new(buf, 0x49)
+        PacketEncoder::new(buf, clientbound::play::UPDATE_VIEW_POSITION)
     }
 }
 
@@ -1010,7 +1097,7 @@ impl ClientBoundPacket for CDisplayScoreboard {
         let mut buf = Vec::new();
         buf.write_byte(self.position as i8);
         buf.write_string(16, &self.score_name);
-        PacketEncoder::new(buf, 0x4C)
+        PacketEncoder::new(buf, clientbound::play::DISPLAY_SCOREBOARD)
     }
 }
 
@@ -1035,7 +1122,7 @@ impl ClientBoundPacket for CEntityMetadata {
             buf.write_bytes(&entry.value);
         }
         buf.write_byte(-1); // 0xFF
-        PacketEncoder::new(buf, 0x4D)
+        PacketEncoder::new(buf, clientbound::play::ENTITY_METADATA)
     }
 }
 
@@ -1058,7 +1145,7 @@ impl ClientBoundPacket for CEntityEquipment {
             buf.write_slot_data(&slot.item);
         }
 
-        PacketEncoder::new(buf, 0x50)
+        PacketEncoder::new(buf, clientbound::play::ENTITY_EQUIPMENT)
     }
 }
 
@@ -1078,7 +1165,23 @@ impl ClientBoundPacket for CScoreboardObjective {
             buf.write_string(32767, &self.objective_value);
             buf.write_varint(self.ty as i32);
         }
-        PacketEncoder::new(buf, 0x53)
+        PacketEncoder::new(buf, clientbound::play::SCOREBOARD_OBJECTIVE)
+    }
+}
+
+pub struct CUpdateHealth {
+    pub health: f32,
+    pub food: i32,
+    pub food_saturation: f32,
+}
+
+impl ClientBoundPacket for CUpdateHealth {
+    fn encode(&self) -> PacketEncoder {
+        let mut buf = Vec::new();
+        buf.write_float(self.health);
+        buf.write_varint(self.food);
+        buf.write_float(self.food_saturation);
+        PacketEncoder::new(buf, clientbound::play::UPDATE_HEALTH)
     }
 }
 
@@ -1098,7 +1201,7 @@ impl ClientBoundPacket for CUpdateScore {
         if self.action != 1 {
             buf.write_varint(self.value as i32);
         }
-        PacketEncoder::new(buf, 0x56)
+        PacketEncoder::new(buf, clientbound::play::UPDATE_SCORE)
     }
 }
 
@@ -1112,7 +1215,7 @@ impl ClientBoundPacket for CTimeUpdate {
         let mut buf = Vec::new();
         buf.write_long(self.world_age);
         buf.write_long(self.time_of_day);
-        PacketEncoder::new(buf, 0x59)
+        PacketEncoder::new(buf, clientbound::play::TIME_UPDATE)
     }
 }
 
@@ -1136,7 +1239,7 @@ impl ClientBoundPacket for CSoundEffect {
         buf.write_int(self.z);
         buf.write_float(self.volume);
         buf.write_float(self.pitch);
-        PacketEncoder::new(buf, 0x5D)
+        PacketEncoder::new(buf, clientbound::play::SOUND_EFFECT)
     }
 }
 
@@ -1160,6 +1263,6 @@ impl ClientBoundPacket for CEntityTeleport {
         buf.write_byte(((self.yaw / 360f32 * 256f32) as i32 % 256) as i8);
         buf.write_byte(((self.pitch / 360f32 * 256f32) as i32 % 256) as i8);
         buf.write_bool(self.on_ground);
-        PacketEncoder::new(buf, 0x62)
+        PacketEncoder::new(buf, clientbound::play::ENTITY_TELEPORT)
     }
 }
@@ -1,4 +1,5 @@
 pub mod clientbound;
+pub mod protocol;
 pub mod serverbound;
 
 use super::NetworkState;
@@ -12,6 +13,7 @@ use std::io::{self, Cursor, Read, Write};
 use std::net::TcpStream;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use tracing::trace;
 
 #[derive(Debug)]
 pub struct SlotData {
@@ -27,6 +29,13 @@ pub struct PalettedContainer {
     pub data_array: Vec<u64>,
 }
 
+/// The largest packet length the vanilla server will accept. This is the
+/// largest number that can be encoded in a 3 byte VarInt.
+pub const DEFAULT_MAX_PACKET_LENGTH: usize = (1 << 21) - 1;
+
+/// The first byte sent by a client performing a legacy server list ping
+pub const LEGACY_PING_ID: u8 = 0xFE;
+
 pub type DecodeResult<T> = std::result::Result<T, PacketDecodeError>;
 
 #[derive(Debug)]
@@ -34,6 +43,17 @@ pub enum PacketDecodeError {
     Io(io::Error),
     FromUtf8(std::string::FromUtf8Error),
     Nbt(nbt::Error),
+    /// The declared length of a frame was negative
+    InvalidLength(i32),
+    /// The declared length of a frame was larger than the maximum packet length
+    PacketTooLarge(usize),
+    /// A length prefix was longer than the 5 bytes a VarInt may take up
+    VarIntTooBig,
+    /// A Client Status packet had an action other than respawn or stats
+    UnknownClientStatusAction(i32),
+    UnknownEntityAction(i32),
+    /// A chat message or command was longer than `MAX_CHAT_MESSAGE_LENGTH` characters
+    ChatMessageTooLong(usize),
 }
 
 impl From<nbt::Error> for PacketDecodeError {
@@ -57,20 +77,39 @@ impl From<std::string::FromUtf8Error> for PacketDecodeError {
 #[derive(Debug)]
 pub enum PacketEncodeError {}
 
+/// Validates a length prefix before anything gets allocated for it
+fn check_length(length: i32, max_length: usize) -> DecodeResult<usize> {
+    if length < 0 {
+        return Err(PacketDecodeError::InvalidLength(length));
+    }
+    let length = length as usize;
+    if length > max_length {
+        return Err(PacketDecodeError::PacketTooLarge(length));
+    }
+    Ok(length)
+}
+
+/// Reads the rest of a compressed frame and returns the packet id and payload
 fn read_compressed<T: PacketDecoderExt>(
     reader: &mut T,
-    network_state: &mut NetworkState,
-) -> DecodeResult<Box<dyn ServerBoundPacket>> {
-    let decompressed_length = reader.read_varint()? as usize;
+    max_length: usize,
+) -> DecodeResult<Vec<u8>> {
+    let decompressed_length = check_length(reader.read_varint()?, max_length)?;
     let data = PacketDecoderExt::read_to_end(reader)?;
     // `data` is not compressed if `decompressed_length` is 0
     if decompressed_length == 0 {
-        read_decompressed(&mut Cursor::new(data), network_state)
+        Ok(data)
     } else {
-        let mut decompresser = ZlibDecoder::new(data.as_slice());
+        let decompresser = ZlibDecoder::new(data.as_slice());
         let mut decompressed_data = Vec::with_capacity(decompressed_length);
-        decompresser.read_to_end(&mut decompressed_data)?;
-        read_decompressed(&mut Cursor::new(decompressed_data), network_state)
+        // Don't trust the declared length, a zip bomb could decompress to far more
+        decompresser
+            .take(max_length as u64 + 1)
+            .read_to_end(&mut decompressed_data)?;
+        if decompressed_data.len() > max_length {
+            return Err(PacketDecodeError::PacketTooLarge(decompressed_data.len()));
+        }
+        Ok(decompressed_data)
     }
 }
 
@@ -78,58 +117,186 @@ fn read_decompressed<T: PacketDecoderExt>(
     reader: &mut T,
     state: &mut NetworkState,
 ) -> DecodeResult<Box<dyn ServerBoundPacket>> {
+    use protocol::serverbound as ids;
+    use NetworkState::{Handshake, Login, Play, Status};
+
     let packet_id = reader.read_varint()?;
-    Ok(match *state {
-        NetworkState::Handshake if packet_id == 0x00 => {
+    Ok(match (state.clone(), packet_id) {
+        (Handshake, ids::handshake::HANDSHAKE) => {
             let handshake = SHandshake::decode(reader)?;
             match handshake.next_state {
-                1 => *state = NetworkState::Status,
-                2 => *state = NetworkState::Login,
+                1 => *state = Status,
+                2 => *state = Login,
                 _ => {}
             }
             Box::new(handshake)
         }
-        NetworkState::Status if packet_id == 0x00 => Box::new(SRequest::decode(reader)?),
-        NetworkState::Status if packet_id == 0x01 => Box::new(SPing::decode(reader)?),
-        NetworkState::Login if packet_id == 0x00 => {
-            *state = NetworkState::Play;
+        (Status, ids::status::REQUEST) => Box::new(SRequest::decode(reader)?),
+        (Status, ids::status::PING) => Box::new(SPing::decode(reader)?),
+        (Login, ids::login::LOGIN_START) => {
+            *state = Play;
             Box::new(SLoginStart::decode(reader)?)
         }
-        _ => match packet_id {
-            0x03 => Box::new(SChatMessage::decode(reader)?),
-            0x05 => Box::new(SClientSettings::decode(reader)?),
-            0x06 => Box::new(STabComplete::decode(reader)?),
-            0x0A => Box::new(SPluginMessage::decode(reader)?),
-            0x0F => Box::new(SKeepAlive::decode(reader)?),
-            0x11 => Box::new(SPlayerPosition::decode(reader)?),
-            0x12 => Box::new(SPlayerPositionAndRotation::decode(reader)?),
-            0x13 => Box::new(SPlayerRotation::decode(reader)?),
-            0x14 => Box::new(SPlayerMovement::decode(reader)?),
-            0x19 => Box::new(SPlayerAbilities::decode(reader)?),
-            0x1A => Box::new(SPlayerDigging::decode(reader)?),
-            0x1B => Box::new(SEntityAction::decode(reader)?),
-            0x25 => Box::new(SHeldItemChange::decode(reader)?),
-            0x28 => Box::new(SCreativeInventoryAction::decode(reader)?),
-            0x2B => Box::new(SUpdateSign::decode(reader)?),
-            0x2C => Box::new(SAnimation::decode(reader)?),
-            0x2E => Box::new(SPlayerBlockPlacemnt::decode(reader)?),
-            _ => Box::new(SUnknown),
-        },
+        (Play, ids::play::TELEPORT_CONFIRM) => Box::new(STeleportConfirm::decode(reader)?),
+        (Play, ids::play::CHAT_MESSAGE) => Box::new(SChatMessage::decode(reader)?),
+        (Play, ids::play::CLIENT_STATUS) => Box::new(SClientStatus::decode(reader)?),
+        (Play, ids::play::CLIENT_SETTINGS) => Box::new(SClientSettings::decode(reader)?),
+        (Play, ids::play::TAB_COMPLETE) => Box::new(STabComplete::decode(reader)?),
+        (Play, ids::play::CLOSE_WINDOW) => Box::new(SCloseWindow::decode(reader)?),
+        (Play, ids::play::PLUGIN_MESSAGE) => Box::new(SPluginMessage::decode(reader)?),
+        (Play, ids::play::KEEP_ALIVE) => Box::new(SKeepAlive::decode(reader)?),
+        (Play, ids::play::PLAYER_POSITION) => Box::new(SPlayerPosition::decode(reader)?),
+        (Play, ids::play::PLAYER_POSITION_AND_ROTATION) => {
+            Box::new(SPlayerPositionAndRotation::decode(reader)?)
+        }
+        (Play, ids::play::PLAYER_ROTATION) => Box::new(SPlayerRotation::decode(reader)?),
+        (Play, ids::play::PLAYER_MOVEMENT) => Box::new(SPlayerMovement::decode(reader)?),
+        (Play, ids::play::PLAYER_ABILITIES) => Box::new(SPlayerAbilities::decode(reader)?),
+        (Play, ids::play::PLAYER_DIGGING) => Box::new(SPlayerDigging::decode(reader)?),
+        (Play, ids::play::ENTITY_ACTION) => Box::new(SEntityAction::decode(reader)?),
+        (Play, ids::play::HELD_ITEM_CHANGE) => Box::new(SHeldItemChange::decode(reader)?),
+        (Play, ids::play::CREATIVE_INVENTORY_ACTION) => {
+            Box::new(SCreativeInventoryAction::decode(reader)?)
+        }
+        (Play, ids::play::UPDATE_SIGN) => Box::new(SUpdateSign::decode(reader)?),
+        (Play, ids::play::ANIMATION) => Box::new(SAnimation::decode(reader)?),
+        (Play, ids::play::PLAYER_BLOCK_PLACEMENT) => {
+            Box::new(SPlayerBlockPlacemnt::decode(reader)?)
+        }
+        (Play, ids::play::USE_ITEM) => Box::new(SUseItem::decode(reader)?),
+        (state, packet_id) => {
+            trace!(
+                "Skipping unknown packet {:#04x} in the {:?} state",
+                packet_id,
+                state
+            );
+            Box::new(SUnknown)
+        }
     })
 }
 
-pub fn read_packet<T: PacketDecoderExt>(
-    reader: &mut T,
+/// Decodes the body of a frame, without its length prefix, into a packet.
+pub fn decode_frame(
+    frame: Vec<u8>,
     compressed: &Arc<AtomicBool>,
     network_state: &mut NetworkState,
+    max_length: usize,
 ) -> DecodeResult<Box<dyn ServerBoundPacket>> {
-    let length = reader.read_varint()?;
-    let data = reader.read_bytes(length as usize)?;
-    let mut cursor = Cursor::new(data);
+    let data = frame_packet_data(frame, compressed, max_length)?;
+    decode_packet_data(data, network_state)
+}
+
+/// Returns the packet id and payload held in the body of a frame, decompressing it if needed.
+pub fn frame_packet_data(
+    frame: Vec<u8>,
+    compressed: &Arc<AtomicBool>,
+    max_length: usize,
+) -> DecodeResult<Vec<u8>> {
     if compressed.load(Ordering::Relaxed) {
-        read_compressed(&mut cursor, network_state)
+        read_compressed(&mut Cursor::new(frame), max_length)
     } else {
-        read_decompressed(&mut cursor, network_state)
+        Ok(frame)
+    }
+}
+
+/// Decodes a packet id and payload, as returned by `frame_packet_data`, into a packet.
+pub fn decode_packet_data(
+    data: Vec<u8>,
+    network_state: &mut NetworkState,
+) -> DecodeResult<Box<dyn ServerBoundPacket>> {
+    read_decompressed(&mut Cursor::new(data), network_state)
+}
+
+/// Describes a packet id and payload received in `state` for the packet log.
+/// The payload is hex-dumped if `payload` is set.
+pub fn packet_log_line(state: &NetworkState, data: &[u8], payload: bool) -> String {
+    let mut reader = Cursor::new(data);
+    let Ok(packet_id) = reader.read_varint() else {
+        return format!(
+            "Received {:?} packet with no id ({} bytes)",
+            state,
+            data.len()
+        );
+    };
+    let mut line = format!(
+        "Received {:?} packet {:#04x} ({} bytes)",
+        state,
+        packet_id,
+        data.len()
+    );
+    if payload {
+        line.push(':');
+        for byte in &data[reader.position() as usize..] {
+            line.push_str(&format!(" {:02x}", byte));
+        }
+    }
+    line
+}
+
+/// How a new connection starts, decided by its very first byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandshakeKind {
+    /// A length-prefixed handshake frame
+    Modern,
+    /// A pre-netty server list ping
+    LegacyPing,
+}
+
+/// Accumulates bytes as they come off the stream and splits them into
+/// complete length-prefixed frames. A frame split across multiple reads
+/// stays buffered until the rest of it arrives.
+#[derive(Default)]
+pub struct FrameBuffer {
+    buf: Vec<u8>,
+}
+
+impl FrameBuffer {
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Peeks at the first buffered byte to decide how a new connection is parsed.
+    /// Legacy server list pings start with `0xFE` instead of a length prefix, and
+    /// no modern handshake is long enough to start with that byte.
+    /// Returns `None` if nothing has been received yet.
+    pub fn peek_handshake(&self) -> Option<HandshakeKind> {
+        match self.buf.first()? {
+            &LEGACY_PING_ID => Some(HandshakeKind::LegacyPing),
+            _ => Some(HandshakeKind::Modern),
+        }
+    }
+
+    /// The number of buffered bytes that aren't part of a frame returned yet
+    pub fn pending_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the body of the next complete frame, or `None` if more bytes are needed.
+    /// A single read often holds several frames, so this should be called until it
+    /// returns `None`.
+    pub fn next_frame(&mut self, max_length: usize) -> DecodeResult<Option<Vec<u8>>> {
+        let mut length = 0i32;
+        let mut header_len = None;
+        for (i, &byte) in self.buf.iter().enumerate().take(5) {
+            length |= ((byte & 0b0111_1111) as i32) << (7 * i);
+            if byte & 0b1000_0000 == 0 {
+                header_len = Some(i + 1);
+                break;
+            }
+        }
+        let header_len = match header_len {
+            Some(header_len) => header_len,
+            None if self.buf.len() >= 5 => return Err(PacketDecodeError::VarIntTooBig),
+            None => return Ok(None),
+        };
+
+        let length = check_length(length, max_length)?;
+        if self.buf.len() < header_len + length {
+            return Ok(None);
+        }
+        let frame = self.buf[header_len..header_len + length].to_vec();
+        self.buf.drain(..header_len + length);
+        Ok(Some(frame))
     }
 }
 
@@ -390,11 +557,11 @@ impl PacketEncoder {
         }
     }
 
-    pub fn write_compressed(&self, mut w: impl Write) -> io::Result<()> {
+    pub fn write_compressed(&self, mut w: impl Write, threshold: usize) -> io::Result<()> {
         // TODO: zero allocation
         let packet_id = PacketEncoder::varint(self.packet_id as i32);
         let data = [packet_id.as_slice(), self.buffer.as_slice()].concat();
-        if self.buffer.len() < 256 {
+        if self.buffer.len() < threshold {
             // Data Length adds another byte
             let packet_length = PacketEncoder::varint((1 + data.len()) as i32);
 
@@ -440,3 +607,299 @@ impl PacketEncoder {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_frame_rejected() {
+        // Declares a length of i32::MAX followed by no data at all. If the
+        // length were trusted, this would try to allocate 2 GiB.
+        let mut frame = PacketEncoder::varint(i32::MAX);
+        frame.extend_from_slice(&[0x00; 4]);
+        let mut buffer = FrameBuffer::default();
+        buffer.extend(&frame);
+        let res = buffer.next_frame(DEFAULT_MAX_PACKET_LENGTH);
+        assert!(
+            matches!(res, Err(PacketDecodeError::PacketTooLarge(len)) if len == i32::MAX as usize)
+        );
+    }
+
+    #[test]
+    fn negative_length_rejected() {
+        let mut buffer = FrameBuffer::default();
+        buffer.extend(&PacketEncoder::varint(-1));
+        let res = buffer.next_frame(DEFAULT_MAX_PACKET_LENGTH);
+        assert!(matches!(res, Err(PacketDecodeError::InvalidLength(-1))));
+
+        // The decompressed length inside a compressed frame is checked the same way
+        let frame = PacketEncoder::varint(-1);
+        let compressed = Arc::new(AtomicBool::new(true));
+        let mut state = NetworkState::Handshake;
+        let res = decode_frame(frame, &compressed, &mut state, DEFAULT_MAX_PACKET_LENGTH);
+        assert!(matches!(res, Err(PacketDecodeError::InvalidLength(-1))));
+    }
+
+    fn uncompressed_frame(packet_id: i32, body: &[u8]) -> Vec<u8> {
+        let mut data = PacketEncoder::varint(packet_id);
+        data.extend_from_slice(body);
+        let mut frame = PacketEncoder::varint(data.len() as i32);
+        frame.extend_from_slice(&data);
+        frame
+    }
+
+    #[test]
+    fn decoded_packet_logged() {
+        let mut frame = uncompressed_frame(0x04, &[0x01]);
+        frame.remove(0);
+        let compressed = Arc::new(AtomicBool::new(false));
+        let data = frame_packet_data(frame, &compressed, DEFAULT_MAX_PACKET_LENGTH).unwrap();
+        assert_eq!(
+            packet_log_line(&NetworkState::Play, &data, false),
+            "Received Play packet 0x04 (2 bytes)"
+        );
+        assert_eq!(
+            packet_log_line(&NetworkState::Play, &data, true),
+            "Received Play packet 0x04 (2 bytes): 01"
+        );
+
+        let mut state = NetworkState::Play;
+        assert!(decode_packet_data(data, &mut state).is_ok());
+    }
+
+    #[test]
+    fn client_status_decoded_in_play() {
+        let mut frame = uncompressed_frame(0x04, &[0x00]);
+        let length = frame.remove(0) as usize;
+        assert_eq!(length, frame.len());
+        let compressed = Arc::new(AtomicBool::new(false));
+        let mut state = NetworkState::Play;
+        let packet = decode_frame(frame, &compressed, &mut state, DEFAULT_MAX_PACKET_LENGTH);
+        assert!(packet.is_ok());
+
+        let status = SClientStatus::decode(&mut Cursor::new(vec![0x00])).unwrap();
+        assert_eq!(status.action, ClientStatusAction::PerformRespawn);
+        let status = SClientStatus::decode(&mut Cursor::new(vec![0x01])).unwrap();
+        assert_eq!(status.action, ClientStatusAction::RequestStats);
+        assert!(matches!(
+            SClientStatus::decode(&mut Cursor::new(vec![0x02])),
+            Err(PacketDecodeError::UnknownClientStatusAction(2))
+        ));
+    }
+
+    #[derive(Default)]
+    struct HandledPackets(Vec<&'static str>);
+
+    impl ServerBoundPacketHandler for HandledPackets {
+        fn handle_handshake(&mut self, _packet: SHandshake, _player_idx: usize) {
+            self.0.push("handshake");
+        }
+        fn handle_ping(&mut self, _packet: SPing, _player_idx: usize) {
+            self.0.push("ping");
+        }
+        fn handle_request(&mut self, _packet: SRequest, _player_idx: usize) {
+            self.0.push("request");
+        }
+        fn handle_login_start(&mut self, _packet: SLoginStart, _player_idx: usize) {
+            self.0.push("login_start");
+        }
+        fn handle_chat_message(&mut self, _packet: SChatMessage, _player_idx: usize) {
+            self.0.push("chat_message");
+        }
+        fn handle_unknown(&mut self, _packet: SUnknown, _player_idx: usize) {
+            self.0.push("unknown");
+        }
+    }
+
+    #[test]
+    fn packets_routed_by_state_and_id() {
+        let compressed = Arc::new(AtomicBool::new(false));
+        let handle = |state: NetworkState, packet_id: i32, body: &[u8]| {
+            let mut state = state;
+            let mut data = PacketEncoder::varint(packet_id);
+            data.extend_from_slice(body);
+            let packet =
+                decode_frame(data, &compressed, &mut state, DEFAULT_MAX_PACKET_LENGTH).unwrap();
+            let mut handled = HandledPackets::default();
+            packet.handle(&mut handled, 0);
+            (handled.0, state)
+        };
+        let chat = [0x02, b'h', b'i'];
+
+        // The same id means different packets in different states
+        assert_eq!(
+            handle(NetworkState::Status, 0x00, &[]),
+            (vec!["request"], NetworkState::Status)
+        );
+        assert_eq!(
+            handle(NetworkState::Login, 0x00, &chat),
+            (vec!["login_start"], NetworkState::Play)
+        );
+        assert_eq!(
+            handle(NetworkState::Play, 0x03, &chat),
+            (vec!["chat_message"], NetworkState::Play)
+        );
+        // Play packets are not accepted before the client has logged in
+        assert_eq!(
+            handle(NetworkState::Login, 0x03, &chat),
+            (vec!["unknown"], NetworkState::Login)
+        );
+        assert_eq!(
+            handle(NetworkState::Play, 0x7F, &[]),
+            (vec!["unknown"], NetworkState::Play)
+        );
+    }
+
+    #[test]
+    fn off_hand_use_decoded() {
+        let use_item = SUseItem::decode(&mut Cursor::new(vec![0x01])).unwrap();
+        assert_eq!(use_item.hand, 1);
+
+        let mut placement = vec![0x01];
+        placement.write_position(1, 64, -1);
+        placement.write_varint(1); // Top face
+        placement.write_float(0.5);
+        placement.write_float(1.0);
+        placement.write_float(0.5);
+        placement.write_bool(false);
+        let placement = SPlayerBlockPlacemnt::decode(&mut Cursor::new(placement)).unwrap();
+        assert_eq!(placement.hand, 1);
+        assert_eq!((placement.x, placement.y, placement.z), (1, 64, -1));
+        assert_eq!(placement.face, 1);
+    }
+
+    #[test]
+    fn oversized_chat_message_rejected() {
+        let chat_message = |message: &str| {
+            let mut data = Vec::new();
+            data.write_string(32767, message);
+            SChatMessage::decode(&mut Cursor::new(data))
+        };
+        let message = "a".repeat(300);
+        assert!(matches!(
+            chat_message(&message),
+            Err(PacketDecodeError::ChatMessageTooLong(300))
+        ));
+        let message = "a".repeat(MAX_CHAT_MESSAGE_LENGTH);
+        assert_eq!(chat_message(&message).unwrap().message, message);
+    }
+
+    #[test]
+    fn close_window_decoded() {
+        let close_window = SCloseWindow::decode(&mut Cursor::new(vec![0x05])).unwrap();
+        assert_eq!(close_window.window_id, 5);
+    }
+
+    #[test]
+    fn legacy_ping_response() {
+        let mut buffer = FrameBuffer::default();
+        // The 1.4-1.5 ping: `0xFE 0x01`
+        buffer.extend(&[0xFE, 0x01]);
+        assert_eq!(buffer.peek_handshake(), Some(HandshakeKind::LegacyPing));
+
+        let response = clientbound::CLegacyStatusResponse {
+            protocol: 127,
+            version: "1.18.2".to_owned(),
+            motd: "A".to_owned(),
+            online_players: 1,
+            max_players: 20,
+        }
+        .encode();
+        let expected = "\u{a7}1\0127\01.18.2\0A\01\020";
+        let mut expected_bytes = vec![0xFF, 0x00, expected.encode_utf16().count() as u8];
+        for c in expected.encode_utf16() {
+            expected_bytes.extend_from_slice(&c.to_be_bytes());
+        }
+        assert_eq!(response, expected_bytes);
+
+        let mut modern = FrameBuffer::default();
+        assert_eq!(modern.peek_handshake(), None);
+        modern.extend(&[0x10, 0x00]);
+        assert_eq!(modern.peek_handshake(), Some(HandshakeKind::Modern));
+    }
+
+    #[test]
+    fn frames_split_across_reads() {
+        let mut handshake = Vec::new();
+        handshake.write_varint(758);
+        handshake.write_string(255, "localhost");
+        handshake.write_unsigned_short(25565);
+        handshake.write_varint(1);
+
+        let mut stream = uncompressed_frame(0x00, &handshake);
+        stream.extend(uncompressed_frame(0x00, &[]));
+
+        let mut buffer = FrameBuffer::default();
+        let mut frames = Vec::new();
+        for byte in &stream {
+            buffer.extend(&[*byte]);
+            while let Some(frame) = buffer.next_frame(DEFAULT_MAX_PACKET_LENGTH).unwrap() {
+                frames.push(frame);
+            }
+        }
+        assert_eq!(frames.len(), 2);
+        assert!(buffer.buf.is_empty());
+
+        let compressed = Arc::new(AtomicBool::new(false));
+        let mut state = NetworkState::Handshake;
+        let mut frames = frames.into_iter();
+        decode_frame(
+            frames.next().unwrap(),
+            &compressed,
+            &mut state,
+            DEFAULT_MAX_PACKET_LENGTH,
+        )
+        .unwrap();
+        assert!(state == NetworkState::Status);
+        decode_frame(
+            frames.next().unwrap(),
+            &compressed,
+            &mut state,
+            DEFAULT_MAX_PACKET_LENGTH,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn concatenated_frames_in_one_read() {
+        let mut handshake = Vec::new();
+        handshake.write_varint(758);
+        handshake.write_string(255, "localhost");
+        handshake.write_unsigned_short(25565);
+        handshake.write_varint(1);
+        let mut ping = Vec::new();
+        ping.write_long(0x0102030405060708);
+
+        let mut stream = uncompressed_frame(0x00, &handshake);
+        stream.extend(uncompressed_frame(0x00, &[]));
+        stream.extend(uncompressed_frame(0x01, &ping));
+        let next_frame = uncompressed_frame(0x00, &[]);
+        stream.push(next_frame[0]);
+
+        let mut buffer = FrameBuffer::default();
+        buffer.extend(&stream);
+        let mut frames = Vec::new();
+        while let Some(frame) = buffer.next_frame(DEFAULT_MAX_PACKET_LENGTH).unwrap() {
+            frames.push(frame);
+        }
+        assert_eq!(frames.len(), 3);
+        // The start of the next frame is kept for the next read
+        assert_eq!(buffer.pending_len(), 1);
+        assert_eq!(
+            frames.iter().map(|frame| frame.len() + 1).sum::<usize>() + buffer.pending_len(),
+            stream.len()
+        );
+
+        let compressed = Arc::new(AtomicBool::new(false));
+        let mut state = NetworkState::Handshake;
+        let mut handled = HandledPackets::default();
+        for frame in frames {
+            decode_frame(frame, &compressed, &mut state, DEFAULT_MAX_PACKET_LENGTH)
+                .unwrap()
+                .handle(&mut handled, 0);
+        }
+        assert_eq!(handled.0, ["handshake", "request", "ping"]);
+        assert!(state == NetworkState::Status);
+    }
+}
@@ -0,0 +1,117 @@
+//! Everything that depends on the version of the protocol being spoken.
+//!
+//! Each supported version gets its own module with its version numbers and packet ids.
+//! Only one version is supported right now, and it is re-exported at the top of this module.
+
+pub use v1_18_2::*;
+
+pub mod v1_18_2 {
+    pub const MC_VERSION: &str = "1.18.2";
+    pub const PROTOCOL_VERSION: i32 = 758;
+
+    /// Ids of packets sent by the server, grouped by the state they are sent in
+    pub mod clientbound {
+        pub mod status {
+            pub const RESPONSE: u32 = 0x00;
+            pub const PONG: u32 = 0x01;
+        }
+
+        pub mod login {
+            pub const DISCONNECT: u32 = 0x00;
+            pub const LOGIN_SUCCESS: u32 = 0x02;
+            pub const SET_COMPRESSION: u32 = 0x03;
+        }
+
+        pub mod play {
+            pub const SPAWN_ENTITY: u32 = 0x00;
+            pub const SPAWN_LIVING_ENTITY: u32 = 0x02;
+            pub const SPAWN_PLAYER: u32 = 0x04;
+            pub const ENTITY_ANIMATION: u32 = 0x06;
+            pub const STATISTICS: u32 = 0x07;
+            pub const BLOCK_ENTITY_DATA: u32 = 0x0A;
+            pub const BLOCK_ACTION: u32 = 0x0B;
+            pub const BLOCK_CHANGE: u32 = 0x0C;
+            pub const CHAT_MESSAGE: u32 = 0x0F;
+            pub const TAB_COMPLETE: u32 = 0x11;
+            pub const DECLARE_COMMANDS: u32 = 0x12;
+            pub const WINDOW_ITEMS: u32 = 0x14;
+            pub const SET_SLOT: u32 = 0x16;
+            pub const PLUGIN_MESSAGE: u32 = 0x18;
+            pub const DISCONNECT: u32 = 0x1A;
+            pub const UNLOAD_CHUNK: u32 = 0x1D;
+            pub const CHANGE_GAME_STATE: u32 = 0x1E;
+            pub const KEEP_ALIVE: u32 = 0x21;
+            pub const CHUNK_DATA: u32 = 0x22;
+            pub const EFFECT: u32 = 0x23;
+            pub const JOIN_GAME: u32 = 0x26;
+            pub const ENTITY_POSITION: u32 = 0x29;
+            pub const ENTITY_POSITION_AND_ROTATION: u32 = 0x2A;
+            pub const ENTITY_ROTATION: u32 = 0x2B;
+            pub const OPEN_WINDOW: u32 = 0x2E;
+            pub const OPEN_SIGN_EDITOR: u32 = 0x2F;
+            pub const PLAYER_ABILITIES: u32 = 0x32;
+            pub const PLAYER_INFO: u32 = 0x36;
+            pub const PLAYER_POSITION_AND_LOOK: u32 = 0x38;
+            pub const DESTROY_ENTITIES: u32 = 0x3A;
+            pub const ENTITY_HEAD_LOOK: u32 = 0x3E;
+            pub const MULTI_BLOCK_CHANGE: u32 = 0x3F;
+            pub const HELD_ITEM_CHANGE: u32 = 0x48;
+            pub const UPDATE_VIEW_POSITION: u32 = 0x49;
+            pub const SPAWN_POSITION: u32 = 0x4B;
+            pub const DISPLAY_SCOREBOARD: u32 = 0x4C;
+            pub const ENTITY_METADATA: u32 = 0x4D;
+            pub const ENTITY_EQUIPMENT: u32 = 0x50;
+            pub const UPDATE_HEALTH: u32 = 0x52;
+            pub const SCOREBOARD_OBJECTIVE: u32 = 0x53;
+            pub const UPDATE_SCORE: u32 = 0x56;
+            pub const TIME_UPDATE: u32 = 0x59;
+            pub const SOUND_EFFECT: u32 = 0x5D;
+            pub const ENTITY_TELEPORT: u32 = 0x62;
+        }
+    }
+
+    /// Ids of packets sent by the client, grouped by the state they are sent in
+    pub mod serverbound {
+        pub mod handshake {
+            pub const HANDSHAKE: i32 = 0x00;
+        }
+
+        pub mod status {
+            pub const REQUEST: i32 = 0x00;
+            pub const PING: i32 = 0x01;
+        }
+
+        pub mod login {
+            pub const LOGIN_START: i32 = 0x00;
+        }
+
+        pub mod play {
+            pub const TELEPORT_CONFIRM: i32 = 0x00;
+            pub const CHAT_MESSAGE: i32 = 0x03;
+            pub const CLIENT_STATUS: i32 = 0x04;
+            pub const CLIENT_SETTINGS: i32 = 0x05;
+            pub const TAB_COMPLETE: i32 = 0x06;
+            pub const CLOSE_WINDOW: i32 = 0x09;
+            pub const PLUGIN_MESSAGE: i32 = 0x0A;
+            pub const KEEP_ALIVE: i32 = 0x0F;
+            pub const PLAYER_POSITION: i32 = 0x11;
+            pub const PLAYER_POSITION_AND_ROTATION: i32 = 0x12;
+            pub const PLAYER_ROTATION: i32 = 0x13;
+            pub const PLAYER_MOVEMENT: i32 = 0x14;
+            pub const PLAYER_ABILITIES: i32 = 0x19;
+            pub const PLAYER_DIGGING: i32 = 0x1A;
+            pub const ENTITY_ACTION: i32 = 0x1B;
+            pub const HELD_ITEM_CHANGE: i32 = 0x25;
+            pub const CREATIVE_INVENTORY_ACTION: i32 = 0x28;
+            pub const UPDATE_SIGN: i32 = 0x2B;
+            pub const ANIMATION: i32 = 0x2C;
+            pub const PLAYER_BLOCK_PLACEMENT: i32 = 0x2E;
+            pub const USE_ITEM: i32 = 0x2F;
+        }
+    }
+}
+
+/// Returns true if clients using `protocol_version` are able to play on this server
+pub fn is_supported(protocol_version: i32) -> bool {
+    protocol_version == PROTOCOL_VERSION
+}
@@ -1,12 +1,16 @@
-use super::{DecodeResult, PacketDecoderExt, SlotData};
+use super::{DecodeResult, PacketDecodeError, PacketDecoderExt, SlotData};
 
 pub trait ServerBoundPacketHandler {
     fn handle_handshake(&mut self, _packet: SHandshake, _player_idx: usize) {}
     fn handle_request(&mut self, _packet: SRequest, _player_idx: usize) {}
     fn handle_ping(&mut self, _packet: SPing, _player_idx: usize) {}
+    fn handle_legacy_ping(&mut self, _packet: SLegacyPing, _player_idx: usize) {}
     fn handle_login_start(&mut self, _packet: SLoginStart, _player_idx: usize) {}
+    fn handle_teleport_confirm(&mut self, _packet: STeleportConfirm, _player_idx: usize) {}
     fn handle_chat_message(&mut self, _packet: SChatMessage, _player_idx: usize) {}
+    fn handle_client_status(&mut self, _packet: SClientStatus, _player_idx: usize) {}
     fn handle_client_settings(&mut self, _packet: SClientSettings, _player_idx: usize) {}
+    fn handle_close_window(&mut self, _packet: SCloseWindow, _player_idx: usize) {}
     fn handle_tab_complete(&mut self, _packet: STabComplete, _player_idx: usize) {}
     fn handle_plugin_message(&mut self, _packet: SPluginMessage, _player_idx: usize) {}
     fn handle_keep_alive(&mut self, _packet: SKeepAlive, _player_idx: usize) {}
@@ -25,6 +29,7 @@ pub trait ServerBoundPacketHandler {
     fn handle_animation(&mut self, _packet: SAnimation, _player_idx: usize) {}
     fn handle_player_block_placement(&mut self, _packet: SPlayerBlockPlacemnt, _player_idx: usize) {
     }
+    fn handle_use_item(&mut self, _packet: SUseItem, _player_idx: usize) {}
     fn handle_held_item_change(&mut self, _packet: SHeldItemChange, _player_idx: usize) {}
     fn handle_creative_inventory_action(
         &mut self,
@@ -106,6 +111,20 @@ impl ServerBoundPacket for SPing {
     }
 }
 
+/// Sent by pre-netty clients (and some monitoring tools) in place of a handshake.
+/// The payload that follows the `0xFE` byte varies between versions and is ignored.
+pub struct SLegacyPing;
+
+impl ServerBoundPacket for SLegacyPing {
+    fn decode<T: PacketDecoderExt>(_decoder: &mut T) -> DecodeResult<Self> {
+        Ok(SLegacyPing)
+    }
+
+    fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
+        handler.handle_legacy_ping(*self, player_idx);
+    }
+}
+
 pub struct SLoginStart {
     pub name: String,
 }
@@ -122,15 +141,21 @@ impl ServerBoundPacket for SLoginStart {
     }
 }
 
+/// The longest chat message or command the vanilla client lets players send
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 256;
+
 pub struct SChatMessage {
     pub message: String,
 }
 
 impl ServerBoundPacket for SChatMessage {
     fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
-        Ok(SChatMessage {
-            message: decoder.read_string()?,
-        })
+        let message = decoder.read_string()?;
+        let length = message.chars().count();
+        if length > MAX_CHAT_MESSAGE_LENGTH {
+            return Err(PacketDecodeError::ChatMessageTooLong(length));
+        }
+        Ok(SChatMessage { message })
     }
 
     fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
@@ -204,6 +229,47 @@ impl ServerBoundPacket for SPluginMessage {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientStatusAction {
+    PerformRespawn,
+    RequestStats,
+}
+
+pub struct SClientStatus {
+    pub action: ClientStatusAction,
+}
+
+impl ServerBoundPacket for SClientStatus {
+    fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        let action = match decoder.read_varint()? {
+            0 => ClientStatusAction::PerformRespawn,
+            1 => ClientStatusAction::RequestStats,
+            id => return Err(PacketDecodeError::UnknownClientStatusAction(id)),
+        };
+        Ok(SClientStatus { action })
+    }
+
+    fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
+        handler.handle_client_status(*self, player_idx);
+    }
+}
+
+pub struct STeleportConfirm {
+    pub teleport_id: i32,
+}
+
+impl ServerBoundPacket for STeleportConfirm {
+    fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        Ok(STeleportConfirm {
+            teleport_id: decoder.read_varint()?,
+        })
+    }
+
+    fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
+        handler.handle_teleport_confirm(*self, player_idx);
+    }
+}
+
 pub struct SKeepAlive {
     pub id: i64,
 }
@@ -310,8 +376,9 @@ pub struct SPlayerAbilities {
 
 impl ServerBoundPacket for SPlayerAbilities {
     fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        // Flying is the only flag clients send
         Ok(SPlayerAbilities {
-            is_flying: decoder.read_byte()? != 0,
+            is_flying: decoder.read_byte()? & 0x02 != 0,
         })
     }
 
@@ -347,17 +414,43 @@ impl ServerBoundPacket for SPlayerDigging {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityAction {
+    StartSneaking,
+    StopSneaking,
+    LeaveBed,
+    StartSprinting,
+    StopSprinting,
+    StartJumpWithHorse,
+    StopJumpWithHorse,
+    OpenHorseInventory,
+    StartFlyingWithElytra,
+}
+
 pub struct SEntityAction {
     pub entity_id: i32,
-    pub action_id: i32,
+    pub action: EntityAction,
     pub jump_boost: i32,
 }
 
 impl ServerBoundPacket for SEntityAction {
     fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        let entity_id = decoder.read_varint()?;
+        let action = match decoder.read_varint()? {
+            0 => EntityAction::StartSneaking,
+            1 => EntityAction::StopSneaking,
+            2 => EntityAction::LeaveBed,
+            3 => EntityAction::StartSprinting,
+            4 => EntityAction::StopSprinting,
+            5 => EntityAction::StartJumpWithHorse,
+            6 => EntityAction::StopJumpWithHorse,
+            7 => EntityAction::OpenHorseInventory,
+            8 => EntityAction::StartFlyingWithElytra,
+            id => return Err(PacketDecodeError::UnknownEntityAction(id)),
+        };
         Ok(SEntityAction {
-            entity_id: decoder.read_varint()?,
-            action_id: decoder.read_varint()?,
+            entity_id,
+            action,
             jump_boost: decoder.read_varint()?,
         })
     }
@@ -384,6 +477,7 @@ impl ServerBoundPacket for SAnimation {
 }
 
 pub struct SPlayerBlockPlacemnt {
+    /// 0: Main hand, 1: Off hand
     pub hand: i32,
     pub x: i32,
     pub y: i32,
@@ -422,6 +516,42 @@ impl ServerBoundPacket for SPlayerBlockPlacemnt {
     }
 }
 
+/// Sent when the player uses the item in their hand without targeting a block
+pub struct SUseItem {
+    /// 0: Main hand, 1: Off hand
+    pub hand: i32,
+}
+
+impl ServerBoundPacket for SUseItem {
+    fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        Ok(SUseItem {
+            hand: decoder.read_varint()?,
+        })
+    }
+
+    fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
+        handler.handle_use_item(*self, player_idx);
+    }
+}
+
+/// Sent when the player closes a window, including their own inventory
+pub struct SCloseWindow {
+    /// 0 for the player inventory
+    pub window_id: u8,
+}
+
+impl ServerBoundPacket for SCloseWindow {
+    fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        Ok(SCloseWindow {
+            window_id: decoder.read_unsigned_byte()?,
+        })
+    }
+
+    fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
+        handler.handle_close_window(*self, player_idx);
+    }
+}
+
 pub struct SHeldItemChange {
     pub slot: i16,
 }
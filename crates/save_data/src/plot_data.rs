@@ -11,10 +11,11 @@ use serde_big_array::BigArray;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::Path;
+use std::str::FromStr;
 use std::{fmt, io};
 use thiserror::Error;
 
-pub const VERSION: u32 = 1;
+pub const VERSION: u32 = 2;
 
 #[derive(Error, Debug)]
 pub enum PlotLoadError {
@@ -82,6 +83,28 @@ pub enum Tps {
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct WorldSendRate(pub u32);
 
+/// How the chunk sections of a plot keep their blocks in memory
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageKind {
+    /// A paletted array with an entry for every block
+    #[default]
+    Dense,
+    /// A map of only the non-air blocks, which is smaller for sparse circuits
+    Sparse,
+}
+
+impl FromStr for StorageKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dense" => Ok(StorageKind::Dense),
+            "sparse" => Ok(StorageKind::Sparse),
+            _ => Err(()),
+        }
+    }
+}
+
 impl Default for WorldSendRate {
     fn default() -> Self {
         Self(60)
@@ -103,6 +126,7 @@ pub struct PlotData<const NUM_CHUNK_SECTIONS: usize> {
     pub world_send_rate: WorldSendRate,
     pub chunk_data: Vec<ChunkData<NUM_CHUNK_SECTIONS>>,
     pub pending_ticks: Vec<TickEntry>,
+    pub storage: StorageKind,
 }
 
 impl<const NUM_CHUNK_SECTIONS: usize> PlotData<NUM_CHUNK_SECTIONS> {
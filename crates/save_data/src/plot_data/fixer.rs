@@ -14,6 +14,7 @@ use std::path::Path;
 use tracing::debug;
 
 mod pre_header;
+mod pre_storage;
 mod pre_worldsendrate;
 
 #[derive(Debug)]
@@ -52,6 +53,10 @@ pub fn try_fix<const NUM_SECTIONS: usize>(
             let data = fs::read(&path)?;
             pre_worldsendrate::try_fix(&data)
         }
+        FixInfo::OldVersion { version: 1 } => {
+            let data = fs::read(&path)?;
+            pre_storage::try_fix(&data)
+        }
         _ => None,
     };
 
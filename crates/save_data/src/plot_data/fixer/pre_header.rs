@@ -2,7 +2,7 @@
 //! plot save file. For mchprs versions targetting 1.17.1 and below, we did
 //! not have a file header.
 
-use crate::plot_data::{ChunkData, ChunkSectionData, PlotData, Tps, WorldSendRate};
+use crate::plot_data::{ChunkData, ChunkSectionData, PlotData, StorageKind, Tps, WorldSendRate};
 use mchprs_blocks::block_entities::BlockEntity;
 use mchprs_blocks::BlockPos;
 use mchprs_world::TickEntry;
@@ -51,6 +51,7 @@ pub fn try_fix<const NUM_SECTIONS: usize>(data: &[u8]) -> Option<PlotData<NUM_SE
             })
             .collect(),
         pending_ticks: old_data.pending_ticks,
+        storage: StorageKind::Dense,
     };
     Some(data)
 }
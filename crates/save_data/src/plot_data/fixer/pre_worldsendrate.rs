@@ -1,4 +1,4 @@
-use crate::plot_data::{ChunkData, PlotData, Tps, WorldSendRate};
+use crate::plot_data::{ChunkData, PlotData, StorageKind, Tps, WorldSendRate};
 use mchprs_world::TickEntry;
 use serde::Deserialize;
 
@@ -19,6 +19,7 @@ pub fn try_fix<const NUM_SECTIONS: usize>(data: &[u8]) -> Option<PlotData<NUM_SE
         world_send_rate: WorldSendRate::default(),
         chunk_data: old_data.chunk_data,
         pending_ticks: old_data.pending_ticks,
+        storage: StorageKind::Dense,
     };
     Some(data)
 }
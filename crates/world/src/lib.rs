@@ -11,6 +11,7 @@ pub enum TickPriority {
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct TickEntry {
+    /// Redstone ticks until this entry runs
     pub ticks_left: u32,
     pub tick_priority: TickPriority,
     pub pos: BlockPos,